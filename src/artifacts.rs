@@ -0,0 +1,279 @@
+// Per-run workspaces for `run code` and the files (artifacts) a run leaves in them
+use crate::{humanize, undo};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// Older workspaces are deleted when a new run starts.
+const KEEP_RUNS: usize = 20;
+const MAX_PREVIEWS: usize = 3;
+
+#[derive(Clone)]
+pub struct Artifact {
+    // Relative to the workspace.
+    pub path: PathBuf,
+    pub size: u64,
+    pub kind: &'static str,
+    // Existed before the run and was changed by it.
+    pub modified: bool,
+}
+
+// The workspace and artifacts of the most recent run that produced any.
+static LAST_RUN: Mutex<Option<(PathBuf, Vec<Artifact>)>> = Mutex::new(None);
+// Workspaces from `persistent_dir` that a running kernel still works in.
+static IN_USE: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+type Snapshot = HashMap<PathBuf, (u64, Option<SystemTime>)>;
+
+pub struct Workspace {
+    pub dir: PathBuf,
+    before: Snapshot,
+    // Reused across runs, so kept even when a run leaves nothing new in it.
+    persistent: bool,
+}
+
+fn runs_dir() -> PathBuf {
+    crate::paths::data_dir().join("runs")
+}
+
+// A new directory under the workspaces, e.g. `kernel-ir-<time>` for a kernel's session.
+#[cfg_attr(not(feature = "jupyter"), allow(dead_code))]
+pub fn persistent_dir(prefix: &str) -> io::Result<PathBuf> {
+    let dir = runs_dir().join(format!("{}-{}", prefix, chrono::Local::now().format("%Y%m%d-%H%M%S-%9f")));
+    fs::create_dir_all(&dir)?;
+    if let Ok(mut in_use) = IN_USE.lock() {
+        in_use.push(dir.clone());
+    }
+    Ok(dir)
+}
+
+// Lets `prune` delete a `persistent_dir` again once its kernel has stopped.
+#[cfg_attr(not(feature = "jupyter"), allow(dead_code))]
+pub fn release(dir: &Path) {
+    if let Ok(mut in_use) = IN_USE.lock() {
+        in_use.retain(|d| d != dir);
+    }
+}
+
+// Deletes the least recently modified workspaces, but never the one `show artifacts` and
+// `promote` refer to or one a running kernel works in.
+fn prune() {
+    let Ok(entries) = fs::read_dir(runs_dir()) else {
+        return;
+    };
+    let mut live: Vec<PathBuf> = IN_USE.lock().map(|in_use| in_use.clone()).unwrap_or_default();
+    live.extend(LAST_RUN.lock().ok().and_then(|last| last.as_ref().map(|(dir, _)| dir.clone())));
+    let mut runs: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()) && !live.contains(&e.path()))
+        .map(|e| (e.metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH), e.path()))
+        .collect();
+    runs.sort();
+    let excess = runs.len().saturating_sub(KEEP_RUNS - 1);
+    for (_, run) in &runs[..excess] {
+        let _ = fs::remove_dir_all(run);
+    }
+}
+
+// Regular files under `dir` with their size and modification time. Symlinks are not followed.
+fn snapshot(dir: &Path, root: &Path, files: &mut Snapshot) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let path = entry.path();
+        if meta.is_dir() {
+            snapshot(&path, root, files);
+        } else if meta.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            files.insert(relative, (meta.len(), meta.modified().ok()));
+        }
+    }
+}
+
+fn kind(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "tif" | "tiff" | "ico" => "image",
+        "svg" => "SVG image",
+        "csv" | "tsv" => "table",
+        "json" | "jsonl" | "ndjson" => "JSON",
+        "html" | "htm" => "HTML",
+        "pdf" => "PDF",
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" => "archive",
+        "txt" | "log" | "md" | "xml" | "yaml" | "yml" | "toml" => "text",
+        _ => {
+            // Anything else is text when its start decodes as UTF-8.
+            let mut head = Vec::new();
+            let readable = fs::File::open(path).and_then(|f| f.take(4096).read_to_end(&mut head)).is_ok();
+            match std::str::from_utf8(&head) {
+                Ok(_) if readable => "text",
+                // A multi-byte character cut off at the end of the sample is still text.
+                Err(e) if readable && e.error_len().is_none() => "text",
+                _ => "binary",
+            }
+        }
+    }
+}
+
+impl Workspace {
+    pub fn create() -> io::Result<Workspace> {
+        prune();
+        let id = chrono::Local::now().format("%Y%m%d-%H%M%S-%9f").to_string();
+        let dir = runs_dir().join(id);
+        fs::create_dir_all(&dir)?;
+        let mut before = Snapshot::new();
+        snapshot(&dir, &dir, &mut before);
+        Ok(Workspace { dir, before, persistent: false })
+    }
+
+    // Watches a directory that outlives a single run, such as a kernel's working directory.
+    #[cfg_attr(not(feature = "jupyter"), allow(dead_code))]
+    pub fn reuse(dir: PathBuf) -> io::Result<Workspace> {
+        fs::create_dir_all(&dir)?;
+        let mut before = Snapshot::new();
+        snapshot(&dir, &dir, &mut before);
+        Ok(Workspace { dir, before, persistent: true })
+    }
+
+    // Finds the files the run created or changed. They become the artifacts `promote` works on;
+    // a workspace left empty is removed.
+    pub fn collect(&self) -> Vec<Artifact> {
+        let mut after = Snapshot::new();
+        snapshot(&self.dir, &self.dir, &mut after);
+        let mut artifacts: Vec<Artifact> = after
+            .into_iter()
+            .filter(|(path, state)| self.before.get(path) != Some(state))
+            .map(|(path, (size, _))| Artifact { kind: kind(&self.dir.join(&path)), modified: self.before.contains_key(&path), path, size })
+            .collect();
+        artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+        if artifacts.is_empty() {
+            if !self.persistent {
+                let _ = fs::remove_dir_all(&self.dir);
+            }
+        } else if let Ok(mut last) = LAST_RUN.lock() {
+            *last = Some((self.dir.clone(), artifacts.clone()));
+        }
+        artifacts
+    }
+}
+
+// Lists artifacts with their type and size, previewing the first few images inline.
+pub fn report(dir: &Path, artifacts: &[Artifact]) -> String {
+    let width = artifacts.iter().map(|a| a.path.display().to_string().len()).max().unwrap_or(0);
+    let mut lines = vec![format!("Artifacts in {}:", dir.display())];
+    let mut previews = Vec::new();
+    for artifact in artifacts {
+        let status = if artifact.modified { ", modified" } else { "" };
+        lines.push(format!("  {:<width$}  {}, {}{}", artifact.path.display(), artifact.kind, humanize::size(artifact.size), status, width = width));
+        if artifact.kind == "image" && previews.len() < MAX_PREVIEWS {
+            previews.extend(crate::preview::render_file(&dir.join(&artifact.path)).map(|p| p.trim_end_matches('\n').to_string()));
+        }
+    }
+    lines.extend(previews);
+    lines.push("Type 'promote artifact <name>' or 'promote artifacts' to move them into the current directory.".to_string());
+    lines.join("\n")
+}
+
+pub fn show_last() -> String {
+    match LAST_RUN.lock().ok().as_deref() {
+        Some(Some((dir, artifacts))) if !artifacts.is_empty() => report(dir, artifacts),
+        _ => "No artifacts yet.".to_string(),
+    }
+}
+
+// Moves the named artifact (or all of them) from the last run's workspace into the current
+// directory, keeping subdirectories. Overwritten files can be brought back with `undo`.
+pub fn promote(name: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let mut last = LAST_RUN.lock().map_err(|_| "artifact list lock poisoned")?;
+    let Some((dir, artifacts)) = last.as_mut() else {
+        return Ok("No artifacts to promote.".to_string());
+    };
+    let selected: Vec<usize> = match name {
+        Some(name) => artifacts.iter().position(|a| a.path == Path::new(name) || a.path.file_name().is_some_and(|f| f == name)).into_iter().collect(),
+        None => (0..artifacts.len()).collect(),
+    };
+    if selected.is_empty() {
+        let names: Vec<String> = artifacts.iter().map(|a| a.path.display().to_string()).collect();
+        return Ok(match name {
+            Some(name) => format!("No artifact named '{}'. Artifacts: {}", name, names.join(", ")),
+            None => "No artifacts to promote.".to_string(),
+        });
+    }
+    let cwd = std::env::current_dir()?;
+    let mut transaction = undo::Transaction::begin("promote artifacts")?;
+    let (mut promoted, mut overwritten) = (Vec::new(), Vec::new());
+    for &index in &selected {
+        let artifact = &artifacts[index];
+        let target = cwd.join(&artifact.path);
+        // The replaced entry itself goes into the journal, so a symlink comes back as a symlink.
+        let replaces = target.symlink_metadata().is_ok_and(|m| !m.is_dir());
+        let moved = if replaces { transaction.remove(&target) } else { Ok(()) }.and_then(|_| transaction.rename(&dir.join(&artifact.path), &target));
+        if let Err(e) = moved {
+            // Put back what was already moved before reporting the failure.
+            transaction.rollback()?;
+            return Err(format!("{}: {}", artifact.path.display(), e).into());
+        }
+        if replaces {
+            overwritten.push(artifact.path.display().to_string());
+        }
+        promoted.push(artifact.path.display().to_string());
+    }
+    transaction.commit()?;
+    for index in selected.into_iter().rev() {
+        artifacts.remove(index);
+    }
+    let mut message = format!("Moved {} into {}", promoted.join(", "), cwd.display());
+    if !overwritten.is_empty() {
+        message.push_str(&format!(" (replaced {}; type 'undo' to restore)", overwritten.join(", ")));
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::DataDir;
+    use std::time::Duration;
+
+    fn touch(dir: &Path, age: u64) {
+        fs::create_dir_all(dir).unwrap();
+        let time = SystemTime::now() - Duration::from_secs(age);
+        fs::File::open(dir).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn prunes_the_least_recently_modified_workspaces() {
+        let _data = DataDir::fresh();
+        for i in 0..KEEP_RUNS {
+            touch(&runs_dir().join(format!("2024{:04}", i)), 1000 + i as u64);
+        }
+        // Sorts after the timestamps by name, but is the most recently used.
+        touch(&runs_dir().join("kernel-python3-20240101"), 10);
+        let last = runs_dir().join("20230101");
+        touch(&last, 5000);
+        *LAST_RUN.lock().unwrap() = Some((last.clone(), Vec::new()));
+        let kernel = persistent_dir("kernel-ir").unwrap();
+        touch(&kernel, 6000);
+
+        prune();
+        *LAST_RUN.lock().unwrap() = None;
+        let left = || -> Vec<String> { fs::read_dir(runs_dir()).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect() };
+        assert_eq!(left().len(), KEEP_RUNS + 1);
+        assert!(left().contains(&"kernel-python3-20240101".to_string()));
+        assert!(left().contains(&"20230101".to_string()) && kernel.is_dir());
+        assert!(!left().contains(&format!("2024{:04}", KEEP_RUNS - 1)) && !left().contains(&format!("2024{:04}", KEEP_RUNS - 2)));
+
+        // Once its kernel has stopped, a kernel workspace is pruned like any other.
+        release(&kernel);
+        prune();
+        assert!(!kernel.exists());
+    }
+}
@@ -0,0 +1,231 @@
+// File checksums for `hash`, `verify` and checked downloads
+use crate::search::SearchOptions;
+use sha2::Digest;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+    Blake3,
+}
+
+impl Algorithm {
+    pub fn parse(name: &str) -> Option<Algorithm> {
+        match name.to_lowercase().replace('-', "").as_str() {
+            "sha256" => Some(Algorithm::Sha256),
+            "sha1" => Some(Algorithm::Sha1),
+            "md5" => Some(Algorithm::Md5),
+            "blake3" | "b3" => Some(Algorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Md5 => "md5",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    // Conventional manifest file name, as written by sha256sum and friends.
+    pub fn sums_file(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "SHA256SUMS",
+            Algorithm::Sha1 => "SHA1SUMS",
+            Algorithm::Md5 => "MD5SUMS",
+            Algorithm::Blake3 => "B3SUMS",
+        }
+    }
+
+    // Candidates for a bare hex digest; 64 hex digits could be either SHA-256 or BLAKE3.
+    fn candidates(digest: &str) -> &'static [Algorithm] {
+        match digest.len() {
+            32 => &[Algorithm::Md5],
+            40 => &[Algorithm::Sha1],
+            64 => &[Algorithm::Sha256, Algorithm::Blake3],
+            _ => &[],
+        }
+    }
+
+    fn from_sums_name(name: &str) -> Option<Algorithm> {
+        let name = name.to_uppercase();
+        [Algorithm::Sha256, Algorithm::Sha1, Algorithm::Md5, Algorithm::Blake3]
+            .into_iter()
+            .find(|a| name.starts_with(a.sums_file()) || name.starts_with(&a.name().to_uppercase()))
+    }
+}
+
+fn stream<R: Read>(mut reader: R, mut update: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            return Ok(());
+        }
+        update(&buffer[..n]);
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn hash_reader<R: Read>(reader: R, algorithm: Algorithm) -> io::Result<String> {
+    match algorithm {
+        Algorithm::Sha256 => {
+            let mut hasher = sha2::Sha256::new();
+            stream(reader, |chunk| hasher.update(chunk))?;
+            Ok(hex(&hasher.finalize()))
+        }
+        Algorithm::Sha1 => {
+            let mut hasher = sha1::Sha1::new();
+            stream(reader, |chunk| hasher.update(chunk))?;
+            Ok(hex(&hasher.finalize()))
+        }
+        Algorithm::Md5 => {
+            let mut hasher = md5::Md5::new();
+            stream(reader, |chunk| hasher.update(chunk))?;
+            Ok(hex(&hasher.finalize()))
+        }
+        Algorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            stream(reader, |chunk| {
+                hasher.update(chunk);
+            })?;
+            Ok(hasher.finalize().to_hex().to_string())
+        }
+    }
+}
+
+pub fn hash_file(path: &Path, algorithm: Algorithm) -> io::Result<String> {
+    hash_reader(File::open(path)?, algorithm)
+}
+
+// Normalises a user-supplied digest, accepting an `sha256:` style prefix.
+fn normalize(digest: &str) -> (Option<Algorithm>, String) {
+    let digest = digest.trim();
+    match digest.split_once(':') {
+        Some((name, hex)) if Algorithm::parse(name).is_some() => (Algorithm::parse(name), hex.trim().to_lowercase()),
+        _ => (None, digest.to_lowercase()),
+    }
+}
+
+// Checks `path` against an expected digest, returning the algorithm that matched.
+pub fn matches(path: &Path, expected: &str, algorithm: Option<Algorithm>) -> Result<Option<Algorithm>, Box<dyn Error>> {
+    let (prefixed, expected) = normalize(expected);
+    if !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' is not a hex digest", expected).into());
+    }
+    let candidates = match algorithm.or(prefixed) {
+        Some(algorithm) => vec![algorithm],
+        None => Algorithm::candidates(&expected).to_vec(),
+    };
+    if candidates.is_empty() {
+        return Err(format!("Cannot tell the algorithm of a {}-digit digest", expected.len()).into());
+    }
+    for algorithm in candidates {
+        if hash_file(path, algorithm)? == expected {
+            return Ok(Some(algorithm));
+        }
+    }
+    Ok(None)
+}
+
+// Parses `<digest>  <name>` lines (the `*` binary marker is allowed) from a SUMS manifest.
+fn parse_sums(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (digest, name) = line.split_once(char::is_whitespace)?;
+            let name = name.trim_start();
+            Some((digest.to_lowercase(), name.strip_prefix('*').unwrap_or(name).to_string()))
+        })
+        .collect()
+}
+
+// `verify <file or dir> against <digest or manifest>`.
+pub fn verify(target: &Path, against: &str) -> Result<String, Box<dyn Error>> {
+    let manifest = Path::new(against);
+    if !manifest.is_file() {
+        if !target.is_file() {
+            return Err(format!("'{}' is not a file", target.display()).into());
+        }
+        let (_, digest) = normalize(against);
+        return Ok(match matches(target, against, None)? {
+            Some(algorithm) => format!("OK: {} matches the expected {} digest", target.display(), algorithm.name()),
+            None => format!("MISMATCH: {} does not match {}", target.display(), digest),
+        });
+    }
+    let entries = parse_sums(&fs::read_to_string(manifest)?);
+    let algorithm = manifest.file_name().and_then(|n| Algorithm::from_sums_name(&n.to_string_lossy()));
+    if target.is_dir() {
+        return verify_all(target, &entries, algorithm);
+    }
+    // Match by relative path first, then by file name.
+    let target_name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let target_str = target.to_string_lossy();
+    let entry = entries
+        .iter()
+        .find(|(_, name)| name.trim_start_matches("./") == target_str.trim_start_matches("./"))
+        .or_else(|| entries.iter().find(|(_, name)| Path::new(name).file_name().is_some_and(|n| n.to_string_lossy() == target_name)));
+    let Some((digest, _)) = entry else {
+        return Err(format!("{} has no entry for {}", manifest.display(), target.display()).into());
+    };
+    Ok(match matches(target, digest, algorithm)? {
+        Some(algorithm) => format!("OK: {} ({})", target.display(), algorithm.name()),
+        None => format!("MISMATCH: {}", target.display()),
+    })
+}
+
+fn verify_all(dir: &Path, entries: &[(String, String)], algorithm: Option<Algorithm>) -> Result<String, Box<dyn Error>> {
+    let mut lines = Vec::new();
+    let (mut ok, mut failed, mut missing) = (0, 0, 0);
+    for (digest, name) in entries {
+        let path = dir.join(name);
+        if !path.is_file() {
+            missing += 1;
+            lines.push(format!("MISSING   {}", name));
+            continue;
+        }
+        if matches(&path, digest, algorithm)?.is_some() {
+            ok += 1;
+        } else {
+            failed += 1;
+            lines.push(format!("MISMATCH  {}", name));
+        }
+    }
+    lines.push(format!("{} OK, {} mismatched, {} missing", ok, failed, missing));
+    Ok(lines.join("\n"))
+}
+
+// A sha256sum-compatible manifest of every file under `dir`, with paths relative to it.
+pub fn manifest(dir: &Path, algorithm: Algorithm, skip: Option<&Path>) -> Result<String, Box<dyn Error>> {
+    let opts = SearchOptions { hidden: true, no_ignore: true, ..SearchOptions::default() };
+    let skip = skip.and_then(|p| fs::canonicalize(p).ok());
+    let mut files: Vec<PathBuf> = opts
+        .walker(dir)?
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .map(|e| e.into_path())
+        .filter(|p| skip.is_none() || fs::canonicalize(p).ok() != skip)
+        .collect();
+    files.sort();
+    let mut lines = Vec::new();
+    for path in files {
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        lines.push(format!("{}  {}", hash_file(&path, algorithm)?, relative.display()));
+    }
+    Ok(lines.join("\n"))
+}
@@ -1,20 +1,28 @@
 use std::error::Error;
-use std::fs;
 
 pub fn handle_command(input: &str) -> Result<Option<String>, Box<dyn Error>> {
     let input = input.trim();
-    if input.eq_ignore_ascii_case("list files in current directory") {
-        return list_files();
+    if let Some(rest) = input.strip_prefix("list files in ") {
+        return list_files(rest.trim(), false);
+    } else if let Some(rest) = input.strip_prefix("show tree of ") {
+        return list_files(rest.trim(), true);
     } else if let Some(expr) = input.strip_prefix("calculate ") {
         return calculate(expr);
     } else if input.eq_ignore_ascii_case("plot a sine wave") {
         return plot_sine_wave();
+    } else if let Some(rest) = input.strip_prefix("plot ") {
+        if let Some((file, columns)) = rest.split_once(" columns ") {
+            return plot_data(file.trim(), columns.trim());
+        }
+        return plot_expressions(rest.trim());
     } else if input.eq_ignore_ascii_case("show current directory") {
         return show_current_directory();
     } else if let Some(path) = input.strip_prefix("change directory to ") {
         return change_directory(path.trim());
     } else if let Some(filename) = input.strip_prefix("show file ") {
         return show_file(filename.trim());
+    } else if let Some(file) = input.strip_prefix("show image ") {
+        return show_image(file.trim());
     } else if let Some(rest) = input.strip_prefix("write to file ") {
         if let Some((filename, content)) = rest.split_once(":") {
             return write_file(filename.trim(), content.trim());
@@ -33,6 +41,19 @@ pub fn handle_command(input: &str) -> Result<Option<String>, Box<dyn Error>> {
                 return search_pattern(pattern, target.trim());
             }
         }
+    } else if let Some(rest) = input.strip_prefix("find duplicates in ") {
+        return find_duplicates(rest.trim());
+    } else if let Some(criteria) = input.strip_prefix("find files ") {
+        return find_files(criteria.trim());
+    } else if let Some(query) = input.strip_prefix("find ") {
+        return find_fuzzy(query.trim());
+    } else if let Some(rest) = input.strip_prefix("replace ") {
+        let re = regex::Regex::new(r#"^"(?P<pattern>(?:[^"\\]|\\.)*)"\s+with\s+"(?P<replacement>(?:[^"\\]|\\.)*)"\s+in\s+(?P<rest>.+)$"#)?;
+        if let Some(caps) = re.captures(rest.trim()) {
+            return replace_pattern(&caps["pattern"], &caps["replacement"].replace("\\\"", "\""), &caps["rest"]);
+        }
+    } else if input.eq_ignore_ascii_case("undo") {
+        return crate::undo::undo_last();
     } else if let Some(filename) = input.strip_prefix("summarize file ") {
         return summarize_file(filename.trim());
     } else if let Some(rest) = input.strip_prefix("count ") {
@@ -43,13 +64,24 @@ pub fn handle_command(input: &str) -> Result<Option<String>, Box<dyn Error>> {
         } else if let Some(filename) = rest.strip_prefix("chars in ") {
             return count_file_stats(filename.trim(), "chars");
         }
-    } else if input.eq_ignore_ascii_case("show disk usage") {
-        return show_disk_usage();
+    } else if let Some(rest) = input.strip_prefix("show disk usage") {
+        return show_disk_usage(rest.trim());
     } else if input.eq_ignore_ascii_case("show top processes") {
         return show_top_processes();
     } else if let Some(rest) = input.strip_prefix("download ") {
-        if let Some((url, filename)) = rest.split_once(" to ") {
-            return download_file(url.trim(), filename.trim());
+        if let Some((url, rest)) = rest.split_once(" to ") {
+            return download_file(url.trim(), rest.trim());
+        }
+    } else if let Some(rest) = input.strip_prefix("http ") {
+        return http_request(rest.trim());
+    } else if let Some(rest) = input.strip_prefix("hash all files in ") {
+        return hash_directory(rest.trim());
+    } else if let Some(rest) = input.strip_prefix("hash ") {
+        let (file, algorithm) = rest.trim().rsplit_once(" with ").unwrap_or((rest.trim(), "sha256"));
+        return hash_file(file.trim(), algorithm.trim());
+    } else if let Some(rest) = input.strip_prefix("verify ") {
+        if let Some((target, against)) = rest.split_once(" against ") {
+            return verify_checksum(target.trim(), against.trim());
         }
     } else if let Some(rest) = input.strip_prefix("extract ") {
         if let Some((archive, dir)) = rest.split_once(" to ") {
@@ -64,17 +96,40 @@ pub fn handle_command(input: &str) -> Result<Option<String>, Box<dyn Error>> {
     } else if let Some(city) = input.strip_prefix("show weather in ") {
         return show_weather(city.trim());
     } else if let Some(cmd) = input.strip_prefix("explain ") {
-        if let Some(shell_cmd) = cmd.trim().strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
-            return explain_shell_command(shell_cmd.trim());
-        }
-    } else if let Some(instr) = input.strip_prefix("run ") {
-        if let Some(nl_cmd) = instr.trim().strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
-            return run_shell_command(nl_cmd.trim());
-        }
+        let cmd = cmd.trim();
+        let shell_cmd = cmd.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(cmd);
+        return explain_shell_command(shell_cmd.trim());
+    } else if input.trim() == "start shell session" {
+        return start_shell_session();
+    } else if input.trim() == "end shell session" {
+        return end_shell_session();
+    } else if input.trim() == "show last result" {
+        return Ok(Some(crate::usage::last_result().unwrap_or_else(|| "No command has been run yet.".to_string())));
+    } else if input.trim() == "show last output" {
+        return show_last_output();
+    } else if input.trim() == "list kernels" {
+        return Ok(Some(crate::jupyter::list()));
+    } else if input.trim() == "show artifacts" {
+        return Ok(Some(crate::artifacts::show_last()));
+    } else if input.trim() == "promote artifacts" {
+        return Ok(Some(crate::artifacts::promote(None)?));
+    } else if let Some(name) = input.strip_prefix("promote artifact ") {
+        return Ok(Some(crate::artifacts::promote(Some(name.trim()))?));
+    } else if let Some(rest) = input.strip_prefix("run file ") {
+        let rest = rest.trim();
+        let (path, options) = match rest.strip_prefix('"').and_then(|r| r.split_once('"')) {
+            Some((path, options)) => (path, options),
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+        return run_file(path, options);
     } else if let Some(rest) = input.strip_prefix("run code ") {
         if let Some((lang, code)) = rest.split_once(":") {
             return run_code_snippet(lang.trim(), code.trim());
         }
+    } else if let Some(instr) = input.strip_prefix("run ") {
+        if let Some((nl_cmd, opts)) = instr.trim().strip_prefix('"').and_then(|s| s.rsplit_once('"')) {
+            return run_shell_command(nl_cmd.trim(), opts);
+        }
     } else if let Some(rest) = input.strip_prefix("diff ") {
         let mut parts = rest.split_whitespace();
         if let (Some(file1), Some(file2)) = (parts.next(), parts.next()) {
@@ -113,7 +168,7 @@ pub fn handle_command(input: &str) -> Result<Option<String>, Box<dyn Error>> {
             return speak_text(text.trim());
         }
     } else if let Some(rest) = input.strip_prefix("copy ") {
-        if let Some((text, to_clipboard)) = rest.trim().split_once(" to clipboard") {
+        if let Some((text, _)) = rest.trim().split_once(" to clipboard") {
             if let Some(text) = text.trim().strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
                 return copy_to_clipboard(text.trim());
             }
@@ -121,7 +176,11 @@ pub fn handle_command(input: &str) -> Result<Option<String>, Box<dyn Error>> {
     } else if input.trim() == "paste from clipboard" {
         return paste_from_clipboard();
     } else if let Some(target) = input.strip_prefix("watch ") {
-        return watch_file_or_dir(target.trim());
+        let re = regex::Regex::new(r#"^(?P<target>.+?)\s+(?:and|then)\s+run\s+"(?P<cmd>.+)"$"#)?;
+        return match re.captures(target.trim()) {
+            Some(caps) => watch_file_or_dir(caps["target"].trim(), Some(caps["cmd"].trim())),
+            None => watch_file_or_dir(target.trim(), None),
+        };
     } else if let Some(rest) = input.strip_prefix("schedule ") {
         if let Some((cmd, at_time)) = rest.trim().strip_prefix("\"").and_then(|s| s.split_once("\" at ")) {
             return schedule_command(cmd.trim(), at_time.trim());
@@ -130,18 +189,37 @@ pub fn handle_command(input: &str) -> Result<Option<String>, Box<dyn Error>> {
     Ok(None)
 }
 
-fn list_files() -> Result<Option<String>, Box<dyn Error>> {
-    let entries = fs::read_dir(".")?;
-    let mut files = Vec::new();
-    for entry in entries {
-        let entry = entry?;
-        let file_name = entry.file_name();
-        files.push(file_name.to_string_lossy().to_string());
+// `<dir> [options]`; the directory may be quoted, and `current directory` means `.`.
+fn list_files(input: &str, as_tree: bool) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::listing::{self, ListOptions};
+    let input = input.replacen("current directory", ".", 1);
+    let re = regex::Regex::new(r#"^(?:"(?P<quoted>[^"]+)"|(?P<dir>\S+))(?P<opts>.*)$"#)?;
+    let Some(caps) = re.captures(&input) else {
+        return Ok(Some("Usage: list files in <dir> [sorted by name|size|time] [reversed] [including hidden] [names only]".to_string()));
+    };
+    let dir = std::path::Path::new(caps.name("quoted").or(caps.name("dir")).map_or(".", |m| m.as_str()));
+    if !dir.is_dir() {
+        return Ok(Some(format!("'{}' is not a directory", dir.display())));
     }
-    Ok(Some(files.join("\n")))
+    let opts = match ListOptions::parse(&caps["opts"]) {
+        Ok(opts) => opts,
+        Err(e) => return Ok(Some(e)),
+    };
+    let output = if as_tree { listing::tree(dir, &opts)? } else { listing::list(dir, &opts)? };
+    Ok(Some(output))
 }
 
 fn calculate(expr: &str) -> Result<Option<String>, Box<dyn Error>> {
+    match crate::units::evaluate(expr) {
+        Ok(Some(result)) => return Ok(Some(result)),
+        Ok(None) => {}
+        Err(e) => return Ok(Some(format!("Error: {}", e))),
+    }
+    match crate::numeric::evaluate(expr) {
+        Ok(Some(result)) => return Ok(Some(result)),
+        Ok(None) => {}
+        Err(e) => return Ok(Some(format!("Error: {}", e))),
+    }
     match meval::eval_str(expr) {
         Ok(result) => Ok(Some(result.to_string())),
         Err(e) => Ok(Some(format!("Error: {}", e))),
@@ -149,26 +227,83 @@ fn calculate(expr: &str) -> Result<Option<String>, Box<dyn Error>> {
 }
 
 fn plot_sine_wave() -> Result<Option<String>, Box<dyn Error>> {
-    use plotters::prelude::{BitMapBackend, ChartBuilder, IntoDrawingArea, LineSeries, WHITE, RED};
-    let path = "sine_wave.png";
-    let root = BitMapBackend::new(path, (640, 480)).into_drawing_area();
-    root.fill(&WHITE)?;
-    let mut chart = ChartBuilder::on(&root)
-        .caption("Sine Wave", ("sans-serif", 40))
-        .margin(10)
-        .x_label_area_size(30)
-        .y_label_area_size(30)
-        .build_cartesian_2d(0f32..(2.0 * std::f32::consts::PI), -1.2f32..1.2f32)?;
-    chart.configure_mesh().draw()?;
-    chart.draw_series(LineSeries::new(
-        (0..1000).map(|x| {
-            let x = x as f32 * 2.0 * std::f32::consts::PI / 1000.0;
-            (x, x.sin())
-        }),
-        &RED,
-    ))?;
-    root.present()?;
-    Ok(Some(format!("Plot saved to {}", path)))
+    use crate::plot::{self, PlotSpec};
+    let range = (0.0, 2.0 * std::f64::consts::PI);
+    let mut spec = PlotSpec::new("Sine Wave");
+    spec.x_range = Some(range);
+    spec.output = "sine_wave.png".into();
+    spec.series.push(plot::sample("sin(x)", range.0, range.1, false)?);
+    plot::render(&spec)?;
+    Ok(Some(with_preview(format!("Plot saved to {}", spec.output.display()), plot::preview(&spec))))
+}
+
+fn plot_expressions(args: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::plot::{self, PlotSpec};
+    use regex::Regex;
+    let re = Regex::new(r"^(?P<exprs>.+?)\s+from\s+(?P<from>\S+)\s+to\s+(?P<to>\S+)(?P<opts>.*)$")?;
+    let Some(caps) = re.captures(args) else {
+        return Ok(Some(
+            "Usage: plot <expr>[, <expr>...] from <a> to <b> [log x|y|xy] [xlabel \"...\"] [ylabel \"...\"] [save to <file.png|file.svg>]"
+                .to_string(),
+        ));
+    };
+    let from = plot::number(&caps["from"])?;
+    let to = plot::number(&caps["to"])?;
+    if from >= to {
+        return Ok(Some("The plot range must go from a smaller to a larger value.".to_string()));
+    }
+    let exprs = plot::split_expressions(&caps["exprs"]);
+    let mut spec = PlotSpec::new(&exprs.join(", "));
+    spec.x_range = Some((from, to));
+    if let Err(e) = spec.apply_options(&caps["opts"]) {
+        return Ok(Some(e));
+    }
+    for expr in &exprs {
+        spec.series.push(plot::sample(expr, from, to, spec.log_x)?);
+    }
+    plot::render(&spec)?;
+    Ok(Some(with_preview(format!("Plot saved to {}", spec.output.display()), plot::preview(&spec))))
+}
+
+fn plot_data(file: &str, args: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::plot::{self, ChartKind, PlotSpec};
+    use regex::Regex;
+    let re = Regex::new(r"^(?P<columns>\S+)(?:\s+as\s+(?P<kind>\w+))?(?P<opts>.*)$")?;
+    let Some(caps) = re.captures(args) else {
+        return Ok(Some("Usage: plot <file.csv|file.json> columns <x>,<y>[,<y>...] [as line|scatter|bar|histogram] [options]".to_string()));
+    };
+    let path = std::path::Path::new(file);
+    let mut spec = PlotSpec::new(&path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default());
+    if let Some(kind) = caps.name("kind") {
+        match ChartKind::parse(kind.as_str()) {
+            Some(kind) => spec.kind = kind,
+            None => return Ok(Some(format!("Unknown chart type '{}'. Use line, scatter, bar or histogram.", kind.as_str()))),
+        }
+    }
+    if let Err(e) = spec.apply_options(&caps["opts"]) {
+        return Ok(Some(e));
+    }
+    let table = crate::dataset::load(path)?;
+    let columns: Vec<&str> = caps["columns"].split(',').map(str::trim).filter(|c| !c.is_empty()).collect();
+    let skipped = match plot::add_table_series(&mut spec, &table, &columns) {
+        Ok(skipped) => skipped,
+        Err(e) => return Ok(Some(e)),
+    };
+    plot::render(&spec)?;
+    let message = if skipped > 0 {
+        format!("Plot saved to {} ({} row(s) without usable values skipped)", spec.output.display(), skipped)
+    } else {
+        format!("Plot saved to {}", spec.output.display())
+    };
+    Ok(Some(with_preview(message, plot::preview(&spec))))
+}
+
+// Appends an inline image preview, when the terminal supports one, below a command's message.
+fn with_preview(message: String, preview: Option<String>) -> String {
+    match preview {
+        Some(preview) => format!("{}\n{}", message, preview.trim_end_matches('\n')),
+        None => message,
+    }
 }
 
 fn show_current_directory() -> Result<Option<String>, Box<dyn Error>> {
@@ -192,8 +327,18 @@ fn write_file(filename: &str, content: &str) -> Result<Option<String>, Box<dyn E
 }
 
 fn delete_file(filename: &str) -> Result<Option<String>, Box<dyn Error>> {
-    std::fs::remove_file(filename)?;
-    Ok(Some(format!("Deleted file {}", filename)))
+    use crate::{prompt, undo};
+    // The entry itself is deleted, so a symlink (even a dangling one) counts as a file.
+    if !std::fs::symlink_metadata(filename).is_ok_and(|m| !m.is_dir()) {
+        return Ok(Some(format!("'{}' is not a file", filename)));
+    }
+    if !prompt::confirm(&format!("Delete {}?", filename)) {
+        return Ok(Some("Delete cancelled.".to_string()));
+    }
+    let mut transaction = undo::Transaction::begin(&format!("delete file {}", filename))?;
+    transaction.remove(std::path::Path::new(filename))?;
+    transaction.commit()?;
+    Ok(Some(format!("Deleted file {}. Type 'undo' to restore it.", filename)))
 }
 
 fn show_system_info() -> Result<Option<String>, Box<dyn Error>> {
@@ -210,14 +355,44 @@ fn show_date_time() -> Result<Option<String>, Box<dyn Error>> {
 
 fn help() -> Result<Option<String>, Box<dyn Error>> {
     let help_text = r#"Available commands:
-- list files in current directory
+- list files in <dir> [sorted by name|size|time] [reversed] [including hidden] [names only]
+- show tree of <dir> [depth <n>] [including hidden]
 - show current directory
 - change directory to <path>
 - show file <filename>
 - write to file <filename>: <content>
 - delete file <filename>
 - calculate <expression>
+- calculate <quantity> to <unit>
 - plot a sine wave
+- plot <expr>[, <expr>...] from <a> to <b> [log x|log y|log xy] [xlabel "<text>"] [ylabel "<text>"] [save to <file>]
+- plot <file> columns <x>,<y>[,<y>...] [as line|scatter|bar|histogram] [bins <n>]
+- show image <file>
+- replace "<regex>" with "<replacement>" in <file or directory> [options]
+- undo
+- find <fuzzy query> [in <dir>]
+- find files named <glob> | larger than <size> | smaller than <size> | modified in the last <n> minutes/hours/days/weeks [in <dir>]
+- find duplicates in <dir> [including hidden] [no ignore] [and hardlink them | and delete them | and move them to <dir>]
+- hash <file> [with sha256|sha1|md5|blake3]
+- hash all files in <dir> [with <algorithm>] [to <manifest>]
+- verify <file or dir> against <digest or manifest>
+- download <url> to <filename> [options]
+- http <METHOD> <url> [options]
+- http replay <name> [from <file>]
+- http saved [in <file>]
+- show weather in <city> [in fahrenheit|celsius]
+- explain "<shell command>"
+- run "<instruction>" [in <dir>] [with NAME=value ...] [timeout 30s] [max output 1MB]
+- show last output
+- show last result
+- start shell session
+- end shell session
+- run code <python|javascript|bash|rust|c|cpp|go>: <code>
+- run file <path> [with args <args>] [stdin from <file>|last output] [with NAME=value ...] [in <dir>]
+- show artifacts
+- promote artifact <name>
+- promote artifacts
+- list kernels
 - show system info
 - show date and time
 - help
@@ -225,42 +400,142 @@ fn help() -> Result<Option<String>, Box<dyn Error>> {
     Ok(Some(help_text.to_string()))
 }
 
-fn search_pattern(pattern: &str, target: &str) -> Result<Option<String>, Box<dyn Error>> {
-    use regex::Regex;
-    use std::fs;
+fn search_pattern(pattern: &str, rest: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::search;
+    let (target, opts) = match search::parse_target(rest) {
+        Ok(parsed) => parsed,
+        Err(e) => return Ok(Some(e)),
+    };
+    let matcher = match opts.regex(pattern) {
+        Ok(matcher) => matcher,
+        Err(e) => return Ok(Some(format!("Invalid pattern: {}", e))),
+    };
+    let results = search::search(&target, &matcher, &opts)?;
+    Ok(Some(search::format(&results)))
+}
+
+// `<dir> [including hidden] [no ignore] [and hardlink|delete|move them [to <dir>]]`
+fn find_duplicates(input: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::{dupes, humanize, prompt, search, undo};
     use std::path::Path;
-    let re = Regex::new(pattern)?;
-    let mut results = Vec::new();
-    fn search_file(path: &Path, re: &Regex, results: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
-        let content = std::fs::read_to_string(path)?;
-        for (i, line) in content.lines().enumerate() {
-            if re.is_match(line) {
-                results.push(format!("{}:{}: {}", path.display(), i + 1, line));
-            }
+    let re = regex::Regex::new(
+        r#"^(?:"(?P<quoted>[^"]+)"|(?P<dir>\S+))(?P<opts>(?:\s+(?:including\s+hidden|no\s+ignore))*)(?:\s+and\s+(?P<action>hardlink|delete|move)(?:\s+them)?(?:\s+to\s+(?P<to>\S+))?)?$"#,
+    )?;
+    let Some(caps) = re.captures(input) else {
+        return Ok(Some("Usage: find duplicates in <dir> [including hidden] [no ignore] [and hardlink|delete them | and move them to <dir>]".to_string()));
+    };
+    let opts = search::SearchOptions::parse(&caps["opts"])?;
+    let root = Path::new(caps.name("quoted").or(caps.name("dir")).map_or(".", |m| m.as_str()));
+    if !root.is_dir() {
+        return Ok(Some(format!("'{}' is not a directory", root.display())));
+    }
+    let sets = dupes::find(root, &opts)?;
+    let report = dupes::format(&sets);
+    let Some(action) = caps.name("action").map(|m| m.as_str()) else {
+        return Ok(Some(report));
+    };
+    if sets.is_empty() {
+        return Ok(Some(report));
+    }
+    let destination = caps.name("to").map(|m| Path::new(m.as_str()));
+    if action == "move" && destination.is_none() {
+        return Ok(Some("Say where to move duplicates: find duplicates in <dir> and move them to <dir>".to_string()));
+    }
+    let target = |duplicate: &Path, to: &Path| to.join(duplicate.strip_prefix(root).unwrap_or(duplicate));
+    if let Some(to) = destination {
+        // Moving onto an existing file would lose it with no way to undo.
+        let taken: Vec<String> = sets
+            .iter()
+            .flat_map(|s| &s.paths[1..])
+            .map(|d| target(d, to))
+            .filter(|t| t.symlink_metadata().is_ok())
+            .map(|t| t.display().to_string())
+            .collect();
+        if !taken.is_empty() {
+            return Ok(Some(format!("{}\nNot moving anything: already exists in {}: {}", report, to.display(), taken.join(", "))));
         }
-        Ok(())
-    }
-    let path = Path::new(target);
-    if path.is_file() {
-        search_file(path, &re, &mut results)?;
-    } else if path.is_dir() {
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                search_file(&path, &re, &mut results)?;
+    }
+    println!("{}", report);
+    let redundant: usize = sets.iter().map(|s| s.paths.len() - 1).sum();
+    let question = match destination {
+        Some(to) => format!("Move {} redundant file(s) to {}?", redundant, to.display()),
+        None => format!("{} {} redundant file(s), keeping the first of each set?", if action == "delete" { "Delete" } else { "Hardlink" }, redundant),
+    };
+    if !prompt::confirm(&question) {
+        return Ok(Some("Cancelled; no files changed.".to_string()));
+    }
+    let mut transaction = undo::Transaction::begin(&format!("{} duplicates in {}", action, root.display()))?;
+    for set in &sets {
+        let keep = &set.paths[0];
+        for duplicate in &set.paths[1..] {
+            let result = match (action, destination) {
+                ("delete", _) => transaction.remove(duplicate),
+                ("hardlink", _) => transaction.remove(duplicate).and_then(|_| std::fs::hard_link(keep, duplicate)),
+                (_, Some(to)) => transaction.rename(duplicate, &target(duplicate, to)),
+                _ => unreachable!(),
+            };
+            if let Err(e) = result {
+                // Put back whatever was already changed before reporting the failure.
+                transaction.rollback()?;
+                return Err(format!("{}: {}", duplicate.display(), e).into());
             }
         }
-    } else {
-        return Ok(Some(format!("Target '{}' not found", target)));
     }
-    if results.is_empty() {
-        Ok(Some("No matches found.".to_string()))
-    } else {
-        Ok(Some(results.join("\n")))
+    transaction.commit()?;
+    let wasted: u64 = sets.iter().map(dupes::DuplicateSet::wasted).sum();
+    let done = match action {
+        "delete" => "Deleted",
+        "hardlink" => "Hardlinked",
+        _ => "Moved",
+    };
+    Ok(Some(format!("{} {} file(s), reclaiming {}. Type 'undo' to revert.", done, redundant, humanize::size(wasted))))
+}
+
+fn find_files(criteria: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::find::{self, Criteria};
+    match Criteria::parse(criteria) {
+        Ok(criteria) => Ok(Some(find::format(&criteria.find()?))),
+        Err(e) => Ok(Some(e)),
     }
 }
 
+// `find <query> [in <dir>]`
+fn find_fuzzy(input: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let (query, root) = match input.rsplit_once(" in ") {
+        Some((query, dir)) if std::path::Path::new(dir.trim()).is_dir() => (query.trim(), dir.trim()),
+        _ => (input, "."),
+    };
+    if query.is_empty() {
+        return Ok(Some("Usage: find <fuzzy query> [in <dir>]".to_string()));
+    }
+    Ok(Some(crate::find::format(&crate::find::fuzzy(std::path::Path::new(root), query)?)))
+}
+
+fn replace_pattern(pattern: &str, replacement: &str, rest: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::{prompt, replace, search};
+    let (target, opts) = match search::parse_target(rest) {
+        Ok(parsed) => parsed,
+        Err(e) => return Ok(Some(e)),
+    };
+    let matcher = match opts.regex(pattern) {
+        Ok(matcher) => matcher,
+        Err(e) => return Ok(Some(format!("Invalid pattern: {}", e))),
+    };
+    let edits = replace::plan(&target, &matcher, replacement, &opts)?;
+    if edits.is_empty() {
+        return Ok(Some("No matches found; nothing to replace.".to_string()));
+    }
+    let count: usize = edits.iter().map(|e| e.count).sum();
+    for edit in &edits {
+        print!("{}", edit.diff());
+    }
+    if !prompt::confirm(&format!("Replace {} occurrence(s) in {} file(s)?", count, edits.len())) {
+        return Ok(Some("Replace cancelled; no files changed.".to_string()));
+    }
+    replace::apply(&edits, &format!("replace \"{}\" with \"{}\" in {}", pattern, replacement, target.display()))?;
+    Ok(Some(format!("Replaced {} occurrence(s) in {} file(s). Type 'undo' to revert.", count, edits.len())))
+}
+
 fn summarize_file(filename: &str) -> Result<Option<String>, Box<dyn Error>> {
     let contents = std::fs::read_to_string(filename)?;
     let summary: Vec<_> = contents
@@ -286,31 +561,23 @@ fn count_file_stats(filename: &str, stat: &str) -> Result<Option<String>, Box<dy
     Ok(Some(format!("{}: {}", stat, result)))
 }
 
-fn show_disk_usage() -> Result<Option<String>, Box<dyn Error>> {
-    use std::fs;
-    use std::path::Path;
-    fn dir_size(path: &Path) -> u64 {
-        if path.is_file() {
-            fs::metadata(path).map(|m| m.len()).unwrap_or(0)
-        } else if path.is_dir() {
-            fs::read_dir(path)
-                .map(|entries| {
-                    entries.filter_map(|e| e.ok())
-                        .map(|e| dir_size(&e.path()))
-                        .sum()
-                })
-                .unwrap_or(0)
-        } else {
-            0
-        }
+// `[of <dir>] [depth <n>] [top <n>]`
+fn show_disk_usage(options: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let re = regex::Regex::new(r#"^(?:(?:of|for|in)\s+(?:"(?P<quoted>[^"]+)"|(?P<dir>\S+)))?\s*(?:depth\s+(?P<depth>\d+))?\s*(?:top\s+(?P<top>\d+))?$"#)?;
+    let Some(caps) = re.captures(options) else {
+        return Ok(Some("Usage: show disk usage [of <dir>] [depth <n>] [top <n>]".to_string()));
+    };
+    let dir = caps.name("quoted").or(caps.name("dir")).map_or(".", |m| m.as_str());
+    if !std::path::Path::new(dir).is_dir() {
+        return Ok(Some(format!("'{}' is not a directory", dir)));
     }
-    let cwd = std::env::current_dir()?;
-    let size = dir_size(&cwd);
-    Ok(Some(format!("Disk usage for {}: {:.2} MB", cwd.display(), size as f64 / 1_048_576.0)))
+    let depth = caps.name("depth").and_then(|d| d.as_str().parse().ok()).unwrap_or(1);
+    let top = caps.name("top").and_then(|t| t.as_str().parse().ok()).unwrap_or(10);
+    Ok(Some(crate::du::report(std::path::Path::new(dir), depth, top)?))
 }
 
 fn show_top_processes() -> Result<Option<String>, Box<dyn Error>> {
-    use sysinfo::{System, Process};
+    use sysinfo::System;
     let mut sys = System::new_all();
     sys.refresh_all();
     let mut processes: Vec<_> = sys.processes().values().collect();
@@ -321,11 +588,109 @@ fn show_top_processes() -> Result<Option<String>, Box<dyn Error>> {
     Ok(Some(format!("Top processes:\n{}", top.join("\n"))))
 }
 
-fn download_file(url: &str, filename: &str) -> Result<Option<String>, Box<dyn Error>> {
-    let resp = reqwest::blocking::get(url)?;
-    let bytes = resp.bytes()?;
-    std::fs::write(filename, &bytes)?;
-    Ok(Some(format!("Downloaded {} to {}", url, filename)))
+// `<filename> [options]`; the filename may be quoted.
+fn download_file(url: &str, rest: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::download::{self, DownloadOptions};
+    let re = regex::Regex::new(r#"^(?:"(?P<quoted>[^"]+)"|(?P<file>\S+))(?P<opts>.*)$"#)?;
+    let Some(caps) = re.captures(rest) else {
+        return Ok(Some("Usage: download <url> to <filename> [options]".to_string()));
+    };
+    let filename = caps.name("quoted").or(caps.name("file")).map_or("", |m| m.as_str());
+    let opts = match DownloadOptions::parse(&caps["opts"]) {
+        Ok(opts) => opts,
+        Err(e) => return Ok(Some(e)),
+    };
+    match download::download(url, std::path::Path::new(filename), &opts) {
+        Ok(summary) => {
+            let verified = summary.verified.map(|a| format!(", {} verified", a.name())).unwrap_or_default();
+            Ok(Some(format!(
+                "Downloaded {} to {} ({} in {:.1}s{})",
+                url,
+                filename,
+                crate::humanize::size(summary.bytes),
+                summary.elapsed.as_secs_f64(),
+                verified
+            )))
+        }
+        Err(e) => Ok(Some(format!("Download of {} failed: {}", url, e))),
+    }
+}
+
+// `http <METHOD> <url> [options]`, `http replay <name> [from <file>]` and `http saved [in <file>]`.
+fn http_request(input: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::http::{self, Invocation};
+    let collection_arg = |rest: &str, keyword: &str| -> Option<std::path::PathBuf> {
+        rest.trim().strip_prefix(keyword).map(|path| std::path::PathBuf::from(path.trim()))
+    };
+    if let Some(rest) = input.strip_prefix("replay ") {
+        let (name, collection) = match rest.split_once(" from ") {
+            Some((name, file)) => (name.trim(), std::path::PathBuf::from(file.trim())),
+            None => (rest.trim(), http::default_collection()),
+        };
+        let request = match http::load(&collection, name) {
+            Ok(request) => request,
+            Err(e) => return Ok(Some(e.to_string())),
+        };
+        return Ok(Some(format!("{} {}\n{}", request.method, request.url, http::send(&request)?)));
+    }
+    if let Some(rest) = input.strip_prefix("saved") {
+        let collection = collection_arg(rest, "in").unwrap_or_else(http::default_collection);
+        return Ok(Some(http::list(&collection)?));
+    }
+    let invocation = match Invocation::parse(input) {
+        Ok(invocation) => invocation,
+        Err(e) => return Ok(Some(format!("{}\nUsage: http <METHOD> <url> [header \"K: V\"] [query k=v] [json '<json>' | body \"<text>\" | body from <file>] [bearer <token> | basic <user:pass>] [save as <name> [in <file>]]", e))),
+    };
+    let mut output = http::send(&invocation.request)?;
+    if let Some(name) = &invocation.save_as {
+        let collection = invocation.collection.clone().unwrap_or_else(http::default_collection);
+        http::save(&collection, name, &invocation.request)?;
+        output.push_str(&format!("\n\nSaved as '{}' in {}", name, collection.display()));
+    }
+    Ok(Some(output))
+}
+
+fn hash_file(file: &str, algorithm: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::checksum::{self, Algorithm};
+    let Some(algorithm) = Algorithm::parse(algorithm) else {
+        return Ok(Some(format!("Unknown algorithm '{}'; use sha256, sha1, md5 or blake3", algorithm)));
+    };
+    let digest = checksum::hash_file(std::path::Path::new(file), algorithm)?;
+    Ok(Some(format!("{}  {}", digest, file)))
+}
+
+// `<dir> [with <algorithm>] [to <manifest>]`
+fn hash_directory(input: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::checksum::{self, Algorithm};
+    use std::path::Path;
+    let re = regex::Regex::new(r"^(?P<dir>.+?)(?:\s+with\s+(?P<algo>[\w-]+))?(?:\s+to\s+(?P<out>\S+))?$")?;
+    let Some(caps) = re.captures(input) else {
+        return Ok(Some("Usage: hash all files in <dir> [with sha256|sha1|md5|blake3] [to <manifest>]".to_string()));
+    };
+    let dir = Path::new(&caps["dir"]);
+    if !dir.is_dir() {
+        return Ok(Some(format!("'{}' is not a directory", dir.display())));
+    }
+    let name = caps.name("algo").map_or("sha256", |m| m.as_str());
+    let Some(algorithm) = Algorithm::parse(name) else {
+        return Ok(Some(format!("Unknown algorithm '{}'; use sha256, sha1, md5 or blake3", name)));
+    };
+    let out = caps.name("out").map(|m| Path::new(m.as_str()));
+    let manifest = checksum::manifest(dir, algorithm, out)?;
+    match out {
+        Some(out) => {
+            std::fs::write(out, format!("{}\n", manifest))?;
+            Ok(Some(format!("Wrote {} {} digest(s) to {}", manifest.lines().count(), algorithm.name(), out.display())))
+        }
+        None => Ok(Some(manifest)),
+    }
+}
+
+fn verify_checksum(target: &str, against: &str) -> Result<Option<String>, Box<dyn Error>> {
+    match crate::checksum::verify(std::path::Path::new(target), against) {
+        Ok(report) => Ok(Some(report)),
+        Err(e) => Ok(Some(e.to_string())),
+    }
 }
 
 fn extract_archive(archive: &str, dir: &str) -> Result<Option<String>, Box<dyn Error>> {
@@ -348,13 +713,13 @@ fn extract_archive(archive: &str, dir: &str) -> Result<Option<String>, Box<dyn E
                 std::io::copy(&mut file, &mut outfile)?;
             }
         }
-        Ok(Some(format!("Extracted {} to {}", archive, dir)))
+        Ok(Some(format!("Extracted {} to {}", path.display(), dir)))
     } else if archive.ends_with(".tar.gz") || archive.ends_with(".tgz") {
         let file = File::open(path)?;
         let decompressor = flate2::read::GzDecoder::new(file);
         let mut archive = tar::Archive::new(decompressor);
         archive.unpack(dir)?;
-        Ok(Some(format!("Extracted {} to {}", archive, dir)))
+        Ok(Some(format!("Extracted {} to {}", path.display(), dir)))
     } else {
         Ok(Some("Unsupported archive format. Only .zip and .tar.gz/.tgz supported.".to_string()))
     }
@@ -371,7 +736,7 @@ fn generate_password(length: usize) -> Result<Option<String>, Box<dyn Error>> {
 }
 
 fn show_calendar() -> Result<Option<String>, Box<dyn Error>> {
-    use chrono::{Datelike, Local, Weekday};
+    use chrono::{Datelike, Local};
     let now = Local::now();
     let year = now.year();
     let month = now.month();
@@ -383,10 +748,10 @@ fn show_calendar() -> Result<Option<String>, Box<dyn Error>> {
     };
     let mut cal = format!("{:^20}\nSu Mo Tu We Th Fr Sa\n", now.format("%B %Y"));
     let mut day = 1;
-    let mut week = vec!["  "; 7];
+    let mut week = vec!["  ".to_string(); 7];
     let first_weekday = first.weekday().num_days_from_sunday() as usize;
-    for i in first_weekday..7 {
-        week[i] = format!("{:2}", day);
+    for cell in week.iter_mut().skip(first_weekday) {
+        *cell = format!("{:2}", day);
         day += 1;
     }
     cal.push_str(&week.join(" "));
@@ -407,67 +772,126 @@ fn show_calendar() -> Result<Option<String>, Box<dyn Error>> {
     Ok(Some(cal))
 }
 
-fn show_weather(city: &str) -> Result<Option<String>, Box<dyn Error>> {
-    let url = format!("https://wttr.in/{}?format=3", city.replace(' ', "+"));
-    let resp = reqwest::blocking::get(&url)?.text()?;
-    Ok(Some(resp))
+// `<city> [in fahrenheit|celsius | imperial|metric]`; `TERMINALAI_UNITS=imperial` changes the default.
+fn show_weather(input: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::weather::{self, Units};
+    let re = regex::Regex::new(r"(?i)^(?P<city>.+?)(?:\s+(?:in\s+)?(?P<units>fahrenheit|celsius|imperial|metric))?$")?;
+    let Some(caps) = re.captures(input) else {
+        return Ok(Some("Usage: show weather in <city> [in fahrenheit|celsius]".to_string()));
+    };
+    let default = std::env::var("TERMINALAI_UNITS").unwrap_or_default();
+    let units = match caps.name("units").map_or(default.as_str(), |m| m.as_str()).to_lowercase().as_str() {
+        "fahrenheit" | "imperial" => Units::Imperial,
+        _ => Units::Metric,
+    };
+    match weather::provider().fetch(&caps["city"]) {
+        Ok(report) => Ok(Some(weather::format(&report, units))),
+        Err(e) => Ok(Some(format!("Could not get weather for {}: {}", &caps["city"], e))),
+    }
 }
 
 fn explain_shell_command(cmd: &str) -> Result<Option<String>, Box<dyn Error>> {
-    // Stub: In a real implementation, use an LLM or a shell explain API
-    Ok(Some(format!("[Explanation for shell command: '{}']", cmd)))
+    match crate::explain::explain(cmd) {
+        Ok(explanation) => Ok(Some(explanation)),
+        Err(e) => Ok(Some(format!("Cannot explain '{}': {}", cmd, e))),
+    }
 }
 
-fn run_shell_command(cmd: &str) -> Result<Option<String>, Box<dyn Error>> {
-    use std::process::Command;
-    #[cfg(target_os = "windows")]
-    let output = Command::new("cmd").args(["/C", cmd]).output()?;
-    #[cfg(not(target_os = "windows"))]
-    let output = Command::new("sh").args(["-c", cmd]).output()?;
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    if !stderr.trim().is_empty() {
-        Ok(Some(format!("{}\n[stderr]: {}", stdout, stderr)))
-    } else {
-        Ok(Some(stdout.to_string()))
+// Prints any risk findings for `cmd` and asks before anything rated high or critical runs.
+fn approve_shell_command(cmd: &str) -> bool {
+    use crate::{prompt, risk};
+    let findings = risk::analyze(cmd);
+    if findings.is_empty() {
+        return true;
     }
+    println!("{}", risk::report(&findings));
+    !risk::requires_confirmation(&findings) || prompt::confirm("This command looks dangerous. Run it anyway?")
 }
 
-fn run_code_snippet(lang: &str, code: &str) -> Result<Option<String>, Box<dyn Error>> {
-    use std::process::{Command, Stdio};
-    use std::io::Write;
-    let (cmd, args) = match lang.to_lowercase().as_str() {
-        "python" => ("python3", vec!["-c"]),
-        "javascript" | "js" | "node" => ("node", vec!["-e"]),
-        "bash" | "sh" => ("bash", vec![]),
-        _ => return Ok(Some("Unsupported language. Supported: python, javascript, bash.".to_string())),
-    };
-    let mut child = if lang.to_lowercase() == "bash" || lang.to_lowercase() == "sh" {
-        let mut child = Command::new(cmd)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-        if let Some(stdin) = child.stdin.as_mut() {
-            stdin.write_all(code.as_bytes())?;
-        }
-        child
-    } else {
-        Command::new(cmd)
-            .args(&args)
-            .arg(code)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?
-    };
-    let output = child.wait_with_output()?;
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    if !stderr.trim().is_empty() {
-        Ok(Some(format!("{}\n[stderr]: {}", stdout, stderr)))
-    } else {
-        Ok(Some(stdout.to_string()))
+// Translates the instruction, previews the command with its explanation and risk findings,
+// and lets the user run, edit or reject it.
+fn run_shell_command(instruction: &str, options: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::translate::{self, Translation};
+    use crate::{explain, prompt, risk, shell};
+    let opts = match shell::ExecOptions::parse(options) {
+        Ok(opts) => opts,
+        Err(e) => return Ok(Some(e)),
+    };
+    let Some(mut translation) = translate::translate(instruction)? else {
+        return Ok(Some(format!(
+            "Could not translate '{}' into a shell command. Rephrase it, type the command itself, or set TERMINALAI_LLM_URL to use a local model.",
+            instruction
+        )));
+    };
+    loop {
+        println!("Command: {}   ({}{})", translation.command, translation.source, if translation.cached { ", cached" } else { "" });
+        if let Ok(explanation) = explain::explain(&translation.command) {
+            println!("{}", explanation);
+        }
+        let findings = risk::analyze(&translation.command);
+        if !findings.is_empty() {
+            println!("{}", risk::report(&findings));
+        }
+        match prompt::ask("Run it? [y]es / [e]dit / [N]o").unwrap_or_default().to_lowercase().as_str() {
+            "y" | "yes" => {
+                if risk::requires_confirmation(&findings) && !prompt::confirm("This command looks dangerous. Run it anyway?") {
+                    return Ok(Some("Cancelled.".to_string()));
+                }
+                translate::remember(instruction, &translation)?;
+                return execute_shell_command(&translation.command, &opts);
+            }
+            "e" | "edit" => match prompt::ask("New command:").filter(|c| !c.is_empty()) {
+                Some(command) => translation = Translation { command, source: "edited".to_string(), cached: false },
+                None => return Ok(Some("Cancelled.".to_string())),
+            },
+            _ => {
+                translate::forget(instruction)?;
+                return Ok(Some("Cancelled.".to_string()));
+            }
+        }
+    }
+}
+
+// Output streams to the terminal as it arrives, so only the exit status is returned.
+fn execute_shell_command(cmd: &str, opts: &crate::shell::ExecOptions) -> Result<Option<String>, Box<dyn Error>> {
+    let result = crate::shell::run(cmd, opts)?;
+    Ok(Some(result.summary()))
+}
+
+fn start_shell_session() -> Result<Option<String>, Box<dyn Error>> {
+    Ok(Some(match crate::shell::start_session()? {
+        Some(shell) => format!("Started a persistent {} session. `run` commands now share its directory and environment; type 'end shell session' to stop it.", shell),
+        None => "A shell session is already running.".to_string(),
+    }))
+}
+
+fn end_shell_session() -> Result<Option<String>, Box<dyn Error>> {
+    let message = if crate::shell::end_session() { "Shell session ended." } else { "No shell session is running." };
+    Ok(Some(message.to_string()))
+}
+
+fn show_last_output() -> Result<Option<String>, Box<dyn Error>> {
+    let Some((stdout, stderr)) = crate::shell::last_output() else {
+        return Ok(Some("No command has been run yet.".to_string()));
+    };
+    let mut sections = vec![if stdout.is_empty() { "(no output)".to_string() } else { stdout.trim_end().to_string() }];
+    if !stderr.is_empty() {
+        sections.push(format!("[stderr]:\n{}", stderr.trim_end()));
     }
+    Ok(Some(sections.join("\n")))
+}
+
+fn run_code_snippet(lang: &str, code: &str) -> Result<Option<String>, Box<dyn Error>> {
+    Ok(Some(crate::languages::execute_code(lang, code).unwrap_or_else(|e| e)))
+}
+
+fn run_file(path: &str, options: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use crate::languages::{self, RunOptions};
+    let opts = match RunOptions::parse(options) {
+        Ok(opts) => opts,
+        Err(e) => return Ok(Some(e)),
+    };
+    Ok(Some(languages::execute_file(path, &opts).unwrap_or_else(|e| e)))
 }
 
 fn file_diff(file1: &str, file2: &str) -> Result<Option<String>, Box<dyn Error>> {
@@ -510,7 +934,21 @@ fn resize_image(file: &str, width: u32, height: u32) -> Result<Option<String>, B
     let resized = img.resize(width, height, FilterType::Lanczos3);
     let out = format!("{}_resized.png", file);
     resized.save(&out)?;
-    Ok(Some(format!("Resized image saved to {}", out)))
+    Ok(Some(with_preview(format!("Resized image saved to {}", out), crate::preview::render(&resized))))
+}
+
+fn show_image(file: &str) -> Result<Option<String>, Box<dyn Error>> {
+    use image::GenericImageView;
+    let img = image::open(file)?;
+    let (width, height) = img.dimensions();
+    let message = format!("{}: {}x{}", file, width, height);
+    match crate::preview::render(&img) {
+        Some(preview) => Ok(Some(with_preview(message, Some(preview)))),
+        None => Ok(Some(format!(
+            "{}\nThis terminal has no inline image support. Set TERMINALAI_GRAPHICS to kitty, sixel, blocks or braille to force one.",
+            message
+        ))),
+    }
 }
 
 fn convert_image(file: &str, format: &str) -> Result<Option<String>, Box<dyn Error>> {
@@ -581,26 +1019,43 @@ fn paste_from_clipboard() -> Result<Option<String>, Box<dyn Error>> {
     Ok(Some(format!("Clipboard: {}", text)))
 }
 
-fn watch_file_or_dir(target: &str) -> Result<Option<String>, Box<dyn Error>> {
+fn watch_file_or_dir(target: &str, action: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
     use notify::{Watcher, RecommendedWatcher, RecursiveMode, Event};
     use std::sync::mpsc::channel;
     use std::time::Duration;
+    if let Some(cmd) = action {
+        if !approve_shell_command(cmd) {
+            return Ok(Some("Cancelled.".to_string()));
+        }
+    }
     let (tx, rx) = channel();
-    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(2))?;
-    watcher.watch(target, RecursiveMode::Recursive)?;
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, notify::Config::default().with_poll_interval(Duration::from_secs(2)))?;
+    watcher.watch(std::path::Path::new(target), RecursiveMode::Recursive)?;
     println!("Watching {} for changes. Press Ctrl+C to stop.", target);
-    for res in rx {
+    while let Ok(res) = rx.recv() {
         match res {
             Ok(Event { kind, .. }) => println!("Change detected: {:?}", kind),
             Err(e) => println!("Watch error: {:?}", e),
         }
+        if let Some(cmd) = action {
+            // Editors touch a file several times per save; run once per burst.
+            while rx.recv_timeout(Duration::from_millis(500)).is_ok() {}
+            match execute_shell_command(cmd, &crate::shell::ExecOptions::default()) {
+                Ok(output) => println!("{}", output.unwrap_or_default()),
+                Err(e) => println!("Command failed: {}", e),
+            }
+        }
     }
     Ok(Some("Stopped watching.".to_string()))
 }
 
 fn schedule_command(cmd: &str, at_time: &str) -> Result<Option<String>, Box<dyn Error>> {
-    use chrono::{Local, NaiveTime};
+    use chrono::{Local, NaiveTime, Timelike};
     use std::{thread, time::Duration};
+    // Nobody is around to answer when it fires, so ask now.
+    if !approve_shell_command(cmd) {
+        return Ok(Some("Cancelled.".to_string()));
+    }
     let now = Local::now().time();
     let target = NaiveTime::parse_from_str(at_time, "%H:%M")?;
     let now_secs = now.second() as i64 + now.minute() as i64 * 60 + now.hour() as i64 * 3600;
@@ -608,6 +1063,6 @@ fn schedule_command(cmd: &str, at_time: &str) -> Result<Option<String>, Box<dyn
     let secs = (target_secs - now_secs).rem_euclid(24*3600);
     println!("Scheduling command '{}' to run in {} seconds (at {}).", cmd, secs, at_time);
     thread::sleep(Duration::from_secs(secs as u64));
-    let result = run_shell_command(cmd)?;
-    Ok(Some(format!("Scheduled command output:\n{}", result.unwrap_or_default())))
+    let result = execute_shell_command(cmd, &crate::shell::ExecOptions::default())?;
+    Ok(Some(format!("Scheduled command finished {}", result.unwrap_or_default())))
 } 
\ No newline at end of file
@@ -0,0 +1,116 @@
+// Tabular data loading for `plot <file>`
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::Value;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+pub fn load(path: &Path) -> Result<Table, Box<dyn Error>> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    match ext.as_str() {
+        "json" => load_json(path),
+        "tsv" | "tab" => load_csv(path, b'\t'),
+        _ => load_csv(path, b','),
+    }
+}
+
+fn load_csv(path: &Path, delimiter: u8) -> Result<Table, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).flexible(true).from_path(path)?;
+    let headers = reader.headers()?.iter().map(|h| h.trim().to_string()).collect();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        rows.push(record?.iter().map(|v| v.trim().to_string()).collect());
+    }
+    Ok(Table { headers, rows })
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Accepts an array of objects, an array of arrays, or an object of equally long column arrays.
+fn load_json(path: &Path) -> Result<Table, Box<dyn Error>> {
+    let value: Value = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            let mut headers: Vec<String> = Vec::new();
+            for item in &items {
+                for key in item.as_object().into_iter().flat_map(|o| o.keys()) {
+                    if !headers.contains(key) {
+                        headers.push(key.clone());
+                    }
+                }
+            }
+            let rows = items.iter().map(|item| headers.iter().map(|h| item.get(h).map(cell).unwrap_or_default()).collect()).collect();
+            Ok(Table { headers, rows })
+        }
+        Value::Array(items) if items.iter().all(Value::is_array) => {
+            let width = items.iter().filter_map(Value::as_array).map(Vec::len).max().unwrap_or(0);
+            let headers = (1..=width).map(|i| i.to_string()).collect();
+            let rows = items.iter().filter_map(Value::as_array).map(|row| row.iter().map(cell).collect()).collect();
+            Ok(Table { headers, rows })
+        }
+        Value::Object(columns) if columns.values().all(Value::is_array) => {
+            let headers: Vec<String> = columns.keys().cloned().collect();
+            let len = columns.values().filter_map(Value::as_array).map(Vec::len).max().unwrap_or(0);
+            let rows = (0..len)
+                .map(|i| columns.values().map(|col| col.get(i).map(cell).unwrap_or_default()).collect())
+                .collect();
+            Ok(Table { headers, rows })
+        }
+        _ => Err("unsupported JSON layout: expected an array of objects, an array of arrays or an object of arrays".into()),
+    }
+}
+
+impl Table {
+    // Resolves a column by header name, falling back to a 1-based index.
+    pub fn column(&self, selector: &str) -> Result<usize, String> {
+        if let Some(i) = self.headers.iter().position(|h| h == selector) {
+            return Ok(i);
+        }
+        if let Some(i) = self.headers.iter().position(|h| h.eq_ignore_ascii_case(selector)) {
+            return Ok(i);
+        }
+        match selector.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= self.headers.len() => Ok(n - 1),
+            _ => Err(format!("No column '{}'. Available columns: {}", selector, self.headers.join(", "))),
+        }
+    }
+
+    pub fn values(&self, column: usize) -> Vec<&str> {
+        self.rows.iter().map(|row| row.get(column).map(String::as_str).unwrap_or("")).collect()
+    }
+}
+
+pub fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+// Parses common date and timestamp layouts into seconds since the Unix epoch.
+pub fn parse_time(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.timestamp() as f64);
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y/%m/%d %H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(dt.and_utc().timestamp() as f64);
+        }
+    }
+    for format in ["%Y-%m-%d", "%Y/%m/%d", "%d.%m.%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+            return Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp() as f64);
+        }
+    }
+    None
+}
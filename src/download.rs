@@ -0,0 +1,393 @@
+// Streaming, resumable HTTP downloads for `download`
+use crate::checksum;
+use crate::humanize;
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub struct DownloadOptions {
+    // Digest the finished file must match, optionally prefixed like `sha256:`.
+    pub expected: Option<String>,
+    pub max_size: Option<u64>,
+    pub timeout: Duration,
+    pub retries: u32,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions { expected: None, max_size: None, timeout: Duration::from_secs(30), retries: 3 }
+    }
+}
+
+impl DownloadOptions {
+    // Parses `with sha256 <digest>`, `expecting <digest>`, `max size 100MB`, `timeout 10s` and `retries 5`.
+    pub fn parse(options: &str) -> Result<DownloadOptions, String> {
+        let mut opts = DownloadOptions::default();
+        let with = Regex::new(r"\bwith\s+([\w-]+)\s+(\S+)").unwrap();
+        let expecting = Regex::new(r"\bexpecting\s+(\S+)").unwrap();
+        let max_size = Regex::new(r"\bmax(?:imum)?\s+size\s+(\d+(?:\.\d+)?\s*[a-zA-Z]*)").unwrap();
+        let timeout = Regex::new(r"\btimeout\s+(\d+)\s*(?:s|secs?|seconds?)?\b").unwrap();
+        let retries = Regex::new(r"\bretries\s+(\d+)\b").unwrap();
+        if let Some(caps) = with.captures(options) {
+            if checksum::Algorithm::parse(&caps[1]).is_none() {
+                return Err(format!("Unknown algorithm '{}'; use sha256, sha1, md5 or blake3", &caps[1]));
+            }
+            opts.expected = Some(format!("{}:{}", &caps[1], &caps[2]));
+        } else if let Some(caps) = expecting.captures(options) {
+            opts.expected = Some(caps[1].to_string());
+        }
+        if let Some(caps) = max_size.captures(options) {
+            opts.max_size = Some(humanize::parse_size(&caps[1]).ok_or(format!("Invalid size: {}", &caps[1]))?);
+        }
+        if let Some(caps) = timeout.captures(options) {
+            opts.timeout = Duration::from_secs(caps[1].parse().map_err(|_| "Invalid timeout")?);
+        }
+        if let Some(caps) = retries.captures(options) {
+            opts.retries = caps[1].parse().map_err(|_| "Invalid retry count")?;
+        }
+        let rest = [&with, &expecting, &max_size, &timeout, &retries].iter().fold(options.to_string(), |acc, re| re.replace_all(&acc, "").to_string());
+        let rest = rest.replace([',', ' '], "");
+        if !rest.is_empty() {
+            return Err(format!("Unrecognized download option(s): {}", rest));
+        }
+        Ok(opts)
+    }
+}
+
+// Failures that another attempt will not fix are not retried.
+#[derive(Debug)]
+enum Failure {
+    Retryable(String),
+    Fatal(String),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Retryable(msg) | Failure::Fatal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for Failure {}
+
+impl From<reqwest::Error> for Failure {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_builder() || e.is_redirect() {
+            Failure::Fatal(e.to_string())
+        } else {
+            Failure::Retryable(e.to_string())
+        }
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::UnexpectedEof | io::ErrorKind::Other => {
+                Failure::Retryable(e.to_string())
+            }
+            _ => Failure::Fatal(e.to_string()),
+        }
+    }
+}
+
+pub struct Summary {
+    pub bytes: u64,
+    pub elapsed: Duration,
+    pub verified: Option<checksum::Algorithm>,
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+// Total length from `Content-Range: bytes 100-199/200` or `bytes */200`.
+fn range_total(response: &Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    value.rsplit_once('/')?.1.parse().ok()
+}
+
+fn range_start(response: &Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    value.strip_prefix("bytes ")?.split_once('-')?.0.parse().ok()
+}
+
+fn progress_bar(total: Option<u64>, position: u64) -> ProgressBar {
+    let bar = match total {
+        Some(total) => {
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} {bytes_per_sec} eta {eta}").unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            bar
+        }
+        None => {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(ProgressStyle::with_template("{spinner} {bytes} {bytes_per_sec}").unwrap_or_else(|_| ProgressStyle::default_spinner()));
+            bar
+        }
+    };
+    bar.set_position(position);
+    bar
+}
+
+// One request, appending to the `.part` file when the server honours the Range header.
+fn attempt(client: &Client, url: &str, part: &Path, opts: &DownloadOptions) -> Result<u64, Failure> {
+    let offset = fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+    }
+    let mut response = request.send()?;
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 && range_total(&response) == Some(offset) {
+        // The previous attempt already fetched everything.
+        return Ok(offset);
+    }
+    if !status.is_success() {
+        let message = format!("server returned {}", status);
+        let transient = status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS;
+        return Err(if transient { Failure::Retryable(message) } else { Failure::Fatal(message) });
+    }
+    let resuming = status == StatusCode::PARTIAL_CONTENT && range_start(&response) == Some(offset);
+    let start = if resuming { offset } else { 0 };
+    let length = response.headers().get(CONTENT_LENGTH).and_then(|v| v.to_str().ok()?.parse::<u64>().ok());
+    let total = range_total(&response).or(length.map(|l| l + start));
+    if let (Some(max), Some(total)) = (opts.max_size, total) {
+        if total > max {
+            return Err(Failure::Fatal(format!("file is {}, over the {} limit", humanize::size(total), humanize::size(max))));
+        }
+    }
+    let mut file = if resuming { OpenOptions::new().append(true).open(part)? } else { File::create(part)? };
+    let bar = progress_bar(total, start);
+    let mut written = start;
+    let mut buffer = [0u8; 64 * 1024];
+    let result = loop {
+        let n = match response.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(n) => n,
+            Err(e) => break Err(Failure::from(e)),
+        };
+        written += n as u64;
+        if opts.max_size.is_some_and(|max| written > max) {
+            break Err(Failure::Fatal(format!("download exceeded the {} limit", humanize::size(opts.max_size.unwrap_or(0)))));
+        }
+        if let Err(e) = file.write_all(&buffer[..n]) {
+            break Err(Failure::Fatal(e.to_string()));
+        }
+        bar.set_position(written);
+    };
+    bar.finish_and_clear();
+    result?;
+    file.sync_all()?;
+    if total.is_some_and(|t| written < t) {
+        return Err(Failure::Retryable(format!("connection closed after {} of {}", written, total.unwrap_or(0))));
+    }
+    Ok(written)
+}
+
+// Streams `url` into `<dest>.part` and renames it into place once complete (and verified).
+// Transient failures are retried with exponential backoff, resuming where the last attempt stopped.
+pub fn download(url: &str, dest: &Path, opts: &DownloadOptions) -> Result<Summary, Box<dyn Error>> {
+    let client = Client::builder().connect_timeout(opts.timeout).timeout(opts.timeout).build()?;
+    let part = part_path(dest);
+    let started = Instant::now();
+    let mut delay = Duration::from_secs(1);
+    let mut attempts = 0;
+    let bytes = loop {
+        attempts += 1;
+        match attempt(&client, url, &part, opts) {
+            Ok(bytes) => break bytes,
+            Err(Failure::Retryable(msg)) if attempts <= opts.retries => {
+                eprintln!("Download interrupted ({}); retrying in {}s", msg, delay.as_secs());
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+            Err(Failure::Fatal(msg)) => {
+                let _ = fs::remove_file(&part);
+                return Err(msg.into());
+            }
+            // Out of retries: keep the partial file so a later run can resume it.
+            Err(Failure::Retryable(msg)) => return Err(format!("{} (gave up after {} attempts; run again to resume)", msg, attempts).into()),
+        }
+    };
+    let verified = match &opts.expected {
+        Some(expected) => match checksum::matches(&part, expected, None) {
+            Ok(Some(algorithm)) => Some(algorithm),
+            Ok(None) => {
+                fs::remove_file(&part)?;
+                return Err("checksum mismatch; the download was discarded".into());
+            }
+            Err(e) => {
+                fs::remove_file(&part)?;
+                return Err(format!("cannot verify the download ({}); it was discarded", e).into());
+            }
+        },
+        None => None,
+    };
+    fs::rename(&part, dest)?;
+    Ok(Summary { bytes, elapsed: started.elapsed(), verified })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    // A local HTTP stand-in: answers the n-th request (0-based) with `respond(n, request head)`,
+    // written as-is, then closes the connection. Returns the base URL and the request heads seen.
+    fn serve(respond: impl Fn(usize, &str) -> Vec<u8> + Send + 'static) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/file", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+        std::thread::spawn(move || {
+            for (n, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut head = Vec::new();
+                let mut byte = [0u8; 1];
+                while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).is_ok_and(|r| r == 1) {
+                    head.push(byte[0]);
+                }
+                let head = String::from_utf8_lossy(&head).to_lowercase();
+                let reply = respond(n, &head);
+                log.lock().unwrap().push(head);
+                let _ = stream.write_all(&reply);
+            }
+        });
+        (url, seen)
+    }
+
+    fn response(status: &str, headers: &[&str], body: &[u8]) -> Vec<u8> {
+        let mut out = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        for header in headers {
+            out.push_str(header);
+            out.push_str("\r\n");
+        }
+        out.push_str("\r\n");
+        let mut out = out.into_bytes();
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn ok(body: &[u8]) -> Vec<u8> {
+        response("200 OK", &[&format!("Content-Length: {}", body.len())], body)
+    }
+
+    fn failure(url: &str, dest: &Path, opts: &DownloadOptions) -> String {
+        match download(url, dest, opts) {
+            Ok(_) => panic!("expected the download to fail"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn options() -> DownloadOptions {
+        DownloadOptions { timeout: Duration::from_secs(5), ..DownloadOptions::default() }
+    }
+
+    #[test]
+    fn rejects_not_found_without_retrying() {
+        let (url, seen) = serve(|_, _| response("404 Not Found", &["Content-Length: 0"], b""));
+        let dir = TempDir::new("download-404");
+        let dest = dir.path().join("out.bin");
+        let error = failure(&url, &dest, &options());
+        assert!(error.contains("404"), "{}", error);
+        assert!(!dest.exists() && !part_path(&dest).exists());
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn resumes_a_partial_file_with_range() {
+        let (url, seen) = serve(|_, head| {
+            if head.contains("range: bytes=6-") {
+                response("206 Partial Content", &["Content-Range: bytes 6-10/11", "Content-Length: 5"], b"world")
+            } else {
+                ok(b"hello world")
+            }
+        });
+        let dir = TempDir::new("download-resume");
+        let dest = dir.path().join("out.bin");
+        fs::write(part_path(&dest), b"hello ").unwrap();
+        let summary = download(&url, &dest, &options()).unwrap();
+        assert_eq!(summary.bytes, 11);
+        assert_eq!(fs::read(&dest).unwrap(), b"hello world");
+        assert!(!part_path(&dest).exists());
+        assert!(seen.lock().unwrap()[0].contains("range: bytes=6-"));
+    }
+
+    #[test]
+    fn retries_a_dropped_connection_and_resumes() {
+        let (url, seen) = serve(|n, head| match n {
+            // Promises 11 bytes but closes after 5.
+            0 => response("200 OK", &["Content-Length: 11"], b"hello"),
+            _ if head.contains("range: bytes=5-") => response("206 Partial Content", &["Content-Range: bytes 5-10/11", "Content-Length: 6"], b" world"),
+            _ => ok(b"hello world"),
+        });
+        let dir = TempDir::new("download-retry");
+        let dest = dir.path().join("out.bin");
+        let summary = download(&url, &dest, &options()).unwrap();
+        assert_eq!(summary.bytes, 11);
+        assert_eq!(fs::read(&dest).unwrap(), b"hello world");
+        assert_eq!(seen.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn retries_server_errors() {
+        let (url, seen) = serve(|n, _| if n == 0 { response("503 Service Unavailable", &["Content-Length: 0"], b"") } else { ok(b"ok") });
+        let dir = TempDir::new("download-503");
+        let dest = dir.path().join("out.bin");
+        download(&url, &dest, &options()).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"ok");
+        assert_eq!(seen.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn enforces_max_size() {
+        let opts = DownloadOptions { max_size: Some(10), ..options() };
+        let (url, _) = serve(|_, _| ok(&[b'x'; 100]));
+        let dir = TempDir::new("download-max-declared");
+        let dest = dir.path().join("out.bin");
+        let error = failure(&url, &dest, &opts);
+        assert!(error.contains("over the 10 B limit"), "{}", error);
+        assert!(!dest.exists() && !part_path(&dest).exists());
+
+        // Without a Content-Length the limit is enforced while streaming.
+        let (url, _) = serve(|_, _| response("200 OK", &[], &[b'x'; 100]));
+        let dir = TempDir::new("download-max-streamed");
+        let dest = dir.path().join("out.bin");
+        let error = failure(&url, &dest, &opts);
+        assert!(error.contains("exceeded the 10 B limit"), "{}", error);
+        assert!(!dest.exists() && !part_path(&dest).exists());
+    }
+
+    #[test]
+    fn discards_a_checksum_mismatch() {
+        let (url, _) = serve(|_, _| ok(b"hello world"));
+        let dir = TempDir::new("download-checksum");
+        let dest = dir.path().join("out.bin");
+        let opts = DownloadOptions { expected: Some(format!("sha256:{}", "0".repeat(64))), ..options() };
+        let error = failure(&url, &dest, &opts);
+        assert!(error.contains("checksum mismatch"), "{}", error);
+        assert!(!dest.exists() && !part_path(&dest).exists());
+
+        let digest = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        let opts = DownloadOptions { expected: Some(format!("sha256:{}", digest)), ..options() };
+        let summary = download(&url, &dest, &opts).unwrap();
+        assert_eq!(summary.verified.map(checksum::Algorithm::name), Some("sha256"));
+    }
+}
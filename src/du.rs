@@ -0,0 +1,187 @@
+// du/ncdu-style disk usage breakdown for `show disk usage`
+use crate::humanize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+const BAR_WIDTH: usize = 20;
+const LARGEST_FILES: usize = 10;
+
+pub struct Node {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_dir: bool,
+    pub children: Vec<Node>,
+}
+
+// Counters gathered while scanning, reported under the breakdown.
+#[derive(Default)]
+pub struct Scan {
+    pub files: usize,
+    pub dirs: usize,
+    pub hardlinks_skipped: usize,
+    pub other_filesystems: usize,
+    pub unreadable: usize,
+    seen: HashSet<(u64, u64)>,
+    device: Option<u64>,
+}
+
+#[cfg(unix)]
+fn device(meta: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_meta: &Metadata) -> Option<u64> {
+    None
+}
+
+// (device, inode) for files with more than one link, so each is counted once.
+#[cfg(unix)]
+fn hardlink_key(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (meta.nlink() > 1).then(|| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn hardlink_key(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+impl Scan {
+    // Walks `root` without following symlinks or crossing onto other filesystems.
+    pub fn run(root: &Path) -> Result<(Node, Scan), Box<dyn Error>> {
+        let meta = fs::symlink_metadata(root)?;
+        let mut scan = Scan { device: device(&meta), ..Scan::default() };
+        let node = scan.visit(root, root.display().to_string(), &meta);
+        Ok((node, scan))
+    }
+
+    fn visit(&mut self, path: &Path, name: String, meta: &Metadata) -> Node {
+        let mut node = Node { name, path: path.to_path_buf(), size: 0, is_dir: meta.is_dir(), children: Vec::new() };
+        if !meta.is_dir() {
+            self.files += 1;
+            if let Some(key) = hardlink_key(meta) {
+                if !self.seen.insert(key) {
+                    self.hardlinks_skipped += 1;
+                    return node;
+                }
+            }
+            node.size = meta.len();
+            return node;
+        }
+        self.dirs += 1;
+        let Ok(entries) = fs::read_dir(path) else {
+            self.unreadable += 1;
+            return node;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let Ok(child_meta) = fs::symlink_metadata(entry.path()) else {
+                self.unreadable += 1;
+                continue;
+            };
+            if child_meta.is_dir() && device(&child_meta) != self.device {
+                self.other_filesystems += 1;
+                continue;
+            }
+            let child = self.visit(&entry.path(), entry.file_name().to_string_lossy().into_owned(), &child_meta);
+            node.size += child.size;
+            node.children.push(child);
+        }
+        node.children.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+        node
+    }
+}
+
+fn bar(fraction: f64) -> String {
+    let filled = ((fraction * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+fn render_children(node: &Node, total: u64, depth: usize, top: usize, indent: usize, lines: &mut Vec<String>) {
+    for child in node.children.iter().take(top) {
+        let fraction = if total > 0 { child.size as f64 / total as f64 } else { 0.0 };
+        let marker = if child.is_dir { "/" } else { "" };
+        lines.push(format!(
+            "{:>9} {:>5.1}% {} {}{}{}",
+            humanize::size(child.size),
+            fraction * 100.0,
+            bar(fraction),
+            "  ".repeat(indent),
+            child.name,
+            marker
+        ));
+        if child.is_dir && depth > 1 {
+            render_children(child, total, depth - 1, top, indent + 1, lines);
+        }
+    }
+    if node.children.len() > top {
+        let rest: u64 = node.children[top..].iter().map(|c| c.size).sum();
+        lines.push(format!("{:>9} {:>6} {} {}... {} more", humanize::size(rest), "", " ".repeat(BAR_WIDTH), "  ".repeat(indent), node.children.len() - top));
+    }
+}
+
+fn largest_files<'a>(node: &'a Node, files: &mut Vec<&'a Node>) {
+    for child in &node.children {
+        if child.is_dir {
+            largest_files(child, files);
+        } else {
+            files.push(child);
+        }
+    }
+}
+
+// Free and total space of the filesystem holding `path`, from the longest matching mount point.
+fn filesystem_space(path: &Path) -> Option<(u64, u64)> {
+    let path = fs::canonicalize(path).ok()?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| (d.available_space(), d.total_space()))
+}
+
+pub fn report(root: &Path, depth: usize, top: usize) -> Result<String, Box<dyn Error>> {
+    let (node, scan) = Scan::run(root)?;
+    let mut lines = vec![format!(
+        "Disk usage for {}: {} ({} file(s), {} director(ies))",
+        root.display(),
+        humanize::size(node.size),
+        scan.files,
+        scan.dirs.saturating_sub(1)
+    )];
+    render_children(&node, node.size, depth, top, 0, &mut lines);
+    let mut files = Vec::new();
+    largest_files(&node, &mut files);
+    if !files.is_empty() {
+        files.sort_by(|a, b| b.size.cmp(&a.size).then(a.path.cmp(&b.path)));
+        lines.push(String::new());
+        lines.push("Largest files:".to_string());
+        for file in files.iter().take(LARGEST_FILES) {
+            lines.push(format!("{:>9}  {}", humanize::size(file.size), file.path.display()));
+        }
+    }
+    let mut notes = Vec::new();
+    if scan.hardlinks_skipped > 0 {
+        notes.push(format!("{} extra hardlink(s) counted once", scan.hardlinks_skipped));
+    }
+    if scan.other_filesystems > 0 {
+        notes.push(format!("{} mount point(s) on other filesystems skipped", scan.other_filesystems));
+    }
+    if scan.unreadable > 0 {
+        notes.push(format!("{} unreadable path(s)", scan.unreadable));
+    }
+    if !notes.is_empty() {
+        lines.push(format!("({})", notes.join(", ")));
+    }
+    if let Some((free, total)) = filesystem_space(root) {
+        let used = if total > 0 { 100.0 * (total - free.min(total)) as f64 / total as f64 } else { 0.0 };
+        lines.push(format!("Filesystem: {} free of {} ({:.0}% used)", humanize::size(free), humanize::size(total), used));
+    }
+    Ok(lines.join("\n"))
+}
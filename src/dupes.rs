@@ -0,0 +1,142 @@
+// Duplicate file detection for `find duplicates`: size, then partial hash, then full hash
+use crate::humanize;
+use crate::search::SearchOptions;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+// Version control metadata is never touched, even when hidden and ignored files are included.
+const VCS_DIRS: [&str; 3] = [".git", ".hg", ".svn"];
+
+// Bytes hashed from the start of each same-sized file before committing to a full hash.
+const PARTIAL_BYTES: usize = 16 * 1024;
+
+pub struct DuplicateSet {
+    pub size: u64,
+    // Sorted; the first path is the one that is kept.
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateSet {
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+#[cfg(unix)]
+fn inode(meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode(_meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+fn partial_hash(path: &Path) -> io::Result<blake3::Hash> {
+    let mut buffer = Vec::with_capacity(PARTIAL_BYTES);
+    File::open(path)?.take(PARTIAL_BYTES as u64).read_to_end(&mut buffer)?;
+    Ok(blake3::hash(&buffer))
+}
+
+fn full_hash(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+// Splits each group further by `key`, dropping groups that end up with a single file.
+fn refine<K, F>(groups: Vec<Vec<PathBuf>>, key: F) -> Vec<Vec<PathBuf>>
+where
+    K: std::hash::Hash + Eq,
+    F: Fn(&Path) -> io::Result<K>,
+{
+    let mut refined = Vec::new();
+    for group in groups {
+        let mut by_key: HashMap<K, Vec<PathBuf>> = HashMap::new();
+        for path in group {
+            // Files that vanish or cannot be read simply drop out of the comparison.
+            if let Ok(k) = key(&path) {
+                by_key.entry(k).or_default().push(path);
+            }
+        }
+        refined.extend(by_key.into_values().filter(|g| g.len() > 1));
+    }
+    refined
+}
+
+// Finds sets of identical non-empty files under `root`, honouring `opts.hidden` and
+// `opts.no_ignore` like `search` does. Symlinks are not followed and paths that are already
+// hardlinks of each other are only considered once.
+pub fn find(root: &Path, opts: &SearchOptions) -> Result<Vec<DuplicateSet>, Box<dyn Error>> {
+    let mut walker = opts.walker(root)?;
+    walker.filter_entry(|e| !(e.depth() > 0 && e.file_type().is_some_and(|t| t.is_dir()) && VCS_DIRS.iter().any(|d| e.file_name() == *d)));
+    let mut seen = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for entry in walker.build().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        if meta.len() == 0 || inode(&meta).is_some_and(|key| !seen.insert(key)) {
+            continue;
+        }
+        by_size.entry(meta.len()).or_default().push(entry.into_path());
+    }
+    let groups = by_size.into_values().filter(|g| g.len() > 1).collect();
+    let groups = refine(groups, partial_hash);
+    let groups = refine(groups, full_hash);
+    let mut sets: Vec<DuplicateSet> = groups
+        .into_iter()
+        .map(|mut paths| {
+            paths.sort();
+            let size = std::fs::metadata(&paths[0]).map(|m| m.len()).unwrap_or(0);
+            DuplicateSet { size, paths }
+        })
+        .collect();
+    sets.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then(a.paths.cmp(&b.paths)));
+    Ok(sets)
+}
+
+pub fn format(sets: &[DuplicateSet]) -> String {
+    if sets.is_empty() {
+        return "No duplicate files found.".to_string();
+    }
+    let mut lines = Vec::new();
+    for set in sets {
+        lines.push(format!("{} x {}:", humanize::size(set.size), set.paths.len()));
+        for (i, path) in set.paths.iter().enumerate() {
+            lines.push(format!("  {} {}", if i == 0 { "keep" } else { "    " }, path.display()));
+        }
+    }
+    let redundant: usize = sets.iter().map(|s| s.paths.len() - 1).sum();
+    let wasted: u64 = sets.iter().map(DuplicateSet::wasted).sum();
+    lines.push(format!("{} duplicate set(s), {} redundant file(s), {} wasted", sets.len(), redundant, humanize::size(wasted)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn skips_hidden_files_unless_asked_and_never_enters_vcs_dirs() {
+        let dir = TempDir::new("dupes");
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git/objects")).unwrap();
+        for name in ["a.txt", "b.txt", ".hidden.txt", ".git/objects/c"] {
+            fs::write(root.join(name), "same").unwrap();
+        }
+        let found = |opts: &SearchOptions| -> Vec<PathBuf> {
+            find(root, opts).unwrap().into_iter().flat_map(|s| s.paths).map(|p| p.strip_prefix(root).unwrap().to_path_buf()).collect()
+        };
+        assert_eq!(found(&SearchOptions::default()), [Path::new("a.txt"), Path::new("b.txt")]);
+        let everything = SearchOptions { hidden: true, no_ignore: true, ..SearchOptions::default() };
+        assert_eq!(found(&everything), [Path::new(".hidden.txt"), Path::new("a.txt"), Path::new("b.txt")]);
+    }
+}
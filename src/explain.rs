@@ -0,0 +1,822 @@
+// Offline shell command explainer for `explain`: a per-token breakdown built from man pages,
+// `--help` output of well-known tools and the bundled descriptions in explain_db
+use crate::explain_db;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const HELP_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_DESCRIPTION: usize = 160;
+
+// Programs that take another command line as their arguments, with the number of positional
+// arguments they consume before it.
+const WRAPPERS: &[(&str, usize)] =
+    &[("sudo", 0), ("doas", 0), ("env", 0), ("nohup", 0), ("time", 0), ("exec", 0), ("nice", 0), ("timeout", 1), ("xargs", 0), ("watch", 0), ("command", 0), ("stdbuf", 0)];
+const SUBCOMMAND_TOOLS: &[&str] = &["git", "cargo", "docker", "podman", "kubectl", "npm", "yarn", "apt", "apt-get", "systemctl", "pip", "go", "brew"];
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh"];
+const BUILTINS: &[&str] = &[
+    "cd", "export", "source", ".", "alias", "unset", "set", "eval", "exit", "return", "read", "shift", "ulimit", "umask", "type", "builtin", "declare", "local",
+];
+const KEYWORDS: &[&str] = &["if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac", "in", "function", "!", "{", "}"];
+// Explaining must not run anything unknown, so only these well-known tools, found on PATH, are
+// asked for their --help. Everything else is described from man pages and explain_db alone.
+const HELP_SAFE: &[&str] = &[
+    "ls", "grep", "egrep", "fgrep", "rg", "find", "tar", "cp", "mv", "rm", "mkdir", "rmdir", "touch", "cat", "head", "tail", "sort", "uniq", "wc", "cut",
+    "tr", "paste", "join", "split", "sed", "awk", "gawk", "xargs", "chmod", "chown", "chgrp", "ln", "tee", "stat", "file", "diff", "cmp", "date", "du", "df",
+    "ps", "env", "timeout", "nohup", "nice", "basename", "dirname", "realpath", "readlink", "which", "curl", "wget", "jq", "zip", "unzip", "gzip",
+    "gunzip", "xz", "bzip2", "rsync", "git", "docker", "podman", "kubectl", "cargo", "rustc", "npm", "node", "python3", "pip", "pip3", "go", "make",
+];
+
+#[derive(Default, Clone)]
+pub struct Word {
+    pub text: String,
+    // As typed, quotes and escapes included.
+    pub raw: String,
+    pub quoted: bool,
+    // Bodies of `$(...)`, backtick and `<(...)`/`>(...)` substitutions.
+    pub subs: Vec<String>,
+}
+
+// Shell tokens, also used by the risk analyzer.
+pub enum Token {
+    Word(Word),
+    Op(String),
+    Redirect { fd: Option<String>, op: String },
+    Open,
+    Close,
+}
+
+// Balanced `$( ... )` body starting at `i` (just past the opening paren); returns it and the
+// index after the closing paren.
+fn capture_parens(chars: &[char], mut i: usize) -> Result<(String, usize), String> {
+    let mut depth = 1;
+    let mut body = String::new();
+    let mut quote = None;
+    while let Some(&c) = chars.get(i) {
+        i += 1;
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok((body, i));
+                }
+            }
+            _ => {}
+        }
+        body.push(c);
+    }
+    Err("Unterminated $( substitution".to_string())
+}
+
+pub fn lex(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut word: Option<Word> = None;
+    let flush = |word: &mut Option<Word>, tokens: &mut Vec<Token>| {
+        if let Some(w) = word.take() {
+            tokens.push(Token::Word(w));
+        }
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            ' ' | '\t' | '\n' => {
+                flush(&mut word, &mut tokens);
+                i += 1;
+            }
+            '#' if word.is_none() => break,
+            '\'' => {
+                let w = word.get_or_insert_with(Word::default);
+                w.quoted = true;
+                w.raw.push(c);
+                i += 1;
+                loop {
+                    let &c = chars.get(i).ok_or("Unterminated single quote")?;
+                    i += 1;
+                    w.raw.push(c);
+                    if c == '\'' {
+                        break;
+                    }
+                    w.text.push(c);
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(Word::default);
+                w.quoted = true;
+                w.raw.push(c);
+                i += 1;
+                loop {
+                    let &c = chars.get(i).ok_or("Unterminated double quote")?;
+                    i += 1;
+                    match c {
+                        '"' => {
+                            w.raw.push(c);
+                            break;
+                        }
+                        '\\' if matches!(chars.get(i), Some('"' | '\\' | '$' | '`')) => {
+                            w.raw.push(c);
+                            w.raw.push(chars[i]);
+                            w.text.push(chars[i]);
+                            i += 1;
+                        }
+                        '$' if chars.get(i) == Some(&'(') => {
+                            let (body, end) = capture_parens(&chars, i + 1)?;
+                            let shown = format!("$({})", body);
+                            w.raw.push_str(&shown);
+                            w.text.push_str(&shown);
+                            if !body.starts_with('(') {
+                                w.subs.push(body);
+                            }
+                            i = end;
+                        }
+                        _ => {
+                            w.raw.push(c);
+                            w.text.push(c);
+                        }
+                    }
+                }
+            }
+            '\\' => {
+                let w = word.get_or_insert_with(Word::default);
+                w.raw.push(c);
+                if let Some(next) = next {
+                    w.raw.push(next);
+                    w.text.push(next);
+                    w.quoted = true;
+                }
+                i += 2;
+            }
+            '$' if next == Some('(') => {
+                let (body, end) = capture_parens(&chars, i + 2)?;
+                let w = word.get_or_insert_with(Word::default);
+                let shown = format!("$({})", body);
+                w.raw.push_str(&shown);
+                w.text.push_str(&shown);
+                // `$(( ... ))` is arithmetic, not a command.
+                if !body.starts_with('(') {
+                    w.subs.push(body);
+                }
+                i = end;
+            }
+            '`' => {
+                let end = chars[i + 1..].iter().position(|&c| c == '`').ok_or("Unterminated backtick substitution")? + i + 1;
+                let body: String = chars[i + 1..end].iter().collect();
+                let w = word.get_or_insert_with(Word::default);
+                w.raw.push_str(&format!("`{}`", body));
+                w.text.push_str(&format!("`{}`", body));
+                w.subs.push(body);
+                i = end + 1;
+            }
+            // `<(cmd)` and `>(cmd)`: process substitution, a file name connected to cmd's output
+            // or input. It is part of a word, not a redirect.
+            '<' | '>' if next == Some('(') => {
+                let (body, end) = capture_parens(&chars, i + 2)?;
+                let w = word.get_or_insert_with(Word::default);
+                let shown = format!("{}({})", c, body);
+                w.raw.push_str(&shown);
+                w.text.push_str(&shown);
+                w.subs.push(body);
+                i = end;
+            }
+            '<' | '>' => {
+                // A bare number right before the operator is the file descriptor, as in `2>`.
+                let fd = match &word {
+                    Some(w) if !w.quoted && !w.text.is_empty() && w.text.chars().all(|c| c.is_ascii_digit()) => word.take().map(|w| w.text),
+                    _ => None,
+                };
+                flush(&mut word, &mut tokens);
+                let rest: String = chars[i..].iter().take(3).collect();
+                let op = ["<<<", "<<", "<&", "<>", ">>", ">&", ">|", "<", ">"].into_iter().find(|op| rest.starts_with(op)).unwrap_or(">");
+                i += op.len();
+                tokens.push(Token::Redirect { fd, op: op.to_string() });
+            }
+            '|' | '&' | ';' => {
+                flush(&mut word, &mut tokens);
+                let rest: String = chars[i..].iter().take(3).collect();
+                if rest.starts_with("&>") {
+                    let op = if rest.starts_with("&>>") { "&>>" } else { "&>" };
+                    i += op.len();
+                    tokens.push(Token::Redirect { fd: None, op: op.to_string() });
+                    continue;
+                }
+                let op = ["||", "|&", "&&", ";;", "|", "&", ";"].into_iter().find(|op| rest.starts_with(op)).unwrap_or(";");
+                i += op.len();
+                tokens.push(Token::Op(if op == ";;" { ";".to_string() } else { op.to_string() }));
+            }
+            '(' | ')' if word.is_none() => {
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+                i += 1;
+            }
+            ')' => {
+                flush(&mut word, &mut tokens);
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            _ => {
+                let w = word.get_or_insert_with(Word::default);
+                w.raw.push(c);
+                w.text.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut word, &mut tokens);
+    Ok(tokens)
+}
+
+struct Opt {
+    names: Vec<String>,
+    takes_arg: bool,
+    description: String,
+}
+
+#[derive(Default)]
+struct Help {
+    summary: Option<String>,
+    options: Vec<Opt>,
+    source: Option<&'static str>,
+}
+
+impl Help {
+    // Prefers an entry with a description; terse listings (like find's) only name the option.
+    fn lookup(&self, name: &str) -> Option<&Opt> {
+        let mut found = self.options.iter().filter(|o| o.names.iter().any(|n| n == name));
+        let first = found.next()?;
+        if !first.description.is_empty() {
+            return Some(first);
+        }
+        found.find(|o| !o.description.is_empty()).or(Some(first))
+    }
+}
+
+// Parses an option spec like `-e, --regexp=PATTERNS`, `--color[=WHEN]` or `-name PATTERN`.
+fn parse_spec(spec: &str, description: &str) -> Option<Opt> {
+    let mut names = Vec::new();
+    let mut takes_arg = false;
+    for part in spec.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        let end = part.find(['=', '[', ' ', '<']).unwrap_or(part.len());
+        let (name, rest) = part.split_at(end);
+        // Lines that list several options at once (`-amin N -anewer FILE`) describe none of them.
+        if rest.contains(" -") {
+            return None;
+        }
+        let rest = rest.trim_start_matches(['=', ' ']);
+        if !rest.is_empty() && !part[end..].starts_with("[=") && !part[end..].starts_with("[ ") {
+            takes_arg = true;
+        }
+        names.push(name.to_string());
+    }
+    if names.is_empty() {
+        return None;
+    }
+    Some(Opt { names, takes_arg, description: clean(description) })
+}
+
+// Collapses whitespace and keeps long descriptions to their first sentence.
+fn clean(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_DESCRIPTION {
+        return text;
+    }
+    if let Some(end) = text.find(". ").filter(|&e| e < MAX_DESCRIPTION) {
+        return text[..=end].to_string();
+    }
+    let cut: String = text.chars().take(MAX_DESCRIPTION - 3).collect();
+    format!("{}...", cut)
+}
+
+// Option lines from man or --help text: the spec runs up to two spaces or a tab, and the
+// description may continue on the following indented lines.
+fn parse_options(text: &str) -> Vec<Opt> {
+    let mut options = Vec::new();
+    let mut pending: Option<(String, String)> = None;
+    let finish = |pending: &mut Option<(String, String)>, options: &mut Vec<Opt>| {
+        if let Some((spec, description)) = pending.take() {
+            options.extend(parse_spec(&spec, &description));
+        }
+    };
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let is_option = trimmed.starts_with('-') && trimmed.chars().nth(1).is_some_and(|c| c.is_alphanumeric() || c == '-') && line.starts_with(char::is_whitespace);
+        if is_option {
+            finish(&mut pending, &mut options);
+            let split = [trimmed.find("  "), trimmed.find('\t')].into_iter().flatten().min();
+            let (spec, description) = match split {
+                Some(at) => (&trimmed[..at], trimmed[at..].trim()),
+                None => (trimmed, ""),
+            };
+            pending = Some((spec.to_string(), description.to_string()));
+        } else if trimmed.is_empty() || !line.starts_with(char::is_whitespace) {
+            finish(&mut pending, &mut options);
+        } else if let Some((_, description)) = pending.as_mut() {
+            if description.len() < MAX_DESCRIPTION {
+                description.push(' ');
+                description.push_str(trimmed);
+            }
+        }
+    }
+    finish(&mut pending, &mut options);
+    options
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+pub fn on_path(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH").is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+// Runs a help command with no stdin, giving up after HELP_TIMEOUT. Help often goes to stderr.
+fn capture(mut command: Command) -> Option<String> {
+    let mut child = command.env("LC_ALL", "C").stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()?;
+    let mut stdout = child.stdout.take()?;
+    let mut stderr = child.stderr.take()?;
+    let out = std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stdout.read_to_end(&mut buffer);
+        buffer
+    });
+    let err = std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stderr.read_to_end(&mut buffer);
+        buffer
+    });
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < HELP_TIMEOUT => std::thread::sleep(Duration::from_millis(20)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let (out, err) = (out.join().ok()?, err.join().ok()?);
+    let text = String::from_utf8_lossy(if out.is_empty() { &err } else { &out }).into_owned();
+    (!text.trim().is_empty()).then_some(text)
+}
+
+fn man_page(name: &str) -> Option<String> {
+    if !on_path("man") {
+        return None;
+    }
+    let mut command = Command::new("man");
+    command.arg(name).env("MANPAGER", "cat").env("PAGER", "cat").env("MANWIDTH", "120").env("GROFF_NO_SGR", "1");
+    let text = capture(command)?;
+    // Strip the overstrike sequences used for bold and underline.
+    let mut plain = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\x08' {
+            plain.pop();
+        } else {
+            plain.push(c);
+        }
+    }
+    plain.contains("NAME").then_some(plain)
+}
+
+// `ls - list directory contents` from the NAME section.
+fn man_summary(page: &str) -> Option<String> {
+    let mut lines = page.lines().skip_while(|l| l.trim() != "NAME").skip(1);
+    let line = lines.find(|l| !l.trim().is_empty())?;
+    let (_, summary) = line.split_once(" - ").or_else(|| line.split_once(" \u{2014} "))?;
+    Some(clean(summary))
+}
+
+// The first prose line before the option list, skipping the usage synopsis.
+fn help_summary(text: &str) -> Option<String> {
+    text.lines()
+        .take_while(|l| !l.trim_start().starts_with('-'))
+        .find(|l| !l.trim().is_empty() && !l.starts_with(char::is_whitespace) && !l.trim_end().ends_with(':') && !l.to_lowercase().starts_with("usage"))
+        .map(clean)
+}
+
+fn load_help(program: &str) -> Help {
+    let name = Path::new(program).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| program.to_string());
+    let builtin = explain_db::TOOLS.iter().find(|t| t.name == name);
+    let mut help = Help::default();
+    if let Some(page) = man_page(&name) {
+        help.summary = man_summary(&page);
+        help.options.extend(parse_options(&page));
+        help.source = Some("man page");
+    }
+    if !program.contains('/') && HELP_SAFE.contains(&name.as_str()) && on_path(program) {
+        let mut command = Command::new(program);
+        command.arg("--help");
+        if let Some(text) = capture(command) {
+            let options = parse_options(&text);
+            if help.summary.is_none() {
+                help.summary = builtin.map(|t| t.summary.to_string()).or_else(|| help_summary(&text));
+            }
+            if !options.is_empty() || help.source.is_none() {
+                help.source = help.source.or(Some("--help"));
+            }
+            help.options.extend(options);
+        }
+    }
+    if let Some(tool) = builtin {
+        help.summary = help.summary.or(Some(tool.summary.to_string()));
+        help.options.extend(tool.options.iter().filter_map(|(spec, description)| parse_spec(spec, description)));
+        help.source = help.source.or(Some("built-in"));
+    }
+    help
+}
+
+// `git commit` and friends: the subcommand's own man page, plus the bundled one-liners.
+fn load_subcommand_help(program: &str, subcommand: &str) -> Help {
+    let key = format!("{} {}", program, subcommand);
+    let mut help = Help::default();
+    if let Some(page) = man_page(&format!("{}-{}", program, subcommand)) {
+        help.summary = man_summary(&page);
+        help.options = parse_options(&page);
+        help.source = Some("man page");
+    }
+    if let Some((_, summary)) = explain_db::SUBCOMMANDS.iter().find(|(name, _)| *name == key) {
+        help.summary = Some(summary.to_string());
+        help.source = help.source.or(Some("built-in"));
+    }
+    help
+}
+
+fn stream_name(fd: &str) -> String {
+    match fd {
+        "0" => "standard input".to_string(),
+        "1" => "standard output".to_string(),
+        "2" => "standard error".to_string(),
+        other => format!("file descriptor {}", other),
+    }
+}
+
+fn operator(op: &str) -> &'static str {
+    match op {
+        "|" => "pipe: standard output of the previous command becomes standard input of the next",
+        "|&" => "pipe both standard output and standard error into the next command",
+        "&&" => "run the next command only if the previous one succeeded",
+        "||" => "run the next command only if the previous one failed",
+        "&" => "run the previous command in the background",
+        _ => "run the next command after the previous one finishes",
+    }
+}
+
+struct Line {
+    indent: usize,
+    token: String,
+    text: String,
+}
+
+#[derive(Default)]
+struct Explainer {
+    help: HashMap<String, Rc<Help>>,
+    lines: Vec<Line>,
+}
+
+impl Explainer {
+    fn push(&mut self, indent: usize, token: impl Into<String>, text: impl Into<String>) {
+        self.lines.push(Line { indent, token: token.into(), text: text.into() });
+    }
+
+    fn help(&mut self, program: &str) -> Rc<Help> {
+        self.help.entry(program.to_string()).or_insert_with(|| Rc::new(load_help(program))).clone()
+    }
+
+    // Explains a nested command line (substitutions, `sh -c` strings, `watch 'cmd'`).
+    fn nested(&mut self, source: &str, indent: usize) {
+        match lex(source) {
+            Ok(tokens) => self.sequence(&tokens, indent),
+            Err(e) => self.push(indent, source, format!("(cannot parse: {})", e)),
+        }
+    }
+
+    fn substitutions(&mut self, word: &Word, indent: usize) {
+        for body in &word.subs {
+            let (shown, text) = if word.raw.contains(&format!("<({})", body)) {
+                (format!("<({})", body), "process substitution: a file name to read the output of")
+            } else if word.raw.contains(&format!(">({})", body)) {
+                (format!(">({})", body), "process substitution: a file name whose writes become the input of")
+            } else {
+                (format!("$({})", body), "command substitution: replaced by the output of")
+            };
+            self.push(indent + 1, shown, text);
+            self.nested(body, indent + 2);
+        }
+    }
+
+    // Commands joined by operators, with `( ... )` subshells.
+    fn sequence(&mut self, tokens: &[Token], mut indent: usize) {
+        let mut i = 0;
+        while i < tokens.len() {
+            match &tokens[i] {
+                Token::Op(op) => {
+                    self.push(indent, op.clone(), operator(op));
+                    i += 1;
+                }
+                Token::Open => {
+                    self.push(indent, "(", "start a subshell: the enclosed commands run in a child shell");
+                    indent += 1;
+                    i += 1;
+                }
+                Token::Close => {
+                    indent = indent.saturating_sub(1);
+                    self.push(indent, ")", "end of the subshell");
+                    i += 1;
+                }
+                _ => {
+                    let end = tokens[i..].iter().position(|t| matches!(t, Token::Op(_) | Token::Open | Token::Close)).map_or(tokens.len(), |p| p + i);
+                    let mut pos = 0;
+                    self.command(&tokens[i..end], &mut pos, indent, &[]);
+                    i = end;
+                }
+            }
+        }
+    }
+
+    fn redirect(&mut self, tokens: &[Token], pos: &mut usize, indent: usize) {
+        let Some(Token::Redirect { fd, op }) = tokens.get(*pos) else {
+            return;
+        };
+        *pos += 1;
+        let target = match tokens.get(*pos) {
+            Some(Token::Word(w)) => {
+                *pos += 1;
+                Some(w.clone())
+            }
+            _ => None,
+        };
+        let shown = format!("{}{}{}", fd.as_deref().unwrap_or(""), op, target.as_ref().map_or("", |t| t.raw.as_str()));
+        let name = target.as_ref().map_or("?", |t| t.text.as_str());
+        let output = stream_name(fd.as_deref().unwrap_or("1"));
+        let input = stream_name(fd.as_deref().unwrap_or("0"));
+        let text = match op.as_str() {
+            ">" => format!("write {} to {}, replacing its contents", output, name),
+            ">|" => format!("write {} to {}, replacing it even if noclobber is set", output, name),
+            ">>" => format!("append {} to {}", output, name),
+            "&>" => format!("write standard output and standard error to {}", name),
+            "&>>" => format!("append standard output and standard error to {}", name),
+            ">&" if name == "-" => format!("close {}", output),
+            ">&" => format!("send {} to wherever {} goes", output, stream_name(name)),
+            "<&" => format!("read {} from {}", input, stream_name(name)),
+            "<" => format!("read {} from {}", input, name),
+            "<>" => format!("open {} for reading and writing as {}", name, input),
+            "<<" => format!("here-document: the following lines up to {} are {}", name, input),
+            _ => format!("here-string: {} is {}", name, input),
+        };
+        self.push(indent, shown, text);
+        if let Some(target) = target {
+            self.substitutions(&target, indent);
+        }
+    }
+
+    // One simple command, stopping before any word in `terminators` (find's `-exec ... ;`).
+    fn command(&mut self, tokens: &[Token], pos: &mut usize, indent: usize, terminators: &[&str]) {
+        let is_terminator = |t: Option<&Token>| matches!(t, Some(Token::Word(w)) if terminators.contains(&w.text.as_str()));
+        // Leading assignments and redirects.
+        let program = loop {
+            match tokens.get(*pos) {
+                Some(Token::Redirect { .. }) => self.redirect(tokens, pos, indent),
+                _ if is_terminator(tokens.get(*pos)) => return,
+                Some(Token::Word(w)) if !w.text.starts_with('=') && w.text.split_once('=').is_some_and(|(n, _)| n.chars().all(|c| c.is_alphanumeric() || c == '_')) => {
+                    let (name, _) = w.text.split_once('=').unwrap_or_default();
+                    let has_command = tokens[*pos + 1..].iter().any(|t| matches!(t, Token::Word(_)));
+                    let text = if has_command { format!("set environment variable {} for this command", name) } else { format!("set shell variable {}", name) };
+                    self.push(indent, w.raw.clone(), text);
+                    self.substitutions(w, indent);
+                    *pos += 1;
+                }
+                Some(Token::Word(w)) => break w.clone(),
+                _ => return,
+            }
+        };
+        *pos += 1;
+        if program.text.contains(char::is_whitespace) {
+            self.push(indent, program.raw.clone(), "command string, run by the shell:");
+            self.nested(&program.text, indent + 1);
+            return;
+        }
+        let name = Path::new(&program.text).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| program.text.clone());
+        if KEYWORDS.contains(&name.as_str()) {
+            self.push(indent, program.raw.clone(), "shell keyword");
+            self.arguments(tokens, pos, indent, terminators, &name, Vec::new());
+            return;
+        }
+        let help = self.help(&program.text);
+        let text = match (&help.summary, help.source) {
+            (Some(summary), Some(source)) => format!("{} [{}]", summary, source),
+            (None, Some(source)) => format!("(no summary) [{}]", source),
+            _ if BUILTINS.contains(&name.as_str()) => "shell builtin".to_string(),
+            _ if on_path(&program.text) => "program with no help available".to_string(),
+            _ => "command not found on PATH and not in the built-in list".to_string(),
+        };
+        self.push(indent, program.raw.clone(), text);
+        self.substitutions(&program, indent);
+        self.arguments(tokens, pos, indent, terminators, &name, vec![help]);
+    }
+
+    fn arguments(&mut self, tokens: &[Token], pos: &mut usize, indent: usize, terminators: &[&str], name: &str, mut helps: Vec<Rc<Help>>) {
+        let wrapper = WRAPPERS.iter().find(|(w, _)| *w == name).map(|(_, skip)| *skip);
+        let mut end_of_options = false;
+        let mut positional = 0;
+        let mut subcommand = !SUBCOMMAND_TOOLS.contains(&name);
+        while *pos < tokens.len() {
+            let word = match &tokens[*pos] {
+                Token::Redirect { .. } => {
+                    self.redirect(tokens, pos, indent + 1);
+                    continue;
+                }
+                Token::Word(w) if terminators.contains(&w.text.as_str()) => return,
+                Token::Word(w) => w.clone(),
+                _ => return,
+            };
+            let text = word.text.as_str();
+            let is_option = !end_of_options && text.starts_with('-') && text.len() > 1;
+            if name == "find" && matches!(text, "-exec" | "-execdir" | "-ok" | "-okdir") {
+                let description = helps.iter().find_map(|h| h.lookup(text)).map_or("run a command for each file found".to_string(), |o| o.description.clone());
+                self.push(indent + 1, word.raw.clone(), description);
+                *pos += 1;
+                self.command(tokens, pos, indent + 2, &[";", "+"]);
+                if let Some(Token::Word(end)) = tokens.get(*pos) {
+                    let text = if end.text == "+" { "end of the command; {} is replaced by as many file names as fit" } else { "end of the command, run once per file" };
+                    self.push(indent + 1, end.raw.clone(), text);
+                    *pos += 1;
+                }
+                continue;
+            }
+            if !is_option && !subcommand {
+                subcommand = true;
+                let sub = Rc::new(load_subcommand_help(name, text));
+                let description = sub.summary.clone().map_or("subcommand".to_string(), |s| format!("{} [{}]", s, sub.source.unwrap_or("built-in")));
+                self.push(indent + 1, word.raw.clone(), description);
+                helps.insert(0, sub);
+                *pos += 1;
+                continue;
+            }
+            if is_option {
+                if SHELLS.contains(&name) && text == "-c" {
+                    if let Some(Token::Word(script)) = tokens.get(*pos + 1) {
+                        self.push(indent + 1, format!("-c {}", script.raw), "run the commands in this string:");
+                        self.nested(&script.text.clone(), indent + 2);
+                        *pos += 2;
+                        continue;
+                    }
+                }
+                if text == "--" {
+                    self.push(indent + 1, "--", "end of options: the remaining words are arguments");
+                    end_of_options = true;
+                    *pos += 1;
+                    continue;
+                }
+                self.option(tokens, pos, indent + 1, &word, &helps);
+                continue;
+            }
+            if let Some(skip) = wrapper {
+                if name == "env" && text.contains('=') {
+                    let (var, _) = text.split_once('=').unwrap_or_default();
+                    self.push(indent + 1, word.raw.clone(), format!("set environment variable {} for the command", var));
+                    *pos += 1;
+                    continue;
+                }
+                if positional >= skip {
+                    self.command(tokens, pos, indent + 1, terminators);
+                    return;
+                }
+            }
+            // Old-style bundled flags, as in `tar xzf`.
+            if name == "tar" && positional == 0 && text.chars().all(|c| c.is_ascii_alphabetic()) {
+                let dashed = Word { text: format!("-{}", text), ..word.clone() };
+                self.option(tokens, pos, indent + 1, &dashed, &helps);
+                positional += 1;
+                continue;
+            }
+            positional += 1;
+            *pos += 1;
+            let description = if let Some(opt) = helps.iter().find_map(|h| h.lookup(text)) {
+                opt.description.clone()
+            } else if !word.subs.is_empty() {
+                "argument built from the output of:".to_string()
+            } else if text == "{}" && terminators.contains(&";") {
+                "replaced by the path of each file found".to_string()
+            } else if text == "-" {
+                "standard input (or output)".to_string()
+            } else if !word.quoted && text.contains(['*', '?', '[']) {
+                "pattern, expanded by the shell to matching file names".to_string()
+            } else if !word.quoted && text.starts_with('~') {
+                "path in your home directory".to_string()
+            } else {
+                "argument".to_string()
+            };
+            self.push(indent + 1, word.raw.clone(), description);
+            self.substitutions(&word, indent + 1);
+        }
+    }
+
+    fn lookup(helps: &[Rc<Help>], name: &str) -> Option<(bool, String)> {
+        helps.iter().find_map(|h| h.lookup(name)).map(|o| (o.takes_arg, if o.description.is_empty() { "option".to_string() } else { o.description.clone() }))
+    }
+
+    // Takes the option's argument from the next word when it is not attached.
+    fn take_argument(tokens: &[Token], pos: &mut usize) -> Option<Word> {
+        match tokens.get(*pos) {
+            Some(Token::Word(w)) => {
+                *pos += 1;
+                Some(w.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn option(&mut self, tokens: &[Token], pos: &mut usize, indent: usize, word: &Word, helps: &[Rc<Help>]) {
+        *pos += 1;
+        let text = word.text.as_str();
+        let (name, attached) = match text.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (text, None),
+        };
+        if let Some((takes_arg, description)) = Self::lookup(helps, name) {
+            let mut shown = word.raw.clone();
+            let argument = if takes_arg && attached.is_none() { Self::take_argument(tokens, pos) } else { None };
+            if let Some(argument) = &argument {
+                shown = format!("{} {}", shown, argument.raw);
+            }
+            self.push(indent, shown, description);
+            self.substitutions(word, indent);
+            if let Some(argument) = argument {
+                self.substitutions(&argument, indent);
+            }
+            return;
+        }
+        // Combined short flags: `-la` is `-l -a`, and `-n5` is `-n 5`.
+        let flags: Vec<char> = name.strip_prefix('-').filter(|rest| !rest.starts_with('-')).map(|rest| rest.chars().collect()).unwrap_or_default();
+        if flags.len() > 1 && Self::lookup(helps, &format!("-{}", flags[0])).is_some() {
+            for (k, c) in flags.iter().enumerate() {
+                let flag = format!("-{}", c);
+                match Self::lookup(helps, &flag) {
+                    Some((true, description)) => {
+                        let rest: String = flags[k + 1..].iter().collect();
+                        if !rest.is_empty() {
+                            self.push(indent, format!("{}{}", flag, rest), description);
+                        } else if let Some(argument) = Self::take_argument(tokens, pos) {
+                            self.push(indent, format!("{} {}", flag, argument.raw), description);
+                        } else {
+                            self.push(indent, flag, description);
+                        }
+                        return;
+                    }
+                    Some((false, description)) => self.push(indent, flag, description),
+                    None => self.push(indent, flag, "unknown option"),
+                }
+            }
+            return;
+        }
+        // `head -5` and `tail -20` are old spellings of `-n 5` and `-n 20`.
+        if name.len() > 1 && name[1..].chars().all(|c| c.is_ascii_digit()) && Self::lookup(helps, "-n").is_some() {
+            self.push(indent, word.raw.clone(), format!("same as -n {}", &name[1..]));
+            return;
+        }
+        self.push(indent, word.raw.clone(), "option (no description found)");
+    }
+
+    fn render(&self) -> String {
+        let left: Vec<String> = self.lines.iter().map(|l| format!("{}{}", "  ".repeat(l.indent), l.token)).collect();
+        let width = left.iter().map(|l| l.chars().count()).max().unwrap_or(0).min(32);
+        left.iter()
+            .zip(&self.lines)
+            .map(|(left, line)| {
+                if left.chars().count() > width {
+                    format!("{}\n{}  {}", left, " ".repeat(width), line.text)
+                } else {
+                    format!("{:width$}  {}", left, line.text, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Explains `command` token by token. Only local man pages, `--help` output of HELP_SAFE tools
+// and the bundled descriptions are consulted; nothing else is run and nothing goes over the network.
+pub fn explain(command: &str) -> Result<String, String> {
+    let tokens = lex(command)?;
+    if tokens.is_empty() {
+        return Err("Nothing to explain".to_string());
+    }
+    let mut explainer = Explainer::default();
+    explainer.sequence(&tokens, 0);
+    Ok(explainer.render())
+}
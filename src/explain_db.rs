@@ -0,0 +1,383 @@
+// Built-in descriptions of common tools, used by `explain` when no man page or --help is available
+pub struct Tool {
+    pub name: &'static str,
+    pub summary: &'static str,
+    // Option specs in --help style (`-n, --lines=NUM`) with their descriptions.
+    pub options: &'static [(&'static str, &'static str)],
+}
+
+pub const TOOLS: &[Tool] = &[
+    Tool {
+        name: "ls",
+        summary: "list directory contents",
+        options: &[
+            ("-a, --all", "do not ignore entries starting with ."),
+            ("-A, --almost-all", "do not list implied . and .."),
+            ("-l", "use a long listing format"),
+            ("-h, --human-readable", "with -l, print sizes like 1K 234M 2G"),
+            ("-t", "sort by modification time, newest first"),
+            ("-S", "sort by file size, largest first"),
+            ("-r, --reverse", "reverse order while sorting"),
+            ("-R, --recursive", "list subdirectories recursively"),
+            ("-d, --directory", "list directories themselves, not their contents"),
+            ("-1", "list one file per line"),
+            ("--color[=WHEN]", "color the output WHEN (always, auto or never)"),
+        ],
+    },
+    Tool {
+        name: "grep",
+        summary: "print lines that match patterns",
+        options: &[
+            ("-i, --ignore-case", "ignore case distinctions in patterns and data"),
+            ("-v, --invert-match", "select non-matching lines"),
+            ("-r, --recursive", "read all files under each directory, recursively"),
+            ("-n, --line-number", "print line number with output lines"),
+            ("-l, --files-with-matches", "print only names of files with selected lines"),
+            ("-c, --count", "print only a count of selected lines per file"),
+            ("-w, --word-regexp", "match only whole words"),
+            ("-E, --extended-regexp", "patterns are extended regular expressions"),
+            ("-F, --fixed-strings", "patterns are strings, not regular expressions"),
+            ("-o, --only-matching", "show only the matching parts of lines"),
+            ("-q, --quiet", "suppress all normal output"),
+            ("-e, --regexp=PATTERNS", "use PATTERNS for matching"),
+            ("-A, --after-context=NUM", "print NUM lines of trailing context"),
+            ("-B, --before-context=NUM", "print NUM lines of leading context"),
+            ("-C, --context=NUM", "print NUM lines of output context"),
+            ("--include=GLOB", "search only files that match GLOB"),
+            ("--exclude=GLOB", "skip files that match GLOB"),
+        ],
+    },
+    Tool {
+        name: "find",
+        summary: "search for files in a directory hierarchy",
+        options: &[
+            ("-name PATTERN", "base of file name matches shell pattern PATTERN"),
+            ("-iname PATTERN", "like -name, but case insensitive"),
+            ("-path PATTERN", "file path matches shell pattern PATTERN"),
+            ("-type C", "file is of type C (f file, d directory, l symlink)"),
+            ("-size N", "file uses N units of space (e.g. +10M for more than 10 MiB)"),
+            ("-mtime N", "file was last modified N*24 hours ago (+N older, -N newer)"),
+            ("-mmin N", "file was last modified N minutes ago"),
+            ("-maxdepth LEVELS", "descend at most LEVELS levels below the starting points"),
+            ("-mindepth LEVELS", "do not apply tests at levels less than LEVELS"),
+            ("-user NAME", "file is owned by user NAME"),
+            ("-perm MODE", "file's permission bits match MODE"),
+            ("-empty", "file is empty and is either a regular file or a directory"),
+            ("-newer FILE", "file was modified more recently than FILE"),
+            ("-print", "print the full file name, followed by a newline"),
+            ("-print0", "print the file name followed by a null character"),
+            ("-delete", "delete matching files"),
+            ("-exec COMMAND ;", "run COMMAND for each match; {} is replaced by the file name"),
+            ("-execdir COMMAND ;", "like -exec, but run from the file's directory"),
+            ("-not", "negate the following expression"),
+            ("-o, -or", "logical OR of two expressions"),
+        ],
+    },
+    Tool {
+        name: "tar",
+        summary: "create, list and extract archive files",
+        options: &[
+            ("-c, --create", "create a new archive"),
+            ("-x, --extract", "extract files from an archive"),
+            ("-t, --list", "list the contents of an archive"),
+            ("-v, --verbose", "verbosely list files processed"),
+            ("-f, --file=ARCHIVE", "use archive file ARCHIVE"),
+            ("-z, --gzip", "filter the archive through gzip"),
+            ("-j, --bzip2", "filter the archive through bzip2"),
+            ("-J, --xz", "filter the archive through xz"),
+            ("-C, --directory=DIR", "change to directory DIR"),
+            ("--exclude=PATTERN", "exclude files matching PATTERN"),
+        ],
+    },
+    Tool {
+        name: "cp",
+        summary: "copy files and directories",
+        options: &[
+            ("-r, -R, --recursive", "copy directories recursively"),
+            ("-a, --archive", "copy recursively, preserving attributes and links"),
+            ("-f, --force", "remove existing destination files if they cannot be opened"),
+            ("-i, --interactive", "prompt before overwrite"),
+            ("-n, --no-clobber", "do not overwrite an existing file"),
+            ("-p", "preserve mode, ownership and timestamps"),
+            ("-v, --verbose", "explain what is being done"),
+            ("-u, --update", "copy only when the source is newer than the destination"),
+        ],
+    },
+    Tool {
+        name: "mv",
+        summary: "move (rename) files",
+        options: &[
+            ("-f, --force", "do not prompt before overwriting"),
+            ("-i, --interactive", "prompt before overwrite"),
+            ("-n, --no-clobber", "do not overwrite an existing file"),
+            ("-v, --verbose", "explain what is being done"),
+            ("-t, --target-directory=DIRECTORY", "move all SOURCE arguments into DIRECTORY"),
+        ],
+    },
+    Tool {
+        name: "rm",
+        summary: "remove files or directories",
+        options: &[
+            ("-f, --force", "ignore nonexistent files and arguments, never prompt"),
+            ("-i", "prompt before every removal"),
+            ("-r, -R, --recursive", "remove directories and their contents recursively"),
+            ("-d, --dir", "remove empty directories"),
+            ("-v, --verbose", "explain what is being done"),
+            ("--no-preserve-root", "do not treat '/' specially"),
+        ],
+    },
+    Tool {
+        name: "mkdir",
+        summary: "make directories",
+        options: &[
+            ("-p, --parents", "no error if existing, make parent directories as needed"),
+            ("-m, --mode=MODE", "set file mode (as in chmod)"),
+            ("-v, --verbose", "print a message for each created directory"),
+        ],
+    },
+    Tool {
+        name: "cat",
+        summary: "concatenate files and print on the standard output",
+        options: &[
+            ("-n, --number", "number all output lines"),
+            ("-A, --show-all", "show nonprinting characters, line ends and tabs"),
+            ("-s, --squeeze-blank", "suppress repeated empty output lines"),
+        ],
+    },
+    Tool {
+        name: "head",
+        summary: "output the first part of files",
+        options: &[("-n, --lines=NUM", "print the first NUM lines"), ("-c, --bytes=NUM", "print the first NUM bytes")],
+    },
+    Tool {
+        name: "tail",
+        summary: "output the last part of files",
+        options: &[
+            ("-n, --lines=NUM", "output the last NUM lines"),
+            ("-c, --bytes=NUM", "output the last NUM bytes"),
+            ("-f, --follow", "output appended data as the file grows"),
+            ("-F", "like --follow, but retry if the file is replaced"),
+        ],
+    },
+    Tool {
+        name: "sort",
+        summary: "sort lines of text files",
+        options: &[
+            ("-n, --numeric-sort", "compare according to string numerical value"),
+            ("-h, --human-numeric-sort", "compare human readable numbers (e.g., 2K 1G)"),
+            ("-r, --reverse", "reverse the result of comparisons"),
+            ("-u, --unique", "output only the first of an equal run"),
+            ("-k, --key=KEYDEF", "sort via a key; KEYDEF gives location and type"),
+            ("-t, --field-separator=SEP", "use SEP instead of non-blank to blank transition"),
+            ("-o, --output=FILE", "write result to FILE instead of standard output"),
+        ],
+    },
+    Tool {
+        name: "uniq",
+        summary: "report or omit repeated lines",
+        options: &[
+            ("-c, --count", "prefix lines by the number of occurrences"),
+            ("-d, --repeated", "only print duplicate lines, one for each group"),
+            ("-u, --unique", "only print unique lines"),
+            ("-i, --ignore-case", "ignore differences in case when comparing"),
+        ],
+    },
+    Tool {
+        name: "wc",
+        summary: "print newline, word, and byte counts for each file",
+        options: &[
+            ("-l, --lines", "print the newline counts"),
+            ("-w, --words", "print the word counts"),
+            ("-c, --bytes", "print the byte counts"),
+            ("-m, --chars", "print the character counts"),
+        ],
+    },
+    Tool {
+        name: "cut",
+        summary: "remove sections from each line of files",
+        options: &[
+            ("-d, --delimiter=DELIM", "use DELIM instead of TAB for field delimiter"),
+            ("-f, --fields=LIST", "select only these fields"),
+            ("-c, --characters=LIST", "select only these characters"),
+        ],
+    },
+    Tool {
+        name: "sed",
+        summary: "stream editor for filtering and transforming text",
+        options: &[
+            ("-n, --quiet, --silent", "suppress automatic printing of pattern space"),
+            ("-e, --expression=SCRIPT", "add the script to the commands to be executed"),
+            ("-f, --file=FILE", "add the contents of FILE to the commands to be executed"),
+            ("-i, --in-place[=SUFFIX]", "edit files in place (makes backup if SUFFIX supplied)"),
+            ("-E, -r, --regexp-extended", "use extended regular expressions in the script"),
+        ],
+    },
+    Tool {
+        name: "awk",
+        summary: "pattern scanning and text processing language",
+        options: &[
+            ("-F FS", "use FS as the input field separator"),
+            ("-v VAR=VALUE", "assign VALUE to variable VAR before execution"),
+            ("-f PROGFILE", "read the AWK program source from the file PROGFILE"),
+        ],
+    },
+    Tool {
+        name: "xargs",
+        summary: "build and execute command lines from standard input",
+        options: &[
+            ("-0, --null", "input items are terminated by a null character"),
+            ("-n, --max-args=MAX-ARGS", "use at most MAX-ARGS arguments per command line"),
+            ("-I REPLACE-STR", "replace REPLACE-STR in the initial arguments with names read from standard input"),
+            ("-P, --max-procs=MAX-PROCS", "run up to MAX-PROCS processes at a time"),
+            ("-r, --no-run-if-empty", "do not run the command if the input is empty"),
+            ("-t, --verbose", "print commands before executing them"),
+        ],
+    },
+    Tool {
+        name: "chmod",
+        summary: "change file mode bits",
+        options: &[("-R, --recursive", "change files and directories recursively"), ("-v, --verbose", "output a diagnostic for every file processed")],
+    },
+    Tool {
+        name: "chown",
+        summary: "change file owner and group",
+        options: &[("-R, --recursive", "operate on files and directories recursively"), ("-h, --no-dereference", "affect symbolic links instead of referenced files")],
+    },
+    Tool {
+        name: "ln",
+        summary: "make links between files",
+        options: &[
+            ("-s, --symbolic", "make symbolic links instead of hard links"),
+            ("-f, --force", "remove existing destination files"),
+            ("-n, --no-dereference", "treat LINK_NAME as a normal file if it is a symbolic link to a directory"),
+        ],
+    },
+    Tool {
+        name: "echo",
+        summary: "display a line of text",
+        options: &[("-n", "do not output the trailing newline"), ("-e", "enable interpretation of backslash escapes")],
+    },
+    Tool {
+        name: "tee",
+        summary: "read from standard input and write to standard output and files",
+        options: &[("-a, --append", "append to the given files, do not overwrite")],
+    },
+    Tool {
+        name: "curl",
+        summary: "transfer a URL",
+        options: &[
+            ("-o, --output <file>", "write to file instead of stdout"),
+            ("-O, --remote-name", "write output to a file named as the remote file"),
+            ("-L, --location", "follow redirects"),
+            ("-s, --silent", "silent mode"),
+            ("-S, --show-error", "show error even when -s is used"),
+            ("-f, --fail", "fail fast with no output on HTTP errors"),
+            ("-X, --request <method>", "specify request method to use"),
+            ("-H, --header <header>", "pass custom header(s) to server"),
+            ("-d, --data <data>", "HTTP POST data"),
+            ("-I, --head", "show document info only"),
+            ("-u, --user <user:password>", "server user and password"),
+            ("-k, --insecure", "allow insecure server connections"),
+        ],
+    },
+    Tool {
+        name: "ps",
+        summary: "report a snapshot of the current processes",
+        options: &[
+            ("-e", "select all processes"),
+            ("-f", "do full-format listing"),
+            ("aux", "BSD syntax: all processes of all users, with user-oriented format"),
+            ("-u USERLIST", "select by effective user ID or name"),
+        ],
+    },
+    Tool {
+        name: "kill",
+        summary: "send a signal to a process",
+        options: &[("-s SIGNAL", "specify the signal to be sent"), ("-9", "send SIGKILL, which cannot be caught or ignored"), ("-l, --list", "list signal names")],
+    },
+    Tool {
+        name: "du",
+        summary: "estimate file space usage",
+        options: &[
+            ("-h, --human-readable", "print sizes in human readable format"),
+            ("-s, --summarize", "display only a total for each argument"),
+            ("-a, --all", "write counts for all files, not just directories"),
+            ("-d, --max-depth=N", "print the total for a directory only if it is N or fewer levels below"),
+            ("-c, --total", "produce a grand total"),
+            ("-x, --one-file-system", "skip directories on different file systems"),
+        ],
+    },
+    Tool {
+        name: "df",
+        summary: "report file system space usage",
+        options: &[("-h, --human-readable", "print sizes in powers of 1024 (e.g., 1023M)"), ("-T, --print-type", "print file system type")],
+    },
+    Tool { name: "sudo", summary: "execute a command as another user", options: &[("-u, --user=user", "run the command as a user other than root"), ("-E, --preserve-env", "preserve the user's environment")] },
+    Tool { name: "env", summary: "run a program in a modified environment", options: &[("-i, --ignore-environment", "start with an empty environment"), ("-u, --unset=NAME", "remove variable from the environment")] },
+    Tool { name: "time", summary: "run a program and report how long it took", options: &[] },
+    Tool { name: "nohup", summary: "run a command immune to hangups", options: &[] },
+    Tool { name: "timeout", summary: "run a command with a time limit", options: &[("-s, --signal=SIGNAL", "specify the signal to be sent on timeout"), ("-k, --kill-after=DURATION", "also send a KILL signal if the command is still running this long after the initial signal")] },
+    Tool { name: "sh", summary: "command interpreter (shell)", options: &[("-c", "read commands from the following string"), ("-e", "exit immediately if a command fails"), ("-x", "print commands as they are executed")] },
+    Tool { name: "bash", summary: "GNU Bourne-Again SHell", options: &[("-c", "read commands from the following string"), ("-e", "exit immediately if a command fails"), ("-x", "print commands as they are executed"), ("-l, --login", "act as if invoked as a login shell")] },
+    Tool { name: "cd", summary: "change the shell working directory (shell builtin)", options: &[] },
+    Tool { name: "export", summary: "set export attribute for shell variables (shell builtin)", options: &[] },
+    Tool { name: "source", summary: "execute commands from a file in the current shell (shell builtin)", options: &[] },
+    Tool {
+        name: "git",
+        summary: "the distributed version control system",
+        options: &[
+            ("-C <path>", "run as if git was started in <path>"),
+            ("-m <msg>", "use the given message (commit, tag, merge)"),
+            ("-a, --all", "stage modified and deleted files (commit) or list all branches (branch)"),
+            ("-b <branch>", "create and switch to a new branch (checkout)"),
+            ("-f, --force", "force the operation"),
+            ("--amend", "replace the tip of the current branch by creating a new commit"),
+            ("--oneline", "show each commit on a single line"),
+            ("-n <number>", "limit the number of commits to output (log)"),
+            ("--hard", "reset the index and working tree, discarding changes"),
+        ],
+    },
+    Tool {
+        name: "docker",
+        summary: "manage containers and images",
+        options: &[
+            ("-d, --detach", "run container in background"),
+            ("-i, --interactive", "keep STDIN open"),
+            ("-t, --tty", "allocate a pseudo-TTY"),
+            ("-p, --publish list", "publish a container's port(s) to the host"),
+            ("-v, --volume list", "bind mount a volume"),
+            ("-e, --env list", "set environment variables"),
+            ("--rm", "automatically remove the container when it exits"),
+            ("--name string", "assign a name to the container"),
+        ],
+    },
+];
+
+// Short descriptions of common subcommands, as `program subcommand`.
+pub const SUBCOMMANDS: &[(&str, &str)] = &[
+    ("git add", "add file contents to the index"),
+    ("git commit", "record changes to the repository"),
+    ("git push", "update remote refs along with associated objects"),
+    ("git pull", "fetch from and integrate with another repository or a local branch"),
+    ("git status", "show the working tree status"),
+    ("git log", "show commit logs"),
+    ("git diff", "show changes between commits, commit and working tree, etc"),
+    ("git checkout", "switch branches or restore working tree files"),
+    ("git switch", "switch branches"),
+    ("git branch", "list, create, or delete branches"),
+    ("git merge", "join two or more development histories together"),
+    ("git rebase", "reapply commits on top of another base tip"),
+    ("git reset", "reset current HEAD to the specified state"),
+    ("git clone", "clone a repository into a new directory"),
+    ("git stash", "stash the changes in a dirty working directory away"),
+    ("cargo build", "compile the current package"),
+    ("cargo run", "run a binary or example of the local package"),
+    ("cargo test", "execute all unit and integration tests"),
+    ("cargo clippy", "check a package to catch common mistakes"),
+    ("docker run", "create and run a new container from an image"),
+    ("docker ps", "list containers"),
+    ("docker build", "build an image from a Dockerfile"),
+    ("docker exec", "execute a command in a running container"),
+    ("systemctl status", "show runtime status of units"),
+    ("systemctl restart", "stop and then start units"),
+];
@@ -0,0 +1,304 @@
+// Tokenizer and parser for calculator expressions
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    // Literals keep their source text so exact arithmetic can read `0.1` as 1/10.
+    Num(String),
+    Ident(String),
+    Neg(Box<Expr>),
+    Factorial(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    List(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(String),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+#[derive(Debug)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '°' || c == 'µ'
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Only treat `e` as an exponent when digits follow, so `2 eV`-style input still lexes as an identifier.
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let literal: String = chars[start..i].iter().collect();
+            if literal.parse::<f64>().is_err() {
+                return Err(ParseError(format!("invalid number '{}'", literal)));
+            }
+            tokens.push(Token::Num(literal));
+        } else if is_ident_char(c) {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '*' && chars.get(i + 1) == Some(&'*') {
+            tokens.push(Token::Op('^'));
+            i += 2;
+        } else if "+-*/^!".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '[' {
+            tokens.push(Token::LBracket);
+            i += 1;
+        } else if c == ']' {
+            tokens.push(Token::RBracket);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else {
+            return Err(ParseError(format!("unexpected character '{}'", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(c @ ('+' | '-'))) = self.peek() {
+            let op = if *c == '+' { Op::Add } else { Op::Sub };
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(c @ ('*' | '/'))) = self.peek() {
+            let op = if *c == '*' { Op::Mul } else { Op::Div };
+            self.pos += 1;
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.juxtaposed(),
+        }
+    }
+
+    // Juxtaposition binds tighter than `*` and `/`, so `5 GiB / 30 s` reads as `(5 GiB) / (30 s)`.
+    fn juxtaposed(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.power()?;
+        while matches!(self.peek(), Some(Token::Num(_) | Token::Ident(_) | Token::LParen | Token::LBracket)) {
+            let rhs = self.power()?;
+            lhs = Expr::Binary(Op::Mul, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let mut base = self.primary()?;
+        while let Some(Token::Op('!')) = self.peek() {
+            self.pos += 1;
+            base = Expr::Factorial(Box::new(base));
+        }
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = match self.peek() {
+                Some(Token::Op('-')) => {
+                    self.pos += 1;
+                    Expr::Neg(Box::new(self.power()?))
+                }
+                _ => self.power()?,
+            };
+            return Ok(Expr::Binary(Op::Pow, Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            // A name directly followed by `(` is a function call rather than an implicit product.
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                Ok(Expr::Call(name, self.items(Token::RParen)?))
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Ident(name)),
+            Some(Token::LParen) => {
+                let inner = self.expr()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err(ParseError("expected ')'".to_string())),
+                }
+            }
+            Some(Token::LBracket) => Ok(Expr::List(self.items(Token::RBracket)?)),
+            Some(token) => Err(ParseError(format!("unexpected token {:?}", token))),
+            None => Err(ParseError("unexpected end of expression".to_string())),
+        }
+    }
+
+    // Parses a comma-separated list up to and including `close`.
+    fn items(&mut self, close: Token) -> Result<Vec<Expr>, ParseError> {
+        let mut items = Vec::new();
+        if self.peek() == Some(&close) {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(self.expr()?);
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(token) if token == close => return Ok(items),
+                _ => return Err(ParseError("expected ',' or closing bracket".to_string())),
+            }
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser { tokens: tokenize(input)?, pos: 0 };
+    let expr = parser.expr()?;
+    if let Some(token) = parser.peek() {
+        return Err(ParseError(format!("unexpected token {:?}", token)));
+    }
+    Ok(expr)
+}
+
+impl Expr {
+    pub fn idents(&self) -> Vec<&str> {
+        let mut out = Vec::new();
+        self.collect_idents(&mut out);
+        out
+    }
+
+    // Replaces every `name` with `value`, e.g. `x` with a sample point when plotting.
+    pub fn substitute(&self, name: &str, value: &Expr) -> Expr {
+        let sub = |e: &Expr| Box::new(e.substitute(name, value));
+        match self {
+            Expr::Ident(ident) if ident == name => value.clone(),
+            Expr::Num(_) | Expr::Ident(_) => self.clone(),
+            Expr::Neg(inner) => Expr::Neg(sub(inner)),
+            Expr::Factorial(inner) => Expr::Factorial(sub(inner)),
+            Expr::Binary(op, lhs, rhs) => Expr::Binary(*op, sub(lhs), sub(rhs)),
+            Expr::Call(f, items) => Expr::Call(f.clone(), items.iter().map(|e| e.substitute(name, value)).collect()),
+            Expr::List(items) => Expr::List(items.iter().map(|e| e.substitute(name, value)).collect()),
+        }
+    }
+
+    fn collect_idents<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Expr::Num(_) => {}
+            Expr::Ident(name) => out.push(name),
+            Expr::Neg(inner) | Expr::Factorial(inner) => inner.collect_idents(out),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_idents(out);
+                rhs.collect_idents(out);
+            }
+            Expr::Call(_, items) | Expr::List(items) => {
+                for item in items {
+                    item.collect_idents(out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn juxtaposition_binds_tighter_than_division() {
+        assert_eq!(parse("2x/3").unwrap(), parse("(2*x)/3").unwrap());
+        assert_eq!(parse("5 GiB / 30 s").unwrap(), parse("(5*GiB)/(30*s)").unwrap());
+        assert_eq!(parse("-2x^2").unwrap(), parse("-(2*(x^2))").unwrap());
+    }
+
+    #[test]
+    fn rejects_unbalanced_input() {
+        assert!(parse("(1 + 2").is_err());
+        assert!(parse("1 +").is_err());
+        assert!(parse("1 2 )").is_err());
+    }
+}
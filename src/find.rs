@@ -0,0 +1,146 @@
+// File finding for `find`: fuzzy path matching and size/name/age filters
+use crate::humanize;
+use crate::search::SearchOptions;
+use chrono::{DateTime, Local};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const MAX_RESULTS: usize = 200;
+
+pub struct Found {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+// Every regular file under `root`, skipping what .gitignore and hidden-file rules exclude.
+fn walk(root: &Path) -> Result<Vec<Found>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for entry in SearchOptions::default().walker(root)?.build().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        let path = entry.path().strip_prefix("./").unwrap_or(entry.path()).to_path_buf();
+        files.push(Found { path, size: meta.len(), modified: meta.modified().ok() });
+    }
+    Ok(files)
+}
+
+// Ranks paths by fuzzy score, best first; ties go to the shorter path.
+pub fn fuzzy(root: &Path, query: &str) -> Result<Vec<Found>, Box<dyn Error>> {
+    let matcher = SkimMatcherV2::default().smart_case();
+    let mut scored: Vec<(i64, Found)> = walk(root)?
+        .into_iter()
+        .filter_map(|f| matcher.fuzzy_match(&f.path.to_string_lossy(), query).map(|score| (score, f)))
+        .collect();
+    scored.sort_by(|(a, fa), (b, fb)| {
+        b.cmp(a).then(fa.path.as_os_str().len().cmp(&fb.path.as_os_str().len())).then(fa.path.cmp(&fb.path))
+    });
+    Ok(scored.into_iter().map(|(_, f)| f).collect())
+}
+
+#[derive(Default)]
+pub struct Criteria {
+    pub root: Option<PathBuf>,
+    pub name: Option<GlobMatcher>,
+    pub larger_than: Option<u64>,
+    pub smaller_than: Option<u64>,
+    pub modified_within: Option<Duration>,
+}
+
+impl Criteria {
+    // Parses `named *.rs larger than 10MB modified in the last 2 days in src` in any order.
+    pub fn parse(input: &str) -> Result<Criteria, String> {
+        let mut criteria = Criteria::default();
+        let named = Regex::new(r"\bnamed\s+(\S+)").unwrap();
+        let larger = Regex::new(r"\b(?:larger|bigger)\s+than\s+(\d+(?:\.\d+)?\s*[a-zA-Z]*)").unwrap();
+        let smaller = Regex::new(r"\bsmaller\s+than\s+(\d+(?:\.\d+)?\s*[a-zA-Z]*)").unwrap();
+        let modified =
+            Regex::new(r"\bmodified\s+(?:in\s+the\s+|within\s+the\s+|within\s+)?(?:last|past)?\s*(\d+)?\s*(minute|hour|day|week)s?\b").unwrap();
+        let root = Regex::new(r"\bin\s+(\S+)").unwrap();
+        if let Some(caps) = named.captures(input) {
+            let glob = Glob::new(&caps[1]).map_err(|e| format!("Invalid pattern '{}': {}", &caps[1], e))?;
+            criteria.name = Some(glob.compile_matcher());
+        }
+        let size = |re: &Regex| -> Result<Option<u64>, String> {
+            re.captures(input).map(|caps| humanize::parse_size(&caps[1]).ok_or(format!("Invalid size: {}", &caps[1]))).transpose()
+        };
+        criteria.larger_than = size(&larger)?;
+        criteria.smaller_than = size(&smaller)?;
+        if let Some(caps) = modified.captures(input) {
+            let count: u64 = caps.get(1).map_or(Ok(1), |m| m.as_str().parse()).map_err(|_| "Invalid time span")?;
+            let unit = match &caps[2] {
+                "minute" => 60,
+                "hour" => 3600,
+                "day" => 86400,
+                _ => 7 * 86400,
+            };
+            criteria.modified_within = Some(Duration::from_secs(count * unit));
+        }
+        // The `in the last` of a modified clause is not a directory, so look for `in` afterwards.
+        let rest = [&named, &larger, &smaller, &modified].iter().fold(input.to_string(), |acc, re| re.replace_all(&acc, "").to_string());
+        if let Some(caps) = root.captures(&rest) {
+            criteria.root = Some(PathBuf::from(&caps[1]));
+        }
+        let and = Regex::new(r"\band\b").unwrap();
+        let leftover = [&root, &and].iter().fold(rest, |acc, re| re.replace_all(&acc, "").to_string()).replace([',', ' '], "");
+        if !leftover.is_empty() {
+            return Err(format!("Unrecognized find criteria: {}", leftover));
+        }
+        if criteria.name.is_none() && criteria.larger_than.is_none() && criteria.smaller_than.is_none() && criteria.modified_within.is_none() {
+            return Err("Give at least one of: named <glob>, larger than <size>, smaller than <size>, modified in the last <n> days".into());
+        }
+        Ok(criteria)
+    }
+
+    fn matches(&self, file: &Found, now: SystemTime) -> bool {
+        let name = file.path.file_name().unwrap_or(file.path.as_os_str());
+        self.name.as_ref().is_none_or(|g| g.is_match(name) || g.is_match(&file.path))
+            && self.larger_than.is_none_or(|n| file.size > n)
+            && self.smaller_than.is_none_or(|n| file.size < n)
+            && self.modified_within.is_none_or(|span| {
+                file.modified.and_then(|m| now.duration_since(m).ok()).is_some_and(|age| age <= span)
+            })
+    }
+
+    // Matching files, newest first for age queries, largest first for size queries, else by path.
+    pub fn find(&self) -> Result<Vec<Found>, Box<dyn Error>> {
+        let now = SystemTime::now();
+        let root = self.root.clone().unwrap_or_else(|| PathBuf::from("."));
+        let mut files: Vec<Found> = walk(&root)?.into_iter().filter(|f| self.matches(f, now)).collect();
+        if self.modified_within.is_some() {
+            files.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.path.cmp(&b.path)));
+        } else if self.larger_than.is_some() || self.smaller_than.is_some() {
+            files.sort_by(|a, b| b.size.cmp(&a.size).then(a.path.cmp(&b.path)));
+        } else {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        Ok(files)
+    }
+}
+
+// One line per file: size, modification time and path.
+pub fn format(files: &[Found]) -> String {
+    if files.is_empty() {
+        return "No matching files found.".to_string();
+    }
+    let mut lines: Vec<String> = files
+        .iter()
+        .take(MAX_RESULTS)
+        .map(|f| {
+            let modified = f.modified.map(|m| DateTime::<Local>::from(m).format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".repeat(16));
+            format!("{:>9}  {}  {}", humanize::size(f.size), modified, f.path.display())
+        })
+        .collect();
+    if files.len() > MAX_RESULTS {
+        lines.push(format!("... and {} more", files.len() - MAX_RESULTS));
+    }
+    lines.push(format!("{} file(s)", files.len()));
+    lines.join("\n")
+}
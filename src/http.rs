@@ -0,0 +1,283 @@
+// curl-like `http` command: build, send, save and replay requests
+use reqwest::blocking::Client;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const MAX_BODY_DISPLAY: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Body {
+    Json(Value),
+    Text(String),
+    File(PathBuf),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Auth {
+    Bearer(String),
+    Basic { user: String, password: Option<String> },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub query: Vec<(String, String)>,
+    pub body: Option<Body>,
+    pub auth: Option<Auth>,
+}
+
+// What to do with a parsed request besides sending it.
+pub struct Invocation {
+    pub request: Request,
+    pub save_as: Option<String>,
+    pub collection: Option<PathBuf>,
+}
+
+// Splits on whitespace, keeping single- or double-quoted runs together.
+pub fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_token = false;
+    for c in input.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_token = true;
+            }
+            None if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err("Unterminated quote".to_string());
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+impl Invocation {
+    // `<METHOD> <url> [header "K: V"] [query k=v] [json '<json>' | body "<text>" | body from <file>]
+    // [bearer <token> | basic <user[:password]>] [save as <name> [in <collection>]]`
+    pub fn parse(input: &str) -> Result<Invocation, String> {
+        let tokens = tokenize(input)?;
+        let mut tokens = tokens.into_iter();
+        let method = tokens.next().ok_or("Missing method")?.to_uppercase();
+        if !matches!(method.as_str(), "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD" | "OPTIONS") {
+            return Err(format!("Unsupported method '{}'", method));
+        }
+        let url = tokens.next().ok_or("Missing URL")?;
+        let url = if url.contains("://") { url } else { format!("http://{}", url) };
+        let mut request = Request { method, url, headers: Vec::new(), query: Vec::new(), body: None, auth: None };
+        let mut save_as = None;
+        let mut collection = None;
+        let next = |what: &str, tokens: &mut std::vec::IntoIter<String>| tokens.next().ok_or(format!("Missing value after '{}'", what));
+        while let Some(keyword) = tokens.next() {
+            match keyword.to_lowercase().as_str() {
+                "header" | "-h" => {
+                    let value = next("header", &mut tokens)?;
+                    let (name, value) = value.split_once(':').ok_or(format!("Header '{}' is not 'Name: value'", value))?;
+                    request.headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+                "query" => {
+                    let value = next("query", &mut tokens)?;
+                    let (name, value) = value.split_once('=').ok_or(format!("Query '{}' is not 'name=value'", value))?;
+                    request.query.push((name.to_string(), value.to_string()));
+                }
+                "json" => {
+                    let value = next("json", &mut tokens)?;
+                    let json = serde_json::from_str(&value).map_err(|e| format!("Invalid JSON body: {}", e))?;
+                    request.body = Some(Body::Json(json));
+                }
+                "body" => {
+                    let value = next("body", &mut tokens)?;
+                    request.body = Some(if value == "from" { Body::File(PathBuf::from(next("body from", &mut tokens)?)) } else { Body::Text(value) });
+                }
+                "bearer" => request.auth = Some(Auth::Bearer(next("bearer", &mut tokens)?)),
+                "basic" => {
+                    let value = next("basic", &mut tokens)?;
+                    let (user, password) = match value.split_once(':') {
+                        Some((user, password)) => (user.to_string(), Some(password.to_string())),
+                        None => (value, None),
+                    };
+                    request.auth = Some(Auth::Basic { user, password });
+                }
+                "save" => {
+                    if next("save", &mut tokens)? != "as" {
+                        return Err("Use 'save as <name>'".to_string());
+                    }
+                    save_as = Some(next("save as", &mut tokens)?);
+                }
+                "in" if save_as.is_some() => collection = Some(PathBuf::from(next("in", &mut tokens)?)),
+                other => return Err(format!("Unrecognized http option '{}'", other)),
+            }
+        }
+        Ok(Invocation { request, save_as, collection })
+    }
+}
+
+// Saved requests live in a JSON object keyed by name.
+pub fn default_collection() -> PathBuf {
+    crate::paths::data_dir().join("http_collection.json")
+}
+
+fn load_collection(path: &Path) -> Result<BTreeMap<String, Request>, Box<dyn Error>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save(collection: &Path, name: &str, request: &Request) -> Result<(), Box<dyn Error>> {
+    let mut saved = load_collection(collection)?;
+    saved.insert(name.to_string(), request.clone());
+    if let Some(parent) = collection.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(collection, serde_json::to_string_pretty(&saved)?)?;
+    Ok(())
+}
+
+pub fn load(collection: &Path, name: &str) -> Result<Request, Box<dyn Error>> {
+    let saved = load_collection(collection)?;
+    match saved.get(name) {
+        Some(request) => Ok(request.clone()),
+        None => {
+            let names: Vec<&str> = saved.keys().map(String::as_str).collect();
+            Err(format!("No saved request '{}'. Saved: {}", name, if names.is_empty() { "none".to_string() } else { names.join(", ") }).into())
+        }
+    }
+}
+
+pub fn list(collection: &Path) -> Result<String, Box<dyn Error>> {
+    let saved = load_collection(collection)?;
+    if saved.is_empty() {
+        return Ok(format!("No saved requests in {}", collection.display()));
+    }
+    Ok(saved.iter().map(|(name, r)| format!("{}  {} {}", name, r.method, r.url)).collect::<Vec<_>>().join("\n"))
+}
+
+pub fn send(request: &Request) -> Result<String, Box<dyn Error>> {
+    let method = Method::from_bytes(request.method.as_bytes())?;
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let mut builder = client.request(method, &request.url).query(&request.query);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    builder = match &request.auth {
+        Some(Auth::Bearer(token)) => builder.bearer_auth(token),
+        Some(Auth::Basic { user, password }) => builder.basic_auth(user, password.as_ref()),
+        None => builder,
+    };
+    builder = match &request.body {
+        Some(Body::Json(json)) => builder.json(json),
+        Some(Body::Text(text)) => builder.body(text.clone()),
+        Some(Body::File(path)) => builder.body(std::fs::read(path)?),
+        None => builder,
+    };
+    let started = Instant::now();
+    let response = builder.send()?;
+    let elapsed = started.elapsed();
+    let color = colors_enabled();
+    let status = response.status();
+    let status_color = if status.is_success() { "32" } else if status.is_redirection() { "33" } else { "31" };
+    let mut out = vec![paint(color, status_color, &format!("{:?} {} ({} ms)", response.version(), status, elapsed.as_millis()))];
+    for (name, value) in response.headers() {
+        out.push(format!("{}: {}", paint(color, "36", name.as_str()), value.to_str().unwrap_or("<binary>")));
+    }
+    let is_json = response.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).is_some_and(|t| t.contains("json"));
+    let bytes = response.bytes()?;
+    out.push(String::new());
+    out.push(format_body(&bytes, is_json, color));
+    Ok(out.join("\n"))
+}
+
+fn format_body(bytes: &[u8], is_json: bool, color: bool) -> String {
+    if bytes.is_empty() {
+        return "(empty body)".to_string();
+    }
+    if let Ok(json) = serde_json::from_slice::<Value>(bytes) {
+        if is_json || matches!(json, Value::Object(_) | Value::Array(_)) {
+            let mut out = String::new();
+            write_json(&json, 0, color, &mut out);
+            return out;
+        }
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) if text.len() > MAX_BODY_DISPLAY => {
+            let cut = (0..=MAX_BODY_DISPLAY).rev().find(|&i| text.is_char_boundary(i)).unwrap_or(0);
+            format!("{}\n... ({} bytes total)", &text[..cut], bytes.len())
+        }
+        Ok(text) => text.to_string(),
+        Err(_) => format!("[{} bytes of binary data]", bytes.len()),
+    }
+}
+
+fn colors_enabled() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn paint(color: bool, code: &str, text: &str) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+// Pretty-prints like `serde_json::to_string_pretty`, colouring keys, strings, numbers and literals.
+fn write_json(value: &Value, indent: usize, color: bool, out: &mut String) {
+    let pad = |n: usize| "  ".repeat(n);
+    match value {
+        Value::Null | Value::Bool(_) => out.push_str(&paint(color, "35", &value.to_string())),
+        Value::Number(n) => out.push_str(&paint(color, "33", &n.to_string())),
+        Value::String(_) => out.push_str(&paint(color, "32", &value.to_string())),
+        Value::Array(items) if items.is_empty() => out.push_str("[]"),
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                out.push_str(&pad(indent + 1));
+                write_json(item, indent + 1, color, out);
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&pad(indent));
+            out.push(']');
+        }
+        Value::Object(map) => {
+            out.push_str("{\n");
+            for (i, (key, item)) in map.iter().enumerate() {
+                out.push_str(&pad(indent + 1));
+                out.push_str(&paint(color, "34", &Value::String(key.clone()).to_string()));
+                out.push_str(": ");
+                write_json(item, indent + 1, color, out);
+                out.push_str(if i + 1 < map.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&pad(indent));
+            out.push('}');
+        }
+    }
+}
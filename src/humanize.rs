@@ -0,0 +1,37 @@
+// Human-readable sizes shared by the file listing commands
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+// Decimal units, e.g. `1.5 MB`.
+pub fn size(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+// Parses `10MB`, `1.5 GB`, `512k` or `4KiB`; a bare number is bytes.
+pub fn parse_size(input: &str) -> Option<u64> {
+    let input = input.trim();
+    let split = input.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit = unit.trim().to_lowercase();
+    let binary = unit.contains('i');
+    let base: f64 = if binary { 1024.0 } else { 1000.0 };
+    let power = match unit.trim_end_matches('b').trim_end_matches('i') {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => return None,
+    };
+    Some((number * base.powi(power)) as u64)
+}
@@ -0,0 +1,113 @@
+// Finds the locally installed Jupyter kernels (IRkernel, IJulia, ...) that `run code` snippets
+// can be sent to; the protocol itself is in kernel.rs, behind the `jupyter` feature
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Deserialize)]
+struct SpecFile {
+    argv: Vec<String>,
+    display_name: String,
+    #[serde(default)]
+    language: String,
+    #[serde(default)]
+    env: HashMap<String, String>,
+}
+
+#[cfg_attr(not(feature = "jupyter"), allow(dead_code))]
+pub struct KernelSpec {
+    pub name: String,
+    pub display_name: String,
+    pub language: String,
+    pub dir: PathBuf,
+    pub argv: Vec<String>,
+    pub env: HashMap<String, String>,
+}
+
+// Where Jupyter looks for `kernels/<name>/kernel.json`, most specific first.
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(paths) = std::env::var_os("JUPYTER_PATH") {
+        dirs.extend(std::env::split_paths(&paths));
+    }
+    let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    if let Some(dir) = std::env::var_os("JUPYTER_DATA_DIR") {
+        dirs.push(PathBuf::from(dir));
+    } else if cfg!(target_os = "macos") {
+        dirs.push(home.join("Library").join("Jupyter"));
+    } else if cfg!(target_os = "windows") {
+        dirs.extend(std::env::var_os("APPDATA").map(|d| PathBuf::from(d).join("jupyter")));
+    } else {
+        let data = std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()).map_or(home.join(".local").join("share"), PathBuf::from);
+        dirs.push(data.join("jupyter"));
+    }
+    for prefix in ["CONDA_PREFIX", "VIRTUAL_ENV"] {
+        dirs.extend(std::env::var_os(prefix).map(|p| PathBuf::from(p).join("share").join("jupyter")));
+    }
+    if cfg!(target_os = "windows") {
+        dirs.extend(std::env::var_os("PROGRAMDATA").map(|d| PathBuf::from(d).join("jupyter")));
+    } else {
+        dirs.push(PathBuf::from("/usr/local/share/jupyter"));
+        dirs.push(PathBuf::from("/usr/share/jupyter"));
+    }
+    dirs
+}
+
+// Installed kernels, sorted by name. A kernel found in an earlier directory hides later ones.
+pub fn kernelspecs() -> Vec<KernelSpec> {
+    let mut specs: Vec<KernelSpec> = Vec::new();
+    for dir in data_dirs() {
+        let Ok(entries) = std::fs::read_dir(dir.join("kernels")) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(spec) = std::fs::read_to_string(entry.path().join("kernel.json")).ok().and_then(|s| serde_json::from_str::<SpecFile>(&s).ok()) else {
+                continue;
+            };
+            if spec.argv.is_empty() || specs.iter().any(|s| s.name == name) {
+                continue;
+            }
+            specs.push(KernelSpec { name, display_name: spec.display_name, language: spec.language, dir: entry.path(), argv: spec.argv, env: spec.env });
+        }
+    }
+    specs.sort_by(|a, b| a.name.cmp(&b.name));
+    specs
+}
+
+// By kernel name (`ir`, `julia-1.10`), then by language (`r`, `julia`), then by name prefix.
+pub fn find(lang: &str) -> Option<KernelSpec> {
+    let specs = kernelspecs();
+    let lang = lang.to_lowercase();
+    let index = specs
+        .iter()
+        .position(|s| s.name.to_lowercase() == lang)
+        .or_else(|| specs.iter().position(|s| s.language.to_lowercase() == lang))
+        .or_else(|| specs.iter().position(|s| s.name.to_lowercase().starts_with(&format!("{}-", lang))))?;
+    specs.into_iter().nth(index)
+}
+
+pub fn list() -> String {
+    let specs = kernelspecs();
+    if specs.is_empty() {
+        return "No Jupyter kernels found. Install one (e.g. IRkernel or IJulia) or set JUPYTER_PATH.".to_string();
+    }
+    let width = specs.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    let mut lines = vec!["Jupyter kernels (use the name or language with `run code <name>: ...`):".to_string()];
+    for spec in specs {
+        lines.push(format!("  {:<width$}  {} ({}) {}", spec.name, spec.display_name, spec.language, spec.dir.display(), width = width));
+    }
+    lines.join("\n")
+}
+
+// Runs `code` on the kernel for `spec`, starting it on first use.
+#[cfg(feature = "jupyter")]
+pub use crate::kernel::{execute, shutdown_all};
+
+#[cfg(not(feature = "jupyter"))]
+pub fn execute(spec: &KernelSpec, _code: &str) -> Result<String, String> {
+    Err(format!("Found the {} kernel, but TerminalAI was built without kernel support (cargo feature `jupyter`).", spec.display_name))
+}
+
+#[cfg(not(feature = "jupyter"))]
+pub fn shutdown_all() {}
@@ -0,0 +1,465 @@
+// Jupyter messaging protocol client: starts kernels, runs code on them over ZeroMQ and shows
+// what they print and display
+use crate::artifacts::{self, Workspace};
+use crate::jupyter::KernelSpec;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::io::{self, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+const PROTOCOL_VERSION: &str = "5.3";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+// Longer than the sandbox's: kernels are for notebook-style work. A cell over it is interrupted.
+const EXECUTE_TIMEOUT: Duration = Duration::from_secs(120);
+// How long an interrupted cell gets to stop before the kernel is shut down.
+const INTERRUPT_GRACE: Duration = Duration::from_secs(10);
+
+fn sign(key: &[u8], parts: &[&[u8]]) -> String {
+    if key.is_empty() {
+        return String::new();
+    }
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(key) else {
+        return String::new();
+    };
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn new_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+// Creates the connection file readable by the user only, since it holds the signing key, in
+// a directory of TerminalAI's own rather than the shared temp dir.
+fn write_connection_file(connection: &Value) -> io::Result<PathBuf> {
+    let dir = crate::paths::data_dir().join("kernels");
+    let mut builder = std::fs::DirBuilder::new();
+    let mut options = std::fs::OpenOptions::new();
+    builder.recursive(true);
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+        builder.mode(0o700);
+        options.mode(0o600);
+    }
+    builder.create(&dir)?;
+    let path = dir.join(format!("kernel-{}.json", new_id()));
+    if let Err(e) = options.open(&path).and_then(|mut file| file.write_all(connection.to_string().as_bytes())) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+// A port the kernel can listen on; there is a small window for another program to take it.
+fn free_port() -> io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+struct Message {
+    msg_type: String,
+    parent_id: String,
+    content: Value,
+}
+
+struct Kernel {
+    name: String,
+    display_name: String,
+    shell: zmq::Socket,
+    iopub: zmq::Socket,
+    control: zmq::Socket,
+    child: Child,
+    key: Vec<u8>,
+    session: String,
+    connection_file: PathBuf,
+    // The kernel's working directory; files it writes there are reported as artifacts.
+    workspace: PathBuf,
+    outputs: usize,
+}
+
+// Kernels stay up between snippets so variables carry over, like notebook cells.
+static KERNELS: Mutex<Vec<Kernel>> = Mutex::new(Vec::new());
+
+impl Kernel {
+    fn start(spec: &KernelSpec) -> Result<Kernel, String> {
+        let workspace = artifacts::persistent_dir(&format!("kernel-{}", spec.name)).map_err(|e| format!("Could not create a workspace: {}", e))?;
+        let mut startup = Startup { child: None, connection_file: None, workspace: Some(workspace.clone()) };
+        let key = new_id();
+        let ports: Vec<u16> = (0..5).map(|_| free_port()).collect::<io::Result<_>>().map_err(|e| format!("No free port for the kernel: {}", e))?;
+        let connection = json!({
+            "transport": "tcp",
+            "ip": "127.0.0.1",
+            "shell_port": ports[0],
+            "iopub_port": ports[1],
+            "stdin_port": ports[2],
+            "control_port": ports[3],
+            "hb_port": ports[4],
+            "key": key,
+            "signature_scheme": "hmac-sha256",
+            "kernel_name": spec.name,
+        });
+        let connection_file = write_connection_file(&connection).map_err(|e| format!("Could not write the kernel connection file: {}", e))?;
+        startup.connection_file = Some(connection_file.clone());
+        let argv: Vec<String> = spec
+            .argv
+            .iter()
+            .map(|a| a.replace("{connection_file}", &connection_file.to_string_lossy()).replace("{resource_dir}", &spec.dir.to_string_lossy()))
+            .collect();
+        // Kernels log to their own stdout and stderr; what user code prints arrives on iopub.
+        let child = Command::new(&argv[0])
+            .args(&argv[1..])
+            .envs(&spec.env)
+            .env("JPY_PARENT_PID", std::process::id().to_string())
+            .current_dir(&workspace)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Could not start the {} kernel ({}): {}", spec.display_name, argv[0], e))?;
+        startup.child = Some(child);
+        // Connecting does not wait for the kernel to listen; wait_ready finds out when it does.
+        let connect = |kind: zmq::SocketType, port: u16| -> Result<zmq::Socket, zmq::Error> {
+            let socket = zmq::Context::new().socket(kind)?;
+            socket.set_linger(0)?;
+            socket.connect(&format!("tcp://127.0.0.1:{}", port))?;
+            Ok(socket)
+        };
+        let sockets = (|| {
+            let iopub = connect(zmq::SUB, ports[1])?;
+            iopub.set_subscribe(b"")?;
+            Ok((connect(zmq::DEALER, ports[0])?, iopub, connect(zmq::DEALER, ports[3])?))
+        })();
+        let (shell, iopub, control) = sockets.map_err(|e: zmq::Error| format!("Could not connect to the {} kernel: {}", spec.display_name, e))?;
+        let (child, connection_file, workspace) = startup.finish();
+        let mut kernel = Kernel {
+            name: spec.name.clone(),
+            display_name: spec.display_name.clone(),
+            shell,
+            iopub,
+            control,
+            child,
+            key: key.into_bytes(),
+            session: new_id(),
+            connection_file,
+            workspace,
+            outputs: 0,
+        };
+        if let Err(e) = kernel.wait_ready() {
+            kernel.shutdown();
+            return Err(e);
+        }
+        Ok(kernel)
+    }
+
+    // Repeats kernel_info requests until one is answered on shell and iopub has delivered a
+    // message: SUB sockets drop whatever is published before the subscription is in place.
+    fn wait_ready(&mut self) -> Result<(), String> {
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        let (mut replied, mut subscribed) = (false, false);
+        while !(replied && subscribed) {
+            if Instant::now() > deadline {
+                return Err(format!("The {} kernel did not respond within {}s.", self.display_name, STARTUP_TIMEOUT.as_secs()));
+            }
+            if let Ok(Some(status)) = self.child.try_wait() {
+                return Err(format!("The {} kernel exited during startup ({}).", self.display_name, status));
+            }
+            self.send(Channel::Shell, "kernel_info_request", json!({}))?;
+            let round = Instant::now() + Duration::from_millis(500);
+            while Instant::now() < round && !(replied && subscribed) {
+                replied |= self.recv(Channel::Shell, Duration::from_millis(50)).is_some_and(|m| m.msg_type == "kernel_info_reply");
+                subscribed |= self.recv(Channel::IoPub, Duration::from_millis(50)).is_some();
+            }
+        }
+        // Drain what is left from the extra requests.
+        while self.recv(Channel::Shell, Duration::from_millis(50)).is_some() || self.recv(Channel::IoPub, Duration::from_millis(50)).is_some() {}
+        Ok(())
+    }
+
+    fn send(&self, channel: Channel, msg_type: &str, content: Value) -> Result<String, String> {
+        let id = new_id();
+        let header = json!({
+            "msg_id": id,
+            "session": self.session,
+            "username": "terminalai",
+            "date": chrono::Utc::now().to_rfc3339(),
+            "msg_type": msg_type,
+            "version": PROTOCOL_VERSION,
+        })
+        .to_string();
+        let parts = [header, "{}".to_string(), "{}".to_string(), content.to_string()];
+        let signature = sign(&self.key, &parts.iter().map(|p| p.as_bytes()).collect::<Vec<_>>());
+        let mut frames = vec![DELIMITER.to_vec(), signature.into_bytes()];
+        frames.extend(parts.into_iter().map(String::into_bytes));
+        let socket = match channel {
+            Channel::Shell => &self.shell,
+            Channel::Control => &self.control,
+            Channel::IoPub => return Err("cannot send on iopub".to_string()),
+        };
+        socket.send_multipart(frames, 0).map_err(|e| format!("Could not send to the kernel: {}", e))?;
+        Ok(id)
+    }
+
+    // The next correctly signed message on `channel`, or None after `timeout`.
+    fn recv(&self, channel: Channel, timeout: Duration) -> Option<Message> {
+        let socket = match channel {
+            Channel::Shell => &self.shell,
+            Channel::Control => &self.control,
+            Channel::IoPub => &self.iopub,
+        };
+        if socket.poll(zmq::POLLIN, timeout.as_millis() as i64).ok()? == 0 {
+            return None;
+        }
+        let frames = socket.recv_multipart(zmq::DONTWAIT).ok()?;
+        let at = frames.iter().position(|f| f == DELIMITER)?;
+        let parts = frames.get(at + 1..at + 6)?;
+        let signed: Vec<&[u8]> = parts[1..].iter().map(|p| p.as_slice()).collect();
+        if sign(&self.key, &signed).as_bytes() != parts[0] {
+            return None;
+        }
+        let header: Value = serde_json::from_slice(&parts[1]).ok()?;
+        let parent: Value = serde_json::from_slice(&parts[2]).ok()?;
+        Some(Message {
+            msg_type: header["msg_type"].as_str().unwrap_or_default().to_string(),
+            parent_id: parent["msg_id"].as_str().unwrap_or_default().to_string(),
+            content: serde_json::from_slice(&parts[4]).unwrap_or(Value::Null),
+        })
+    }
+
+    // Sends the code, prints streams and rich outputs as they arrive and returns once the kernel
+    // is idle again, with the reply status.
+    fn execute(&mut self, code: &str) -> Result<String, String> {
+        let id = self.send(
+            Channel::Shell,
+            "execute_request",
+            json!({ "code": code, "silent": false, "store_history": true, "user_expressions": {}, "allow_stdin": false, "stop_on_error": true }),
+        )?;
+        let started = Instant::now();
+        let mut interrupted = false;
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let mut at_line_start = true;
+        loop {
+            if !interrupted && started.elapsed() > EXECUTE_TIMEOUT {
+                self.send(Channel::Control, "interrupt_request", json!({}))?;
+                interrupted = true;
+            }
+            if started.elapsed() > EXECUTE_TIMEOUT + INTERRUPT_GRACE {
+                if !at_line_start {
+                    println!();
+                }
+                return Err(format!(
+                    "The {} kernel did not stop within {}s of being interrupted; shut it down (its variables are lost).",
+                    self.display_name,
+                    INTERRUPT_GRACE.as_secs()
+                ));
+            }
+            if let Ok(Some(status)) = self.child.try_wait() {
+                return Err(format!("The {} kernel died ({}).", self.display_name, status));
+            }
+            let Some(message) = self.recv(Channel::IoPub, Duration::from_millis(200)) else {
+                continue;
+            };
+            if message.parent_id != id {
+                continue;
+            }
+            let content = &message.content;
+            let text = match message.msg_type.as_str() {
+                "status" if content["execution_state"] == "idle" => break,
+                "stream" => {
+                    let text = content["text"].as_str().unwrap_or_default();
+                    if content["name"] == "stderr" {
+                        stderr.push_str(text);
+                        show(text, true);
+                    } else {
+                        stdout.push_str(text);
+                        show(text, false);
+                    }
+                    at_line_start = text.ends_with('\n');
+                    continue;
+                }
+                "execute_result" | "display_data" => self.rich_output(&content["data"]),
+                "error" => {
+                    let traceback: Vec<&str> = content["traceback"].as_array().into_iter().flatten().filter_map(|l| l.as_str()).collect();
+                    let text = if traceback.is_empty() { format!("{}: {}", content["ename"].as_str().unwrap_or("Error"), content["evalue"].as_str().unwrap_or_default()) } else { traceback.join("\n") };
+                    stderr.push_str(&text);
+                    show(&format!("{}\n", text), true);
+                    at_line_start = true;
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(text) = text {
+                if !at_line_start {
+                    println!();
+                }
+                println!("{}", text.trim_end());
+                stdout.push_str(&text);
+                at_line_start = true;
+            }
+        }
+        if !at_line_start {
+            println!();
+        }
+        crate::shell::set_last_output(&stdout, &stderr);
+        let reply = self.recv(Channel::Shell, Duration::from_secs(5));
+        let status = reply.as_ref().and_then(|r| r.content["status"].as_str()).unwrap_or("ok");
+        let status = if interrupted { format!("interrupted after {}s", EXECUTE_TIMEOUT.as_secs()) } else { status.to_string() };
+        let count = reply.as_ref().and_then(|r| r.content["execution_count"].as_u64()).map(|n| format!(" [{}]", n)).unwrap_or_default();
+        Ok(format!("[{}{}: {} in {:.2}s]", self.display_name, count, status, started.elapsed().as_secs_f64()))
+    }
+
+    // The text to show for a mime bundle. Images are saved into the workspace, where the
+    // artifact report previews them.
+    fn rich_output(&mut self, data: &Value) -> Option<String> {
+        for (mime, extension) in [("image/png", "png"), ("image/jpeg", "jpg"), ("image/gif", "gif")] {
+            let Some(encoded) = data[mime].as_str() else {
+                continue;
+            };
+            // Base64 in the notebook format may be wrapped over several lines.
+            let cleaned: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+            let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(cleaned) else {
+                continue;
+            };
+            self.outputs += 1;
+            let path = self.workspace.join(format!("output-{}.{}", self.outputs, extension));
+            return match std::fs::write(&path, bytes) {
+                Ok(()) => Some(format!("[{} saved as {}]", mime, path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned()))),
+                Err(e) => Some(format!("[{} could not be saved: {}]", mime, e)),
+            };
+        }
+        if let Some(svg) = data["image/svg+xml"].as_str() {
+            self.outputs += 1;
+            let path = self.workspace.join(format!("output-{}.svg", self.outputs));
+            return Some(match std::fs::write(&path, svg) {
+                Ok(()) => format!("[image/svg+xml saved as output-{}.svg]", self.outputs),
+                Err(e) => format!("[image/svg+xml could not be saved: {}]", e),
+            });
+        }
+        // HTML first: for HTML-only displays text/plain is just a placeholder repr.
+        if let Some(html) = data["text/html"].as_str() {
+            return Some(html_to_text(html));
+        }
+        if let Some(markdown) = data["text/markdown"].as_str() {
+            return Some(markdown.to_string());
+        }
+        data["text/plain"].as_str().map(str::to_string).or_else(|| data.as_object().map(|o| format!("[{}]", o.keys().cloned().collect::<Vec<_>>().join(", "))))
+    }
+
+    fn shutdown(mut self) {
+        let _ = self.send(Channel::Control, "shutdown_request", json!({ "restart": false }));
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline && !matches!(self.child.try_wait(), Ok(Some(_))) {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.connection_file);
+        artifacts::release(&self.workspace);
+    }
+}
+
+// Until a started kernel is handed over, kills it, removes its connection file and gives up its
+// workspace when start returns early.
+struct Startup {
+    child: Option<Child>,
+    connection_file: Option<PathBuf>,
+    workspace: Option<PathBuf>,
+}
+
+impl Startup {
+    fn finish(mut self) -> (Child, PathBuf, PathBuf) {
+        (self.child.take().expect("kernel started"), self.connection_file.take().expect("connection file written"), self.workspace.take().expect("workspace created"))
+    }
+}
+
+impl Drop for Startup {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Some(file) = &self.connection_file {
+            let _ = std::fs::remove_file(file);
+        }
+        if let Some(workspace) = &self.workspace {
+            artifacts::release(workspace);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Channel {
+    Shell,
+    Control,
+    IoPub,
+}
+
+fn show(text: &str, is_stderr: bool) {
+    if is_stderr {
+        let color = io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        let _ = if color { write!(io::stderr(), "\x1b[31m{}\x1b[0m", text) } else { write!(io::stderr(), "{}", text) };
+    } else {
+        print!("{}", text);
+        let _ = io::stdout().flush();
+    }
+}
+
+// Enough HTML rendering for the tables and summaries kernels produce (e.g. data frames).
+pub fn html_to_text(html: &str) -> String {
+    let hidden = regex::Regex::new(r"(?is)<(script|style|head)\b.*?</(script|style|head)>").unwrap();
+    let breaks = regex::Regex::new(r"(?i)<br\s*/?>|</(p|div|tr|li|h[1-6]|table|thead|tbody|pre)>").unwrap();
+    let cells = regex::Regex::new(r"(?i)</t[dh]>").unwrap();
+    let items = regex::Regex::new(r"(?i)<li\b[^>]*>").unwrap();
+    let tags = regex::Regex::new(r"<[^>]+>").unwrap();
+    let text = hidden.replace_all(html, "");
+    let text = breaks.replace_all(&text, "\n");
+    let text = cells.replace_all(&text, "\t");
+    let text = items.replace_all(&text, "- ");
+    let text = tags.replace_all(&text, "");
+    let text = text.replace("&nbsp;", " ").replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&#39;", "'").replace("&amp;", "&");
+    let lines: Vec<&str> = text.lines().map(|l| l.trim_end_matches(['\t', ' '])).filter(|l| !l.trim().is_empty()).collect();
+    lines.join("\n")
+}
+
+// Runs `code` on the kernel for `spec`, starting it on first use.
+pub fn execute(spec: &KernelSpec, code: &str) -> Result<String, String> {
+    let mut kernels = KERNELS.lock().map_err(|_| "kernel list lock poisoned".to_string())?;
+    let index = match kernels.iter().position(|k| k.name == spec.name) {
+        Some(index) => index,
+        None => {
+            println!("Starting the {} kernel...", spec.display_name);
+            kernels.push(Kernel::start(spec)?);
+            kernels.len() - 1
+        }
+    };
+    let workspace = Workspace::reuse(kernels[index].workspace.clone()).map_err(|e| e.to_string())?;
+    let result = kernels[index].execute(code);
+    if result.is_err() {
+        kernels.remove(index).shutdown();
+    }
+    let summary = result?;
+    let artifacts = workspace.collect();
+    if artifacts.is_empty() {
+        return Ok(summary);
+    }
+    Ok(format!("{}\n{}", artifacts::report(&workspace.dir, &artifacts), summary))
+}
+
+// Stops every kernel started this session; called when TerminalAI exits.
+pub fn shutdown_all() {
+    if let Ok(mut kernels) = KERNELS.lock() {
+        for kernel in kernels.drain(..) {
+            kernel.shutdown();
+        }
+    }
+}
\ No newline at end of file
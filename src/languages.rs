@@ -1,4 +1,330 @@
-// Placeholder for multi-language code execution
-pub fn execute_code(_lang: &str, _code: &str) -> Result<String, String> {
-    Err("Multi-language execution not yet implemented".to_string())
-} 
\ No newline at end of file
+// `run code` and `run file` execution: interpreted languages directly, compiled ones through a
+// temp project with builds cached by source hash. Builds and programs both run under the sandbox
+// limits, and snippets run in a per-run workspace whose new files are reported as artifacts.
+use crate::artifacts::{self, Workspace};
+use crate::{explain, jupyter};
+use crate::sandbox::{self, Limits, Outcome};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SUPPORTED: &str = "python, javascript, bash, rust, c, c++, go";
+
+struct Interpreter {
+    names: &'static [&'static str],
+    program: &'static str,
+    // Takes the code as the next argument, keeping stdin free for the program.
+    inline: &'static str,
+    extensions: &'static [&'static str],
+}
+
+const INTERPRETERS: &[Interpreter] = &[
+    Interpreter { names: &["python", "py"], program: "python3", inline: "-c", extensions: &["py"] },
+    Interpreter { names: &["javascript", "js", "node"], program: "node", inline: "-e", extensions: &["js", "mjs", "cjs"] },
+    Interpreter { names: &["bash", "sh"], program: "bash", inline: "-c", extensions: &["sh", "bash"] },
+];
+
+struct Compiled {
+    names: &'static [&'static str],
+    extensions: &'static [&'static str],
+    name: &'static str,
+    compiler: &'static str,
+    file: &'static str,
+    // Snippets in which this pattern finds no entry point are wrapped in `header` and `footer`
+    // to make a whole program.
+    entry: &'static str,
+    header: &'static str,
+    footer: &'static str,
+}
+
+const RUST: Compiled = Compiled { names: &["rust", "rs"], extensions: &["rs"], name: "Rust", compiler: "rustc", file: "main.rs", entry: r"\bfn\s+main\s*\(", header: "fn main() {\n", footer: "\n}\n" };
+const C: Compiled = Compiled {
+    names: &["c"],
+    extensions: &["c"],
+    name: "C",
+    compiler: "cc",
+    file: "main.c",
+    entry: r"\bint\s+main\s*\(",
+    header: "#include <stdio.h>\n#include <stdlib.h>\n#include <string.h>\n#include <math.h>\n\nint main(void) {\n",
+    footer: "\nreturn 0;\n}\n",
+};
+const CPP: Compiled = Compiled {
+    names: &["c++", "cpp", "cxx"],
+    extensions: &["cpp", "cc", "cxx"],
+    name: "C++",
+    compiler: "c++",
+    file: "main.cpp",
+    entry: r"\bint\s+main\s*\(",
+    header: "#include <iostream>\n#include <string>\n#include <vector>\n#include <map>\n#include <algorithm>\nusing namespace std;\n\nint main() {\n",
+    footer: "\nreturn 0;\n}\n",
+};
+// The trailing `fmt` use keeps the import legal for snippets that never print.
+const GO: Compiled = Compiled {
+    names: &["go", "golang"],
+    extensions: &["go"],
+    name: "Go",
+    compiler: "go",
+    file: "main.go",
+    entry: r"(?m)^\s*package\s",
+    header: "package main\n\nimport \"fmt\"\n\nfunc main() {\n",
+    footer: "\n}\n\nvar _ = fmt.Sprint\n",
+};
+
+// Whether `code` defines its own entry point, as opposed to merely mentioning `main(` somewhere.
+fn has_entry(spec: &Compiled, code: &str) -> bool {
+    Regex::new(spec.entry).unwrap().is_match(code)
+}
+
+const COMPILED: &[&Compiled] = &[&RUST, &C, &CPP, &GO];
+
+// What `run file` passes to the program, e.g.
+// `with args -v "two words" stdin from input.txt with DEBUG=1 in /tmp`.
+#[derive(Default)]
+pub struct RunOptions {
+    pub args: Vec<String>,
+    pub stdin: Option<Vec<u8>>,
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<PathBuf>,
+}
+
+impl RunOptions {
+    // Parses `with args ...` (shell-style quoting; it ends at the next option), `stdin from <file>`,
+    // `stdin from last output`, `with NAME=value ...` and `in <dir>`.
+    pub fn parse(options: &str) -> Result<RunOptions, String> {
+        let mut opts = RunOptions::default();
+        let args = Regex::new(r"\bwith\s+args?\s+").unwrap();
+        let next_option = Regex::new(r"(?:^|\s)(?:stdin\s+from|in|with)\s").unwrap();
+        let stdin = Regex::new(r#"\bstdin\s+from\s+(?:(?P<last>last\s+output)|"(?P<quoted>[^"]+)"|(?P<file>\S+))"#).unwrap();
+        let env = Regex::new(r"\bwith\s+(?P<vars>(?:[A-Za-z_]\w*=\S*(?:\s+|$))+)").unwrap();
+        let dir = Regex::new(r#"\bin\s+(?:"(?P<quoted>[^"]+)"|(?P<dir>\S+))"#).unwrap();
+        let mut rest = options.to_string();
+        if let Some(start) = args.find(options) {
+            let tail = &options[start.end()..];
+            let end = next_option.find(tail).map_or(tail.len(), |m| m.start());
+            for token in explain::lex(&tail[..end])? {
+                match token {
+                    explain::Token::Word(word) => opts.args.push(word.text),
+                    _ => return Err(format!("Quote shell operators in arguments: {}", &tail[..end])),
+                }
+            }
+            rest = format!("{} {}", &options[..start.start()], &tail[end..]);
+        }
+        if let Some(caps) = stdin.captures(&rest) {
+            opts.stdin = Some(if caps.name("last").is_some() {
+                crate::shell::last_output().ok_or("No command has been run yet, so there is no last output.")?.0.into_bytes()
+            } else {
+                let file = caps.name("quoted").or(caps.name("file")).map_or("", |m| m.as_str());
+                std::fs::read(file).map_err(|e| format!("Could not read {}: {}", file, e))?
+            });
+        }
+        if let Some(caps) = env.captures(&rest) {
+            for var in caps["vars"].split_whitespace() {
+                let (name, value) = var.split_once('=').unwrap_or((var, ""));
+                opts.env.push((name.to_string(), value.to_string()));
+            }
+        }
+        if let Some(caps) = dir.captures(&rest) {
+            let path = PathBuf::from(caps.name("quoted").or(caps.name("dir")).map_or("", |m| m.as_str()));
+            if !path.is_dir() {
+                return Err(format!("'{}' is not a directory", path.display()));
+            }
+            opts.cwd = Some(path);
+        }
+        let rest = [&stdin, &env, &dir].iter().fold(rest, |acc, re| re.replace_all(&acc, "").to_string());
+        let rest = Regex::new(r"\band\b").unwrap().replace_all(&rest, "").replace([',', ' '], "");
+        if !rest.is_empty() {
+            return Err(format!("Unrecognized run option(s): {}", rest));
+        }
+        Ok(opts)
+    }
+
+    fn apply(&self, command: &mut Command) {
+        command.args(&self.args);
+        command.envs(self.env.iter().map(|(k, v)| (k, v)));
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+    }
+}
+
+fn build_command(spec: &Compiled, project: &Path) -> Command {
+    let mut command = Command::new(spec.compiler);
+    match spec.compiler {
+        "rustc" => command.args(["--edition", "2021", "--error-format=short", "-o", "program", spec.file]),
+        "cc" => command.args(["-std=c11", "-O1", "-fno-diagnostics-show-caret", "-o", "program", spec.file, "-lm"]),
+        "c++" => command.args(["-std=c++17", "-O1", "-fno-diagnostics-show-caret", "-o", "program", spec.file]),
+        _ => command.args(["build", "-o", "program", spec.file]),
+    };
+    command.current_dir(project);
+    command
+}
+
+// Identifies the installed toolchain, so upgrading it invalidates cached builds.
+fn compiler_version(spec: &Compiled) -> String {
+    let flag = if spec.compiler == "go" { "version" } else { "--version" };
+    Command::new(spec.compiler).arg(flag).output().map(|o| String::from_utf8_lossy(&o.stdout).into_owned()).unwrap_or_default()
+}
+
+// Points diagnostics at the user's code: with a two-line header, `main.rs:3:5` becomes
+// `snippet:1:5` (or `script.rs:1:5` for `run file`).
+fn map_lines(diagnostics: &str, spec: &Compiled, label: &str, offset: usize, code_lines: usize) -> String {
+    let Ok(location) = Regex::new(&format!(r"(?:\./)?{}:(\d+)(:\d+)?", regex::escape(spec.file))) else {
+        return diagnostics.to_string();
+    };
+    location
+        .replace_all(diagnostics, |c: &regex::Captures| {
+            let column = c.get(2).map_or("", |m| m.as_str());
+            match c[1].parse::<usize>().ok().and_then(|line| line.checked_sub(offset)) {
+                Some(line) if (1..=code_lines).contains(&line) => format!("{}:{}{}", label, line, column),
+                _ => format!("{} (generated wrapper)", label),
+            }
+        })
+        .into_owned()
+}
+
+// Returns the cached or freshly built binary and whether it came from the cache.
+fn compile(spec: &Compiled, code: &str, label: &str) -> Result<(PathBuf, bool), String> {
+    if !explain::on_path(spec.compiler) {
+        return Err(format!("`{}` was not found on PATH; it is needed to run {} code.", spec.compiler, spec.name));
+    }
+    let wrapped = !has_entry(spec, code);
+    let (source, offset) = if wrapped { (format!("{}{}{}", spec.header, code, spec.footer), spec.header.matches('\n').count()) } else { (code.to_string(), 0) };
+    let hash = blake3::hash(format!("{}\0{}\0{}", spec.file, compiler_version(spec), source).as_bytes()).to_hex();
+    let cache = crate::paths::data_dir().join("builds");
+    let binary = cache.join(format!("{}{}", &hash[..32], std::env::consts::EXE_SUFFIX));
+    if binary.is_file() {
+        return Ok((binary, true));
+    }
+    let project = std::env::temp_dir().join(format!("terminalai-build-{}-{}", &hash[..16], std::process::id()));
+    let built = (|| -> Result<(), String> {
+        std::fs::create_dir_all(&project).map_err(|e| format!("Could not create {}: {}", project.display(), e))?;
+        std::fs::write(project.join(spec.file), &source).map_err(|e| format!("Could not write {}: {}", spec.file, e))?;
+        let limits = Limits::build();
+        let outcome = sandbox::run(build_command(spec, &project), None, &limits).map_err(|e| format!("Could not run {}: {}", spec.compiler, e))?;
+        if !outcome.success() {
+            let diagnostics = map_lines(format!("{}{}", outcome.stdout, outcome.stderr).trim_end(), spec, label, offset, code.lines().count().max(1));
+            let reason = outcome.failure(&limits).filter(|_| outcome.timed_out || outcome.signal.is_some());
+            return Err(format!("Compilation failed:\n{}{}", diagnostics, reason.map(|r| format!("\n{}", r)).unwrap_or_default()));
+        }
+        std::fs::create_dir_all(&cache).map_err(|e| format!("Could not create {}: {}", cache.display(), e))?;
+        // Copied rather than renamed: the cache may be on another filesystem than the temp dir.
+        std::fs::copy(project.join(format!("program{}", std::env::consts::EXE_SUFFIX)), &binary).map_err(|e| format!("Could not cache the build: {}", e))?;
+        Ok(())
+    })();
+    let _ = std::fs::remove_dir_all(&project);
+    built.map(|_| (binary, false))
+}
+
+fn report(outcome: &Outcome, limits: &Limits, note: Option<&str>) -> String {
+    crate::shell::set_last_output(&outcome.stdout, &outcome.stderr);
+    crate::usage::record(outcome);
+    let mut sections = Vec::new();
+    sections.extend(note.map(str::to_string));
+    if !outcome.stdout.is_empty() {
+        sections.push(outcome.stdout.trim_end().to_string());
+    }
+    if !outcome.stderr.trim().is_empty() {
+        sections.push(format!("[stderr]: {}", outcome.stderr.trim_end()));
+    }
+    if outcome.truncated {
+        sections.push("[output truncated]".to_string());
+    }
+    sections.extend(outcome.failure(limits));
+    if crate::usage::profiling() {
+        sections.push(outcome.usage.report());
+    }
+    sections.join("\n")
+}
+
+fn launch(mut command: Command, opts: &RunOptions, note: Option<&str>) -> Result<String, String> {
+    let limits = Limits::program();
+    opts.apply(&mut command);
+    let program = command.get_program().to_string_lossy().into_owned();
+    let outcome = sandbox::run(command, opts.stdin.as_deref(), &limits).map_err(|e| format!("Could not run {}: {}", program, e))?;
+    Ok(report(&outcome, &limits, note))
+}
+
+// Runs a snippet in a fresh workspace and reports the files it leaves there.
+fn launch_in_workspace(command: Command, note: Option<&str>) -> Result<String, String> {
+    let workspace = Workspace::create().map_err(|e| format!("Could not create a workspace: {}", e))?;
+    let opts = RunOptions { cwd: Some(workspace.dir.clone()), ..RunOptions::default() };
+    let output = launch(command, &opts, note);
+    let artifacts = workspace.collect();
+    let output = output?;
+    if artifacts.is_empty() {
+        return Ok(output);
+    }
+    let listing = artifacts::report(&workspace.dir, &artifacts);
+    Ok(if output.is_empty() { listing } else { format!("{}\n{}", output, listing) })
+}
+
+pub fn execute_code(lang: &str, code: &str) -> Result<String, String> {
+    let lang = lang.to_lowercase();
+    if let Some(spec) = COMPILED.iter().find(|c| c.names.contains(&lang.as_str())) {
+        let (binary, cached) = compile(spec, code, "snippet")?;
+        return launch_in_workspace(Command::new(binary), cached.then_some("(cached build)"));
+    }
+    let Some(interpreter) = INTERPRETERS.iter().find(|i| i.names.contains(&lang.as_str())) else {
+        // Anything else goes to an installed Jupyter kernel for it, if there is one.
+        return match jupyter::find(&lang) {
+            Some(spec) => jupyter::execute(&spec, code),
+            None => Err(format!("Unsupported language. Supported: {}, or any language with an installed Jupyter kernel (see `list kernels`).", SUPPORTED)),
+        };
+    };
+    let mut command = Command::new(interpreter.program);
+    command.args([interpreter.inline, code]);
+    launch_in_workspace(command, None)
+}
+
+// `#!/usr/bin/env python3`, `#!/bin/bash -e` and the like; Rust's `#![...]` attributes are not one.
+fn shebang(source: &str) -> Option<Vec<String>> {
+    let line = source.strip_prefix("#!").filter(|s| !s.starts_with('['))?.lines().next()?;
+    let mut words: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+    // `env -S` splits the rest itself; we already have.
+    if words.first().is_some_and(|w| w.ends_with("/env")) && words.get(1).is_some_and(|w| w == "-S") {
+        words.remove(1);
+    }
+    (!words.is_empty()).then_some(words)
+}
+
+// Runs a script or source file, picking the interpreter from its #! line or its extension.
+pub fn execute_file(path: &str, opts: &RunOptions) -> Result<String, String> {
+    let path = Path::new(path);
+    let source = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    // Absolute, so `in <dir>` cannot change which file runs.
+    let path = path.canonicalize().map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    if let Some(spec) = COMPILED.iter().find(|c| c.extensions.contains(&extension.as_str())) {
+        let label = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        let (binary, cached) = compile(spec, &source, &label)?;
+        return launch(Command::new(binary), opts, cached.then_some("(cached build)"));
+    }
+    let mut command = if let Some(words) = shebang(&source) {
+        let mut command = Command::new(&words[0]);
+        command.args(&words[1..]);
+        command
+    } else if let Some(interpreter) = INTERPRETERS.iter().find(|i| i.extensions.contains(&extension.as_str())) {
+        Command::new(interpreter.program)
+    } else {
+        return Err(format!("Cannot tell what language {} is in. Give it a #! line or one of the extensions for {}.", path.display(), SUPPORTED));
+    };
+    command.arg(&path);
+    launch(command, opts, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_wraps_snippets_without_an_entry_point() {
+        assert!(has_entry(&C, "#include <stdio.h>\nint main(void) { return 0; }"));
+        assert!(has_entry(&CPP, "int main (int argc, char **argv) {}"));
+        assert!(!has_entry(&C, "printf(\"main()\\n\");"));
+        assert!(!has_entry(&C, "int domain(int x) { return x; }"));
+        assert!(has_entry(&RUST, "fn main() {}"));
+        assert!(!has_entry(&RUST, "println!(\"fn main\");"));
+        assert!(has_entry(&GO, "package main\n\nfunc main() {}"));
+        assert!(!has_entry(&GO, "fmt.Println(\"package delivered\")"));
+    }
+}
@@ -0,0 +1,224 @@
+// Directory listings for `list files in` and `show tree of`
+use crate::humanize;
+use chrono::{DateTime, Local};
+use regex::Regex;
+use std::error::Error;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+const MAX_TREE_LINES: usize = 2000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Time,
+}
+
+pub struct ListOptions {
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub hidden: bool,
+    pub long: bool,
+    pub depth: Option<usize>,
+}
+
+impl ListOptions {
+    // Parses `sorted by size`, `reversed`, `including hidden`, `names only` and `depth 2`.
+    pub fn parse(options: &str) -> Result<ListOptions, String> {
+        let sort = Regex::new(r"\bsorted\s+by\s+(\w+)").unwrap();
+        let reverse = Regex::new(r"\b(?:reversed|in\s+reverse)\b").unwrap();
+        let hidden = Regex::new(r"\b(?:including|with|showing)\s+hidden(?:\s+files)?\b").unwrap();
+        let short = Regex::new(r"\b(?:names\s+only|short)\b").unwrap();
+        let depth = Regex::new(r"\b(?:depth|max\s+depth)\s+(\d+)\b").unwrap();
+        let mut opts = ListOptions { sort: SortKey::Name, reverse: false, hidden: false, long: true, depth: None };
+        if let Some(caps) = sort.captures(options) {
+            opts.sort = match caps[1].to_lowercase().as_str() {
+                "name" => SortKey::Name,
+                "size" => SortKey::Size,
+                "time" | "date" | "mtime" | "modified" => SortKey::Time,
+                other => return Err(format!("Cannot sort by '{}'; use name, size or time", other)),
+            };
+        }
+        opts.reverse = reverse.is_match(options);
+        opts.hidden = hidden.is_match(options);
+        opts.long = !short.is_match(options);
+        if let Some(caps) = depth.captures(options) {
+            opts.depth = caps[1].parse().ok();
+        }
+        let rest = [&sort, &reverse, &hidden, &short, &depth].iter().fold(options.to_string(), |acc, re| re.replace_all(&acc, "").to_string());
+        let rest = rest.replace([',', ' '], "");
+        if !rest.is_empty() {
+            return Err(format!("Unrecognized option(s): {}", rest));
+        }
+        Ok(opts)
+    }
+}
+
+struct Entry {
+    name: String,
+    path: PathBuf,
+    // Metadata of the entry itself; symlinks are not followed.
+    meta: Metadata,
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+fn read_entries(dir: &Path, opts: &ListOptions) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !opts.hidden && is_hidden(&name) {
+            continue;
+        }
+        let Ok(meta) = fs::symlink_metadata(entry.path()) else { continue };
+        entries.push(Entry { name, path: entry.path(), meta });
+    }
+    match opts.sort {
+        SortKey::Name => entries.sort_by_key(|e| e.name.to_lowercase()),
+        SortKey::Size => entries.sort_by(|a, b| b.meta.len().cmp(&a.meta.len()).then(a.name.cmp(&b.name))),
+        SortKey::Time => entries.sort_by(|a, b| b.meta.modified().ok().cmp(&a.meta.modified().ok()).then(a.name.cmp(&b.name))),
+    }
+    if opts.reverse {
+        entries.reverse();
+    }
+    Ok(entries)
+}
+
+// `ls -F` style markers: `dir/`, `link@ -> target`, `program*`.
+fn display_name(entry: &Entry) -> String {
+    let kind = entry.meta.file_type();
+    if kind.is_dir() {
+        format!("{}/", entry.name)
+    } else if kind.is_symlink() {
+        let target = fs::read_link(&entry.path).map(|t| t.display().to_string()).unwrap_or_else(|_| "?".to_string());
+        format!("{}@ -> {}", entry.name, target)
+    } else if is_executable(&entry.meta) {
+        format!("{}*", entry.name)
+    } else {
+        entry.name.clone()
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn permissions(meta: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = meta.permissions().mode();
+    let kind = if meta.is_symlink() {
+        'l'
+    } else if meta.is_dir() {
+        'd'
+    } else {
+        '-'
+    };
+    let bits = "rwxrwxrwx".chars().enumerate().map(|(i, c)| if mode & (0o400 >> i) != 0 { c } else { '-' });
+    std::iter::once(kind).chain(bits).collect()
+}
+
+#[cfg(not(unix))]
+fn permissions(meta: &Metadata) -> String {
+    let kind = if meta.is_dir() { "d" } else { "-" };
+    format!("{}{}", kind, if meta.permissions().readonly() { "r--" } else { "rw-" })
+}
+
+#[cfg(unix)]
+fn owner(meta: &Metadata, users: &sysinfo::Users) -> String {
+    use std::os::unix::fs::MetadataExt;
+    let uid = meta.uid();
+    uid.to_string()
+        .parse::<sysinfo::Uid>()
+        .ok()
+        .and_then(|id| users.get_user_by_id(&id))
+        .map(|user| user.name().to_string())
+        .unwrap_or_else(|| uid.to_string())
+}
+
+#[cfg(not(unix))]
+fn owner(_meta: &Metadata, _users: &sysinfo::Users) -> String {
+    "-".to_string()
+}
+
+// One entry per line; the long format shows permissions, owner, size and modification time.
+pub fn list(dir: &Path, opts: &ListOptions) -> Result<String, Box<dyn Error>> {
+    let entries = read_entries(dir, opts)?;
+    if entries.is_empty() {
+        return Ok(format!("{} is empty", dir.display()));
+    }
+    if !opts.long {
+        return Ok(entries.iter().map(display_name).collect::<Vec<_>>().join("\n"));
+    }
+    let users = sysinfo::Users::new_with_refreshed_list();
+    let rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|e| {
+            let modified = e.meta.modified().map(|m| DateTime::<Local>::from(m).format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+            let size = if e.meta.is_dir() { "-".to_string() } else { humanize::size(e.meta.len()) };
+            [permissions(&e.meta), owner(&e.meta, &users), size, modified, display_name(e)]
+        })
+        .collect();
+    let owner_width = rows.iter().map(|r| r[1].len()).max().unwrap_or(0);
+    let size_width = rows.iter().map(|r| r[2].len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = rows
+        .iter()
+        .map(|[perms, owner, size, modified, name]| format!("{}  {:<ow$}  {:>sw$}  {}  {}", perms, owner, size, modified, name, ow = owner_width, sw = size_width))
+        .collect();
+    let dirs = entries.iter().filter(|e| e.meta.is_dir()).count();
+    let total: u64 = entries.iter().filter(|e| e.meta.is_file()).map(|e| e.meta.len()).sum();
+    lines.push(format!("{} director(ies), {} file(s), {} total", dirs, entries.len() - dirs, humanize::size(total)));
+    Ok(lines.join("\n"))
+}
+
+// `tree`-style recursive view. Symlinked directories are shown but not descended into.
+pub fn tree(dir: &Path, opts: &ListOptions) -> Result<String, Box<dyn Error>> {
+    let mut lines = vec![format!("{}/", dir.display().to_string().trim_end_matches('/'))];
+    let mut counts = (0, 0);
+    walk_tree(dir, opts, "", 1, &mut lines, &mut counts)?;
+    if lines.len() > MAX_TREE_LINES {
+        let hidden = lines.len() - MAX_TREE_LINES;
+        lines.truncate(MAX_TREE_LINES);
+        lines.push(format!("... {} more line(s); limit the depth with `depth <n>`", hidden));
+    }
+    lines.push(format!("{} director(ies), {} file(s)", counts.0, counts.1));
+    Ok(lines.join("\n"))
+}
+
+fn walk_tree(dir: &Path, opts: &ListOptions, prefix: &str, level: usize, lines: &mut Vec<String>, counts: &mut (usize, usize)) -> Result<(), Box<dyn Error>> {
+    let entries = match read_entries(dir, opts) {
+        Ok(entries) => entries,
+        Err(e) => {
+            lines.push(format!("{}[{}]", prefix, e));
+            return Ok(());
+        }
+    };
+    for (i, entry) in entries.iter().enumerate() {
+        let last = i + 1 == entries.len();
+        lines.push(format!("{}{}{}", prefix, if last { "└── " } else { "├── " }, display_name(entry)));
+        if entry.meta.is_dir() {
+            counts.0 += 1;
+            if opts.depth.is_none_or(|d| level < d) {
+                let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                walk_tree(&entry.path, opts, &child_prefix, level + 1, lines, counts)?;
+            }
+        } else {
+            counts.1 += 1;
+        }
+        if lines.len() > MAX_TREE_LINES {
+            break;
+        }
+    }
+    Ok(())
+}
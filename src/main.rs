@@ -1,15 +1,56 @@
 use clap::Parser;
 use std::io::{self, Write};
 
+mod artifacts;
+mod checksum;
 mod commands;
+mod dataset;
+mod download;
+mod du;
+mod dupes;
+mod explain;
+mod explain_db;
+mod expr;
+mod find;
+mod http;
+mod humanize;
+mod jupyter;
+#[cfg(feature = "jupyter")]
+mod kernel;
+mod languages;
+mod listing;
+mod numeric;
+mod paths;
+mod plot;
+mod preview;
+mod prompt;
+mod replace;
+mod risk;
+mod sandbox;
+mod search;
+mod shell;
+#[cfg(test)]
+mod testing;
+mod translate;
+mod undo;
+mod units;
+mod usage;
+mod weather;
 
 /// TerminalAI: A Local Code Interpreter in Rust
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {}
+struct Args {
+    /// Print wall time, CPU time, peak memory and bytes read/written after each run
+    #[arg(long)]
+    profile: bool,
+}
 
 fn main() {
-    let _args = Args::parse();
+    let args = Args::parse();
+    if args.profile {
+        usage::enable_profiling();
+    }
     println!("Welcome to TerminalAI! Type your commands below. Type 'exit' to quit.");
     let stdin = io::stdin();
     loop {
@@ -31,4 +72,5 @@ fn main() {
             Err(e) => println!("Error: {}", e),
         }
     }
+    jupyter::shutdown_all();
 } 
\ No newline at end of file
@@ -0,0 +1,652 @@
+// Exact, big-number, matrix and statistics evaluation for `calculate`
+use crate::expr::{self, Expr, Op};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+
+const MAX_FACTORIAL: u32 = 10_000;
+const MAX_EXACT_EXPONENT: u32 = 10_000;
+// About 300,000 decimal digits; larger powers are computed in floating point.
+const MAX_EXACT_BITS: u64 = 1_000_000;
+const MAX_DECIMAL_PLACES: usize = 20;
+
+#[derive(Debug, Clone)]
+enum Number {
+    Exact(BigRational),
+    Float(f64),
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(Number),
+    List(Vec<Number>),
+    Matrix(Vec<Vec<Number>>),
+}
+
+enum EvalError {
+    // Something this engine does not know about; the caller should fall back to meval.
+    Unsupported,
+    Invalid(String),
+}
+
+type EvalResult<T> = Result<T, EvalError>;
+
+fn invalid<T>(message: impl Into<String>) -> EvalResult<T> {
+    Err(EvalError::Invalid(message.into()))
+}
+
+impl Number {
+    fn integer(n: impl Into<BigInt>) -> Number {
+        Number::Exact(BigRational::from_integer(n.into()))
+    }
+
+    fn to_f64(&self) -> f64 {
+        match self {
+            Number::Exact(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Float(f) => *f,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            Number::Exact(r) => r.is_zero(),
+            Number::Float(f) => *f == 0.0,
+        }
+    }
+
+    fn as_integer(&self) -> Option<BigInt> {
+        match self {
+            Number::Exact(r) if r.is_integer() => Some(r.to_integer()),
+            Number::Float(f) if f.fract() == 0.0 && f.abs() < 9e15 => Some(BigInt::from(*f as i64)),
+            _ => None,
+        }
+    }
+
+    fn add(&self, other: &Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a + b),
+            _ => Number::Float(self.to_f64() + other.to_f64()),
+        }
+    }
+
+    fn sub(&self, other: &Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a - b),
+            _ => Number::Float(self.to_f64() - other.to_f64()),
+        }
+    }
+
+    fn mul(&self, other: &Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a * b),
+            _ => Number::Float(self.to_f64() * other.to_f64()),
+        }
+    }
+
+    fn div(&self, other: &Number) -> EvalResult<Number> {
+        if other.is_zero() {
+            return invalid("division by zero");
+        }
+        Ok(match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a / b),
+            _ => Number::Float(self.to_f64() / other.to_f64()),
+        })
+    }
+
+    fn neg(&self) -> Number {
+        match self {
+            Number::Exact(r) => Number::Exact(-r),
+            Number::Float(f) => Number::Float(-f),
+        }
+    }
+
+    fn pow(&self, exponent: &Number) -> EvalResult<Number> {
+        if let (Number::Exact(base), Some(n)) = (self, exponent.as_integer().and_then(|n| n.to_i32())) {
+            let bits = base.numer().bits().max(base.denom().bits()).saturating_mul(n.unsigned_abs() as u64);
+            if n.unsigned_abs() <= MAX_EXACT_EXPONENT && bits <= MAX_EXACT_BITS {
+                if n < 0 && base.is_zero() {
+                    return invalid("division by zero");
+                }
+                return Ok(Number::Exact(Pow::pow(base, n)));
+            }
+            // Too big to compute exactly; the float can still overflow, which is an error rather than `inf`.
+            let result = self.to_f64().powf(exponent.to_f64());
+            if !result.is_finite() {
+                return invalid(if base.is_zero() { "division by zero" } else { "result too large to compute" });
+            }
+            return Ok(Number::Float(result));
+        }
+        Ok(Number::Float(self.to_f64().powf(exponent.to_f64())))
+    }
+}
+
+fn parse_literal(literal: &str) -> EvalResult<Number> {
+    let (mantissa, exponent) = match literal.find(['e', 'E']) {
+        Some(idx) => (&literal[..idx], literal[idx + 1..].parse::<i64>().map_err(|_| EvalError::Unsupported)?),
+        None => (literal, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits: BigInt = format!("{}{}", int_part, frac_part).parse().map_err(|_| EvalError::Unsupported)?;
+    let scale = exponent - frac_part.len() as i64;
+    if scale.unsigned_abs() > MAX_EXACT_EXPONENT as u64 {
+        return Ok(Number::Float(literal.parse().map_err(|_| EvalError::Unsupported)?));
+    }
+    let power = BigInt::from(10).pow(scale.unsigned_abs() as u32);
+    Ok(Number::Exact(if scale >= 0 {
+        BigRational::from_integer(digits * power)
+    } else {
+        BigRational::new(digits, power)
+    }))
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "a number",
+            Value::List(_) => "a list",
+            Value::Matrix(_) => "a matrix",
+        }
+    }
+
+    fn into_number(self, context: &str) -> EvalResult<Number> {
+        match self {
+            Value::Number(n) => Ok(n),
+            other => invalid(format!("{} expects a number, got {}", context, other.kind())),
+        }
+    }
+
+    fn into_matrix(self, context: &str) -> EvalResult<Vec<Vec<Number>>> {
+        match self {
+            Value::Matrix(m) => Ok(m),
+            other => invalid(format!("{} expects a matrix, got {}", context, other.kind())),
+        }
+    }
+}
+
+fn eval(expr: &Expr) -> EvalResult<Value> {
+    match expr {
+        Expr::Num(literal) => Ok(Value::Number(parse_literal(literal)?)),
+        Expr::Ident(name) => match name.as_str() {
+            "pi" => Ok(Value::Number(Number::Float(std::f64::consts::PI))),
+            "e" => Ok(Value::Number(Number::Float(std::f64::consts::E))),
+            _ => Err(EvalError::Unsupported),
+        },
+        Expr::Neg(inner) => Ok(match eval(inner)? {
+            Value::Number(n) => Value::Number(n.neg()),
+            Value::List(v) => Value::List(v.iter().map(Number::neg).collect()),
+            Value::Matrix(m) => Value::Matrix(m.iter().map(|row| row.iter().map(Number::neg).collect()).collect()),
+        }),
+        Expr::Factorial(inner) => Ok(Value::Number(factorial(&eval(inner)?.into_number("factorial")?)?)),
+        Expr::Binary(op, lhs, rhs) => binary(*op, eval(lhs)?, eval(rhs)?),
+        Expr::List(items) => list(items.iter().map(eval).collect::<EvalResult<Vec<_>>>()?),
+        Expr::Call(name, args) => call(name, args.iter().map(eval).collect::<EvalResult<Vec<_>>>()?),
+    }
+}
+
+fn list(items: Vec<Value>) -> EvalResult<Value> {
+    if items.iter().all(|v| matches!(v, Value::Number(_))) {
+        return Ok(Value::List(
+            items.into_iter().filter_map(|v| if let Value::Number(n) = v { Some(n) } else { None }).collect(),
+        ));
+    }
+    let rows: Vec<Vec<Number>> = items
+        .into_iter()
+        .map(|v| match v {
+            Value::List(row) => Ok(row),
+            other => invalid(format!("matrix rows must be lists of numbers, got {}", other.kind())),
+        })
+        .collect::<EvalResult<_>>()?;
+    if rows.iter().any(|row| row.len() != rows[0].len() || row.is_empty()) {
+        return invalid("matrix rows must all have the same, non-zero length");
+    }
+    Ok(Value::Matrix(rows))
+}
+
+fn scalar(op: Op, a: &Number, b: &Number) -> EvalResult<Number> {
+    match op {
+        Op::Add => Ok(a.add(b)),
+        Op::Sub => Ok(a.sub(b)),
+        Op::Mul => Ok(a.mul(b)),
+        Op::Div => a.div(b),
+        Op::Pow => a.pow(b),
+    }
+}
+
+fn binary(op: Op, a: Value, b: Value) -> EvalResult<Value> {
+    match (op, a, b) {
+        (_, Value::Number(x), Value::Number(y)) => Ok(Value::Number(scalar(op, &x, &y)?)),
+        (Op::Add | Op::Sub, Value::List(x), Value::List(y)) => {
+            if x.len() != y.len() {
+                return invalid(format!("cannot combine lists of length {} and {}", x.len(), y.len()));
+            }
+            Ok(Value::List(x.iter().zip(&y).map(|(a, b)| scalar(op, a, b)).collect::<EvalResult<_>>()?))
+        }
+        (Op::Add | Op::Sub, Value::Matrix(x), Value::Matrix(y)) => {
+            if x.len() != y.len() || x[0].len() != y[0].len() {
+                return invalid(format!("cannot combine {} and {} matrices", shape(&x), shape(&y)));
+            }
+            let rows = x
+                .iter()
+                .zip(&y)
+                .map(|(r1, r2)| r1.iter().zip(r2).map(|(a, b)| scalar(op, a, b)).collect::<EvalResult<Vec<_>>>())
+                .collect::<EvalResult<_>>()?;
+            Ok(Value::Matrix(rows))
+        }
+        (Op::Mul, Value::Number(s), Value::List(v)) | (Op::Mul, Value::List(v), Value::Number(s)) => {
+            Ok(Value::List(v.iter().map(|x| x.mul(&s)).collect()))
+        }
+        (Op::Div, Value::List(v), Value::Number(s)) => {
+            Ok(Value::List(v.iter().map(|x| x.div(&s)).collect::<EvalResult<_>>()?))
+        }
+        (Op::Mul, Value::Number(s), Value::Matrix(m)) | (Op::Mul, Value::Matrix(m), Value::Number(s)) => {
+            Ok(Value::Matrix(m.iter().map(|row| row.iter().map(|x| x.mul(&s)).collect()).collect()))
+        }
+        (Op::Div, Value::Matrix(m), Value::Number(s)) => Ok(Value::Matrix(
+            m.iter()
+                .map(|row| row.iter().map(|x| x.div(&s)).collect::<EvalResult<Vec<_>>>())
+                .collect::<EvalResult<_>>()?,
+        )),
+        (Op::Mul, Value::Matrix(x), Value::Matrix(y)) => Ok(Value::Matrix(matmul(&x, &y)?)),
+        (Op::Mul, Value::Matrix(x), Value::List(v)) => {
+            let column: Vec<Vec<Number>> = v.into_iter().map(|n| vec![n]).collect();
+            Ok(Value::List(matmul(&x, &column)?.into_iter().map(|mut row| row.remove(0)).collect()))
+        }
+        (Op::Pow, Value::Matrix(m), Value::Number(n)) => Ok(Value::Matrix(matrix_power(m, &n)?)),
+        (op, a, b) => invalid(format!("cannot apply {} to {} and {}", op_name(op), a.kind(), b.kind())),
+    }
+}
+
+fn op_name(op: Op) -> &'static str {
+    match op {
+        Op::Add => "+",
+        Op::Sub => "-",
+        Op::Mul => "*",
+        Op::Div => "/",
+        Op::Pow => "^",
+    }
+}
+
+fn shape(m: &[Vec<Number>]) -> String {
+    format!("{}x{}", m.len(), m.first().map_or(0, Vec::len))
+}
+
+fn factorial(n: &Number) -> EvalResult<Number> {
+    let Some(n) = n.as_integer().and_then(|n| n.to_u32()) else {
+        return invalid("factorial needs a non-negative integer");
+    };
+    if n > MAX_FACTORIAL {
+        return invalid(format!("factorial is limited to n <= {}", MAX_FACTORIAL));
+    }
+    Ok(Number::integer((2..=n).fold(BigInt::one(), |acc, k| acc * k)))
+}
+
+fn matmul(a: &[Vec<Number>], b: &[Vec<Number>]) -> EvalResult<Vec<Vec<Number>>> {
+    if a[0].len() != b.len() {
+        return invalid(format!("cannot multiply {} and {} matrices", shape(a), shape(b)));
+    }
+    Ok(a.iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|j| row.iter().zip(b).fold(Number::integer(0), |acc, (x, brow)| acc.add(&x.mul(&brow[j]))))
+                .collect()
+        })
+        .collect())
+}
+
+fn identity(n: usize) -> Vec<Vec<Number>> {
+    (0..n).map(|i| (0..n).map(|j| Number::integer(i32::from(i == j))).collect()).collect()
+}
+
+fn transpose(m: &[Vec<Number>]) -> Vec<Vec<Number>> {
+    (0..m[0].len()).map(|j| m.iter().map(|row| row[j].clone()).collect()).collect()
+}
+
+fn require_square(m: &[Vec<Number>], context: &str) -> EvalResult<usize> {
+    if m.len() != m[0].len() {
+        return invalid(format!("{} needs a square matrix, got {}", context, shape(m)));
+    }
+    Ok(m.len())
+}
+
+// Index of the row at or below `col` with the largest magnitude in that column, if any is non-zero.
+fn pivot_row(m: &[Vec<Number>], col: usize) -> Option<usize> {
+    (col..m.len())
+        .filter(|&r| !m[r][col].is_zero())
+        .max_by(|&r1, &r2| m[r1][col].to_f64().abs().partial_cmp(&m[r2][col].to_f64().abs()).unwrap_or(Ordering::Equal))
+}
+
+fn determinant(m: &[Vec<Number>]) -> EvalResult<Number> {
+    let n = require_square(m, "det")?;
+    let mut a = m.to_vec();
+    let mut det = Number::integer(1);
+    for col in 0..n {
+        let Some(pivot) = pivot_row(&a, col) else {
+            return Ok(Number::integer(0));
+        };
+        if pivot != col {
+            a.swap(pivot, col);
+            det = det.neg();
+        }
+        det = det.mul(&a[col][col]);
+        let pivot_row = a[col].clone();
+        for row in a.iter_mut().skip(col + 1) {
+            let factor = row[col].div(&pivot_row[col])?;
+            for (x, p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                *x = x.sub(&factor.mul(p));
+            }
+        }
+    }
+    Ok(det)
+}
+
+fn inverse(m: &[Vec<Number>]) -> EvalResult<Vec<Vec<Number>>> {
+    let n = require_square(m, "inverse")?;
+    let mut a = m.to_vec();
+    let mut inv = identity(n);
+    for col in 0..n {
+        let Some(pivot) = pivot_row(&a, col) else {
+            return invalid("matrix is singular and has no inverse");
+        };
+        a.swap(pivot, col);
+        inv.swap(pivot, col);
+        let p = a[col][col].clone();
+        for c in 0..n {
+            a[col][c] = a[col][c].div(&p)?;
+            inv[col][c] = inv[col][c].div(&p)?;
+        }
+        for r in (0..n).filter(|&r| r != col) {
+            let factor = a[r][col].clone();
+            if factor.is_zero() {
+                continue;
+            }
+            for c in 0..n {
+                a[r][c] = a[r][c].sub(&factor.mul(&a[col][c]));
+                inv[r][c] = inv[r][c].sub(&factor.mul(&inv[col][c]));
+            }
+        }
+    }
+    Ok(inv)
+}
+
+fn matrix_power(m: Vec<Vec<Number>>, exponent: &Number) -> EvalResult<Vec<Vec<Number>>> {
+    let n = require_square(&m, "matrix power")?;
+    let Some(k) = exponent.as_integer().and_then(|k| k.to_i32()) else {
+        return invalid("matrix powers need an integer exponent");
+    };
+    if k.unsigned_abs() > MAX_EXACT_EXPONENT {
+        return invalid(format!("matrix powers are limited to |n| <= {}", MAX_EXACT_EXPONENT));
+    }
+    let mut base = if k < 0 { inverse(&m)? } else { m };
+    let mut result = identity(n);
+    let mut k = k.unsigned_abs();
+    while k > 0 {
+        if k & 1 == 1 {
+            result = matmul(&result, &base)?;
+        }
+        base = matmul(&base, &base)?;
+        k >>= 1;
+    }
+    Ok(result)
+}
+
+// Statistics accept either a single list (or matrix) or the values as separate arguments.
+fn numbers(args: Vec<Value>, context: &str) -> EvalResult<Vec<Number>> {
+    let values = match <[Value; 1]>::try_from(args) {
+        Ok([Value::List(v)]) => v,
+        Ok([Value::Matrix(m)]) => m.into_iter().flatten().collect(),
+        Ok([Value::Number(n)]) => vec![n],
+        Err(args) => args.into_iter().map(|v| v.into_number(context)).collect::<EvalResult<_>>()?,
+    };
+    if values.is_empty() {
+        return invalid(format!("{} needs at least one value", context));
+    }
+    Ok(values)
+}
+
+fn sorted(mut values: Vec<Number>) -> Vec<Number> {
+    values.sort_by(|a, b| a.to_f64().partial_cmp(&b.to_f64()).unwrap_or(Ordering::Equal));
+    values
+}
+
+fn sum(values: &[Number]) -> Number {
+    values.iter().fold(Number::integer(0), |acc, x| acc.add(x))
+}
+
+fn mean(values: &[Number]) -> EvalResult<Number> {
+    sum(values).div(&Number::integer(values.len()))
+}
+
+fn variance(values: &[Number]) -> EvalResult<Number> {
+    if values.len() < 2 {
+        return invalid("variance needs at least two values");
+    }
+    let m = mean(values)?;
+    let squares: Vec<Number> = values.iter().map(|x| x.sub(&m).mul(&x.sub(&m))).collect();
+    sum(&squares).div(&Number::integer(values.len() - 1))
+}
+
+// Linear interpolation between closest ranks, matching numpy's default.
+fn percentile(values: Vec<Number>, p: &Number) -> EvalResult<Number> {
+    let p_f = p.to_f64();
+    if !(0.0..=100.0).contains(&p_f) {
+        return invalid("percentile must be between 0 and 100");
+    }
+    let values = sorted(values);
+    let rank = p.mul(&Number::integer(values.len() - 1)).div(&Number::integer(100))?;
+    let lo = rank.to_f64().floor() as usize;
+    let hi = (lo + 1).min(values.len() - 1);
+    let frac = rank.sub(&Number::integer(lo));
+    Ok(values[lo].add(&values[hi].sub(&values[lo]).mul(&frac)))
+}
+
+fn sqrt(n: &Number) -> EvalResult<Number> {
+    if n.to_f64() < 0.0 {
+        return invalid("square root of a negative number");
+    }
+    if let Number::Exact(r) = n {
+        let (num, den) = (r.numer().sqrt(), r.denom().sqrt());
+        if &(&num * &num) == r.numer() && &(&den * &den) == r.denom() {
+            return Ok(Number::Exact(BigRational::new(num, den)));
+        }
+    }
+    Ok(Number::Float(n.to_f64().sqrt()))
+}
+
+fn call(name: &str, args: Vec<Value>) -> EvalResult<Value> {
+    let unary = |args: Vec<Value>| -> EvalResult<Value> {
+        match <[Value; 1]>::try_from(args) {
+            Ok([v]) => Ok(v),
+            Err(_) => invalid(format!("{}() takes exactly one argument", name)),
+        }
+    };
+    let float = |args: Vec<Value>, f: fn(f64) -> f64| -> EvalResult<Value> {
+        Ok(Value::Number(Number::Float(f(unary(args)?.into_number(name)?.to_f64()))))
+    };
+    match name {
+        "sum" => Ok(Value::Number(sum(&numbers(args, name)?))),
+        "mean" | "avg" | "average" => Ok(Value::Number(mean(&numbers(args, name)?)?)),
+        "median" => {
+            let values = sorted(numbers(args, name)?);
+            let mid = values.len() / 2;
+            if values.len() % 2 == 1 {
+                Ok(Value::Number(values[mid].clone()))
+            } else {
+                Ok(Value::Number(values[mid - 1].add(&values[mid]).div(&Number::integer(2))?))
+            }
+        }
+        "var" | "variance" => Ok(Value::Number(variance(&numbers(args, name)?)?)),
+        "stdev" | "stddev" | "std" => Ok(Value::Number(Number::Float(variance(&numbers(args, name)?)?.to_f64().sqrt()))),
+        "min" => Ok(Value::Number(sorted(numbers(args, name)?).remove(0))),
+        "max" => Ok(Value::Number(sorted(numbers(args, name)?).pop().expect("numbers() is never empty"))),
+        "count" => Ok(Value::Number(Number::integer(numbers(args, name)?.len()))),
+        "percentile" => {
+            let mut args = args;
+            if args.len() < 2 {
+                return invalid("usage: percentile(<list>, <p>)");
+            }
+            let p = args.pop().expect("checked length").into_number(name)?;
+            Ok(Value::Number(percentile(numbers(args, name)?, &p)?))
+        }
+        "transpose" => Ok(Value::Matrix(transpose(&unary(args)?.into_matrix(name)?))),
+        "inverse" | "inv" => Ok(Value::Matrix(inverse(&unary(args)?.into_matrix(name)?)?)),
+        "det" | "determinant" => Ok(Value::Number(determinant(&unary(args)?.into_matrix(name)?)?)),
+        "factorial" => Ok(Value::Number(factorial(&unary(args)?.into_number(name)?)?)),
+        "sqrt" => Ok(Value::Number(sqrt(&unary(args)?.into_number(name)?)?)),
+        "abs" => Ok(Value::Number(match unary(args)?.into_number(name)? {
+            Number::Exact(r) => Number::Exact(r.abs()),
+            Number::Float(f) => Number::Float(f.abs()),
+        })),
+        "exp" => float(args, f64::exp),
+        "ln" => float(args, f64::ln),
+        "log" => float(args, f64::log10),
+        "sin" => float(args, f64::sin),
+        "cos" => float(args, f64::cos),
+        "tan" => float(args, f64::tan),
+        _ => Err(EvalError::Unsupported),
+    }
+}
+
+pub fn format_number(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    if !(-6..15).contains(&magnitude) {
+        let formatted = format!("{:.9e}", value);
+        let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        return format!("{}e{}", mantissa, exponent);
+    }
+    let decimals = (9 - magnitude).max(0) as usize;
+    let formatted = format!("{:.*}", decimals, value);
+    if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        formatted
+    }
+}
+
+// Renders a rational as a terminating decimal when it has one of reasonable length.
+fn exact_decimal(r: &BigRational) -> Option<String> {
+    let mut denom = r.denom().clone();
+    let (two, five) = (BigInt::from(2), BigInt::from(5));
+    let (mut twos, mut fives) = (0usize, 0usize);
+    while (&denom % &two).is_zero() {
+        denom /= &two;
+        twos += 1;
+    }
+    while (&denom % &five).is_zero() {
+        denom /= &five;
+        fives += 1;
+    }
+    if !denom.is_one() {
+        return None;
+    }
+    let places = twos.max(fives);
+    if places > MAX_DECIMAL_PLACES {
+        return None;
+    }
+    let scaled = (r * BigRational::from_integer(BigInt::from(10).pow(places as u32))).to_integer();
+    let digits = scaled.abs().to_string();
+    let digits = format!("{:0>width$}", digits, width = places + 1);
+    let (int_part, frac_part) = digits.split_at(digits.len() - places);
+    let sign = if scaled.is_negative() { "-" } else { "" };
+    Some(format!("{}{}.{}", sign, int_part, frac_part))
+}
+
+fn show_number(n: &Number) -> String {
+    match n {
+        Number::Exact(r) if r.is_integer() => r.to_integer().to_string(),
+        Number::Exact(r) => exact_decimal(r).unwrap_or_else(|| format!("{}/{}", r.numer(), r.denom())),
+        Number::Float(f) => format_number(*f),
+    }
+}
+
+fn show(value: &Value) -> String {
+    match value {
+        Value::Number(n @ Number::Exact(r)) if !r.is_integer() && exact_decimal(r).is_none() => {
+            format!("{} (≈ {})", show_number(n), format_number(n.to_f64()))
+        }
+        Value::Number(n) => show_number(n),
+        Value::List(v) => format!("[{}]", v.iter().map(show_number).collect::<Vec<_>>().join(", ")),
+        Value::Matrix(m) => {
+            let cells: Vec<Vec<String>> = m.iter().map(|row| row.iter().map(show_number).collect()).collect();
+            let widths: Vec<usize> =
+                (0..cells[0].len()).map(|j| cells.iter().map(|row| row[j].chars().count()).max().unwrap_or(0)).collect();
+            cells
+                .iter()
+                .map(|row| {
+                    let padded: Vec<String> =
+                        row.iter().zip(&widths).map(|(cell, &w)| format!("{:>width$}", cell, width = w)).collect();
+                    format!("[ {} ]", padded.join("  "))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+}
+
+/// Evaluates `input` with exact rationals, big integers, matrices and statistics. Returns `Ok(None)`
+/// for anything outside that feature set so callers can fall back to meval.
+pub fn evaluate(input: &str) -> Result<Option<String>, String> {
+    let Ok(parsed) = expr::parse(input) else {
+        return Ok(None);
+    };
+    match eval(&parsed) {
+        Ok(value) => Ok(Some(show(&value))),
+        Err(EvalError::Unsupported) => Ok(None),
+        Err(EvalError::Invalid(message)) => Err(message),
+    }
+}
+
+/// Evaluates `parsed` to a single float with `x` bound to `value`, the way `calculate` would.
+/// Returns `Ok(None)` for anything outside this engine, like `evaluate`.
+pub fn evaluate_at(parsed: &Expr, x: f64) -> Result<Option<f64>, String> {
+    let parsed = parsed.substitute("x", &Expr::Num(format!("{:?}", x)));
+    match eval(&parsed) {
+        Ok(Value::Number(n)) => Ok(Some(n.to_f64())),
+        Ok(other) => Err(format!("expected a number, got {}", other.kind())),
+        Err(EvalError::Unsupported) => Ok(None),
+        Err(EvalError::Invalid(message)) => Err(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(input: &str) -> String {
+        evaluate(input).unwrap().unwrap()
+    }
+
+    #[test]
+    fn keeps_fractions_exact() {
+        assert_eq!(calc("1/3 + 1/6"), "0.5");
+        assert_eq!(calc("2/3"), "2/3 (≈ 0.6666666667)");
+        assert_eq!(calc("2^100"), "1267650600228229401496703205376");
+    }
+
+    #[test]
+    fn bounds_the_bits_of_exact_powers() {
+        // 2^1000 has 1001 bits, so ^999 stays just under MAX_EXACT_BITS and ^1000 goes over it.
+        assert_eq!(calc("(2^1000)^999").len(), 300729);
+        assert_eq!(evaluate("(2^1000)^1000").unwrap_err(), "result too large to compute");
+        // Over the bound but small enough for a float.
+        assert_eq!(calc("(1/2^1000)^1000"), "0");
+        assert_eq!(evaluate("0^-1").unwrap_err(), "division by zero");
+    }
+
+    #[test]
+    fn inverts_only_regular_matrices() {
+        assert_eq!(calc("inverse([[4,7],[2,6]])"), "[  0.6  -0.7 ]\n[ -0.2   0.4 ]");
+        assert_eq!(evaluate("inverse([[1,2],[2,4]])").unwrap_err(), "matrix is singular and has no inverse");
+        assert_eq!(evaluate("[[1,2],[2,4]]^-1").unwrap_err(), "matrix is singular and has no inverse");
+        assert_eq!(calc("det([[1,2],[2,4]])"), "0");
+    }
+
+    #[test]
+    fn leaves_unknown_functions_to_meval() {
+        assert_eq!(evaluate("erf(1)").unwrap(), None);
+    }
+}
@@ -0,0 +1,14 @@
+// Locations for TerminalAI's own files (undo journal, caches)
+use std::path::PathBuf;
+
+// `$TERMINALAI_DATA_DIR`, else `$XDG_DATA_HOME/terminalai`, else `~/.local/share/terminalai`.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("TERMINALAI_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join("terminalai");
+    }
+    let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(std::env::temp_dir);
+    home.join(".local").join("share").join("terminalai")
+}
@@ -0,0 +1,516 @@
+// Chart rendering for the `plot` commands
+use crate::dataset::{self, Table};
+use crate::numeric::{self, format_number};
+use crate::preview::{self, Graphics};
+use plotters::coord::ranged1d::{AsRangedCoord, DefaultFormatting, KeyPointHint, ValueFormatter};
+use plotters::coord::types::RangedCoordf64;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use regex::Regex;
+use std::error::Error;
+use std::path::PathBuf;
+
+const SAMPLES: usize = 1000;
+const SIZE: (u32, u32) = (800, 600);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    Line,
+    Scatter,
+    Bar,
+    Histogram,
+}
+
+impl ChartKind {
+    pub fn parse(name: &str) -> Option<ChartKind> {
+        match name.to_lowercase().as_str() {
+            "line" => Some(ChartKind::Line),
+            "scatter" => Some(ChartKind::Scatter),
+            "bar" => Some(ChartKind::Bar),
+            "histogram" | "hist" => Some(ChartKind::Histogram),
+            _ => None,
+        }
+    }
+}
+
+// How x values are labelled: plain numbers, Unix timestamps, or indices into category names.
+pub enum XAxis {
+    Numeric,
+    Time,
+    Categories(Vec<String>),
+}
+
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+pub struct PlotSpec {
+    pub title: String,
+    pub series: Vec<Series>,
+    pub kind: ChartKind,
+    pub x_axis: XAxis,
+    // Derived from the data when not given explicitly.
+    pub x_range: Option<(f64, f64)>,
+    pub x_label: Option<String>,
+    pub y_label: Option<String>,
+    pub log_x: bool,
+    pub log_y: bool,
+    pub bins: Option<usize>,
+    pub output: PathBuf,
+}
+
+impl PlotSpec {
+    pub fn new(title: &str) -> Self {
+        PlotSpec {
+            title: title.to_string(),
+            series: Vec::new(),
+            kind: ChartKind::Line,
+            x_axis: XAxis::Numeric,
+            x_range: None,
+            x_label: None,
+            y_label: None,
+            log_x: false,
+            log_y: false,
+            bins: None,
+            output: PathBuf::from("plot.png"),
+        }
+    }
+
+    // Applies trailing options such as `log y`, `xlabel "t"`, `bins 30` and `save to out.svg`.
+    pub fn apply_options(&mut self, options: &str) -> Result<(), String> {
+        let log = Regex::new(r"\blog\s+(xy|x|y)\b").unwrap();
+        let label = Regex::new(r#"\b(x|y)label\s+"([^"]*)""#).unwrap();
+        let bins = Regex::new(r"\bbins\s+(\d+)").unwrap();
+        let save = Regex::new(r"\bsave\s+to\s+(\S+)").unwrap();
+        for caps in log.captures_iter(options) {
+            self.log_x |= caps[1].contains('x');
+            self.log_y |= caps[1].contains('y');
+        }
+        for caps in label.captures_iter(options) {
+            let text = Some(caps[2].to_string());
+            if &caps[1] == "x" {
+                self.x_label = text;
+            } else {
+                self.y_label = text;
+            }
+        }
+        if let Some(caps) = bins.captures(options) {
+            self.bins = caps[1].parse().ok().filter(|&n| n > 0);
+        }
+        if let Some(caps) = save.captures(options) {
+            self.output = PathBuf::from(&caps[1]);
+        }
+        let rest = [&log, &label, &bins, &save].iter().fold(options.to_string(), |acc, re| re.replace_all(&acc, "").to_string());
+        let rest = rest.replace([',', ' '], "");
+        if !rest.is_empty() {
+            return Err(format!("Unrecognized plot option(s): {}", rest));
+        }
+        Ok(())
+    }
+
+    fn has_bars(&self) -> bool {
+        matches!(self.kind, ChartKind::Bar | ChartKind::Histogram)
+    }
+
+    // Full width of one bar group, from the closest spacing between x values.
+    fn bar_width(&self) -> f64 {
+        let mut xs: Vec<f64> = self.series.iter().flat_map(|s| &s.points).map(|p| p.0).collect();
+        xs.sort_by(|a, b| a.total_cmp(b));
+        xs.dedup();
+        let spacing = xs.windows(2).map(|w| w[1] - w[0]).fold(f64::INFINITY, f64::min);
+        let spacing = if spacing.is_finite() { spacing } else { 1.0 };
+        if self.kind == ChartKind::Histogram {
+            spacing
+        } else {
+            spacing * 0.8
+        }
+    }
+
+    fn x_range(&self) -> Option<(f64, f64)> {
+        if self.x_range.is_some() {
+            return self.x_range;
+        }
+        let xs = self.series.iter().flat_map(|s| &s.points).map(|p| p.0).filter(|x| !self.log_x || *x > 0.0);
+        let (lo, hi) = xs.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        if lo > hi {
+            return None;
+        }
+        if self.log_x {
+            return Some((lo / 1.2, hi * 1.2));
+        }
+        let pad = if self.has_bars() {
+            self.bar_width() / 2.0 + (hi - lo) * 0.02
+        } else if hi > lo {
+            (hi - lo) * 0.02
+        } else {
+            1.0
+        };
+        Some((lo - pad, hi + pad))
+    }
+
+    fn y_range(&self) -> Option<(f64, f64)> {
+        let ys = self.series.iter().flat_map(|s| &s.points).map(|p| p.1).filter(|y| y.is_finite() && (!self.log_y || *y > 0.0));
+        let (mut lo, mut hi) = ys.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), y| (lo.min(y), hi.max(y)));
+        if lo > hi {
+            return None;
+        }
+        if self.log_y {
+            return Some((lo / 1.5, hi * 1.5));
+        }
+        if self.has_bars() {
+            lo = lo.min(0.0);
+            hi = hi.max(0.0);
+        }
+        let pad = if hi > lo { (hi - lo) * 0.05 } else { 1.0 };
+        Some((lo - pad, hi + pad))
+    }
+
+    fn format_x(&self, value: f64, span: f64) -> String {
+        match &self.x_axis {
+            XAxis::Numeric => format_number(value),
+            XAxis::Time => {
+                let format = if span > 2.0 * DAY { "%Y-%m-%d" } else { "%m-%d %H:%M" };
+                chrono::DateTime::from_timestamp(value as i64, 0).map(|dt| dt.format(format).to_string()).unwrap_or_default()
+            }
+            XAxis::Categories(names) => {
+                let index = value.round();
+                if (value - index).abs() > 1e-6 || index < 0.0 {
+                    return String::new();
+                }
+                names.get(index as usize).cloned().unwrap_or_default()
+            }
+        }
+    }
+}
+
+type Function = Box<dyn Fn(f64) -> Result<f64, String>>;
+
+// Evaluates `expr` at `x` with the calculator's engine, falling back to meval for what it does
+// not support, as `calculate` does.
+fn evaluator(expr: &str) -> Result<Function, Box<dyn Error>> {
+    if let Ok(parsed) = crate::expr::parse(expr) {
+        // Whether the engine supports an expression does not depend on `x`.
+        if !matches!(numeric::evaluate_at(&parsed, 1.0), Ok(None)) {
+            return Ok(Box::new(move |x| numeric::evaluate_at(&parsed, x).map(|v| v.unwrap_or(f64::NAN))));
+        }
+    }
+    let f = expr.parse::<meval::Expr>()?.bind("x")?;
+    Ok(Box::new(move |x| Ok(f(x))))
+}
+
+// A constant such as a plot bound, e.g. `2*pi`.
+pub fn number(expr: &str) -> Result<f64, Box<dyn Error>> {
+    Ok(evaluator(expr)?(0.0)?)
+}
+
+// Samples `expr` as a function of `x`, geometrically spaced when the x axis is logarithmic.
+pub fn sample(expr: &str, from: f64, to: f64, log_x: bool) -> Result<Series, Box<dyn Error>> {
+    let f = evaluator(expr)?;
+    if log_x && (from <= 0.0 || to <= 0.0) {
+        return Err("a logarithmic x axis needs a positive range".into());
+    }
+    let mut error = None;
+    let points: Vec<(f64, f64)> = (0..=SAMPLES)
+        .map(|i| {
+            let t = i as f64 / SAMPLES as f64;
+            let x = if log_x { from * (to / from).powf(t) } else { from + (to - from) * t };
+            (x, f(x).unwrap_or_else(|e| {
+                error.get_or_insert(e);
+                f64::NAN
+            }))
+        })
+        .collect();
+    // Undefined at some points is a gap in the curve; undefined everywhere is an error.
+    if let (Some(e), false) = (error, points.iter().any(|p| p.1.is_finite())) {
+        return Err(format!("{}: {}", expr, e).into());
+    }
+    Ok(Series { label: expr.to_string(), points })
+}
+
+// Splits `sin(x), max(x, 1)` on top-level commas only.
+pub fn split_expressions(input: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    for c in input.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current.trim().to_string());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+// Picks numeric, then date, then categorical interpretation for an x column.
+fn x_values(raw: &[&str]) -> (Vec<Option<f64>>, XAxis) {
+    let present: Vec<&&str> = raw.iter().filter(|v| !v.is_empty()).collect();
+    if present.iter().all(|v| dataset::parse_number(v).is_some()) {
+        return (raw.iter().map(|v| dataset::parse_number(v)).collect(), XAxis::Numeric);
+    }
+    if present.iter().all(|v| dataset::parse_time(v).is_some()) {
+        return (raw.iter().map(|v| dataset::parse_time(v)).collect(), XAxis::Time);
+    }
+    let mut names: Vec<String> = Vec::new();
+    let xs = raw
+        .iter()
+        .map(|v| {
+            if v.is_empty() {
+                return None;
+            }
+            let index = names.iter().position(|n| n == v).unwrap_or_else(|| {
+                names.push(v.to_string());
+                names.len() - 1
+            });
+            Some(index as f64)
+        })
+        .collect();
+    (xs, XAxis::Categories(names))
+}
+
+fn histogram(values: &[f64], bins: Option<usize>) -> Vec<(f64, f64)> {
+    let bins = bins.unwrap_or_else(|| ((values.len() as f64).sqrt().ceil() as usize).clamp(5, 50));
+    let (lo, hi) = values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let width = if hi > lo { (hi - lo) / bins as f64 } else { 1.0 };
+    let mut counts = vec![0usize; bins];
+    for &v in values {
+        let bin = (((v - lo) / width) as usize).min(bins - 1);
+        counts[bin] += 1;
+    }
+    counts.iter().enumerate().map(|(i, &c)| (lo + width * (i as f64 + 0.5), c as f64)).collect()
+}
+
+// Fills `spec` with series read from `columns` of `table`. The first column is the x axis,
+// except for histograms, which bin the values of a single column. Returns the number of skipped rows.
+pub fn add_table_series(spec: &mut PlotSpec, table: &Table, columns: &[&str]) -> Result<usize, String> {
+    let indices = columns.iter().map(|c| table.column(c)).collect::<Result<Vec<_>, _>>()?;
+    if spec.kind == ChartKind::Histogram {
+        let column = *indices.last().ok_or("No column given.")?;
+        let raw = table.values(column);
+        let values: Vec<f64> = raw.iter().filter_map(|v| dataset::parse_number(v)).collect();
+        if values.is_empty() {
+            return Err(format!("Column '{}' has no numeric values.", table.headers[column]));
+        }
+        spec.series.push(Series { label: table.headers[column].clone(), points: histogram(&values, spec.bins) });
+        spec.x_label.get_or_insert_with(|| table.headers[column].clone());
+        spec.y_label.get_or_insert_with(|| "count".to_string());
+        return Ok(raw.len() - values.len());
+    }
+    let [x_column, y_columns @ ..] = indices.as_slice() else {
+        return Err("No columns given.".to_string());
+    };
+    if y_columns.is_empty() {
+        return Err("Give an x column and at least one y column, e.g. `columns date,latency`.".to_string());
+    }
+    let (xs, axis) = x_values(&table.values(*x_column));
+    let mut skipped = 0;
+    for &y_column in y_columns {
+        let ys = table.values(y_column);
+        let mut points: Vec<(f64, f64)> = xs
+            .iter()
+            .zip(&ys)
+            .filter_map(|(x, y)| Some(((*x)?, dataset::parse_number(y)?)))
+            .collect();
+        skipped = skipped.max(xs.len() - points.len());
+        if matches!(axis, XAxis::Time) {
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        spec.series.push(Series { label: table.headers[y_column].clone(), points });
+    }
+    spec.x_axis = axis;
+    spec.x_label.get_or_insert_with(|| table.headers[*x_column].clone());
+    if y_columns.len() == 1 {
+        spec.y_label.get_or_insert_with(|| table.headers[y_columns[0]].clone());
+    }
+    Ok(skipped)
+}
+
+// Splits a series into runs of finite points so asymptotes and gaps are not bridged by a line.
+fn segments(points: &[(f64, f64)], log_y: bool) -> Vec<Vec<(f64, f64)>> {
+    let mut out = vec![Vec::new()];
+    for &(x, y) in points {
+        if y.is_finite() && (!log_y || y > 0.0) {
+            out.last_mut().unwrap().push((x, y));
+        } else if !out.last().unwrap().is_empty() {
+            out.push(Vec::new());
+        }
+    }
+    out.retain(|s| !s.is_empty());
+    out
+}
+
+const DAY: f64 = 86400.0;
+
+// Tick positions on a time axis at whole minutes, hours or days (UTC, like the labels). Spans the
+// labels show as dates get whole-day steps, so no date appears twice.
+fn time_ticks(from: f64, to: f64) -> Vec<f64> {
+    const STEPS: [f64; 15] = [60.0, 300.0, 900.0, 1800.0, 3600.0, 10800.0, 21600.0, 43200.0, DAY, 2.0 * DAY, 7.0 * DAY, 14.0 * DAY, 28.0 * DAY, 91.0 * DAY, 364.0 * DAY];
+    let span = to - from;
+    let step = STEPS
+        .into_iter()
+        .filter(|&step| span <= 2.0 * DAY || step >= DAY)
+        .find(|&step| span / step <= 10.0)
+        .unwrap_or_else(|| (span / 10.0 / (364.0 * DAY)).ceil() * 364.0 * DAY);
+    let mut ticks = Vec::new();
+    let mut tick = (from / step).ceil() * step;
+    while tick <= to {
+        ticks.push(tick);
+        tick += step;
+    }
+    ticks
+}
+
+// A linear axis of Unix timestamps with its ticks from `time_ticks`.
+struct TimeAxis {
+    linear: RangedCoordf64,
+    ticks: Vec<f64>,
+}
+
+impl TimeAxis {
+    fn new(from: f64, to: f64) -> TimeAxis {
+        TimeAxis { linear: (from..to).into(), ticks: time_ticks(from, to) }
+    }
+}
+
+impl Ranged for TimeAxis {
+    type FormatOption = DefaultFormatting;
+    type ValueType = f64;
+
+    fn map(&self, value: &f64, limit: (i32, i32)) -> i32 {
+        self.linear.map(value, limit)
+    }
+
+    fn key_points<Hint: KeyPointHint>(&self, hint: Hint) -> Vec<f64> {
+        if hint.max_num_points() == 0 {
+            return Vec::new();
+        }
+        self.ticks.clone()
+    }
+
+    fn range(&self) -> std::ops::Range<f64> {
+        self.linear.range()
+    }
+}
+
+fn draw_chart<DB, X, Y>(root: &DrawingArea<DB, Shift>, spec: &PlotSpec, x: X, y: Y) -> Result<(), Box<dyn Error>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    X: AsRangedCoord<Value = f64>,
+    Y: AsRangedCoord<Value = f64>,
+    X::CoordDescType: ValueFormatter<f64>,
+    Y::CoordDescType: ValueFormatter<f64>,
+{
+    let mut chart = ChartBuilder::on(root)
+        .caption(&spec.title, ("sans-serif", 30))
+        .margin(15)
+        .x_label_area_size(40)
+        .y_label_area_size(80)
+        .build_cartesian_2d(x, y)?;
+    let x_span = chart.x_range().end - chart.x_range().start;
+    let y_base = if spec.log_y { chart.y_range().start } else { 0.0 };
+    let x_formatter = |v: &f64| spec.format_x(*v, x_span);
+    let y_formatter = |v: &f64| format_number(*v);
+    let mut mesh = chart.configure_mesh();
+    mesh.x_label_formatter(&x_formatter).y_label_formatter(&y_formatter);
+    if let Some(label) = &spec.x_label {
+        mesh.x_desc(label.as_str());
+    }
+    if let Some(label) = &spec.y_label {
+        mesh.y_desc(label.as_str());
+    }
+    mesh.draw()?;
+    let bar_width = spec.bar_width() / spec.series.len() as f64;
+    for (i, series) in spec.series.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let legend = move |(x, y): (i32, i32)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2));
+        match spec.kind {
+            ChartKind::Line => {
+                for (n, segment) in segments(&series.points, spec.log_y).into_iter().enumerate() {
+                    let anno = chart.draw_series(LineSeries::new(segment, color.stroke_width(2)))?;
+                    if n == 0 {
+                        anno.label(series.label.as_str()).legend(legend);
+                    }
+                }
+            }
+            ChartKind::Scatter => {
+                chart
+                    .draw_series(series.points.iter().map(|&p| Circle::new(p, 3, color.filled())))?
+                    .label(series.label.as_str())
+                    .legend(legend);
+            }
+            ChartKind::Bar | ChartKind::Histogram => {
+                // Side-by-side bars when several series share the x axis.
+                let offset = -spec.bar_width() / 2.0 + bar_width * i as f64;
+                chart
+                    .draw_series(series.points.iter().map(|&(x, y)| {
+                        Rectangle::new([(x + offset, y_base), (x + offset + bar_width, y)], color.filled())
+                    }))?
+                    .label(series.label.as_str())
+                    .legend(legend);
+            }
+        }
+    }
+    if spec.series.len() > 1 {
+        chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    }
+    Ok(())
+}
+
+fn draw<DB>(root: DrawingArea<DB, Shift>, spec: &PlotSpec) -> Result<(), Box<dyn Error>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let (x0, x1) = spec.x_range().ok_or("nothing to plot: no usable x values")?;
+    let (y0, y1) = spec.y_range().ok_or("nothing to plot: no finite values in range")?;
+    match (spec.log_x, spec.log_y) {
+        (false, false) if matches!(spec.x_axis, XAxis::Time) => draw_chart(&root, spec, TimeAxis::new(x0, x1), y0..y1)?,
+        (false, true) if matches!(spec.x_axis, XAxis::Time) => {
+            draw_chart(&root, spec, TimeAxis::new(x0, x1), (y0..y1).log_scale())?
+        }
+        (false, false) => draw_chart(&root, spec, x0..x1, y0..y1)?,
+        (true, false) => draw_chart(&root, spec, (x0..x1).log_scale(), y0..y1)?,
+        (false, true) => draw_chart(&root, spec, x0..x1, (y0..y1).log_scale())?,
+        (true, true) => draw_chart(&root, spec, (x0..x1).log_scale(), (y0..y1).log_scale())?,
+    }
+    root.present()?;
+    Ok(())
+}
+
+fn is_svg(spec: &PlotSpec) -> bool {
+    spec.output.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+// Renders `spec` to its output path; the extension picks SVG or a bitmap format.
+pub fn render(spec: &PlotSpec) -> Result<(), Box<dyn Error>> {
+    if is_svg(spec) {
+        draw(SVGBackend::new(&spec.output, SIZE).into_drawing_area(), spec)
+    } else {
+        draw(BitMapBackend::new(&spec.output, SIZE).into_drawing_area(), spec)
+    }
+}
+
+// Inline terminal preview of a rendered plot. SVG output is rasterised again in memory.
+pub fn preview(spec: &PlotSpec) -> Option<String> {
+    if preview::detect() == Graphics::None {
+        return None;
+    }
+    if !is_svg(spec) {
+        return preview::render_file(&spec.output);
+    }
+    let mut buffer = vec![0u8; (SIZE.0 * SIZE.1 * 3) as usize];
+    draw(BitMapBackend::with_buffer(&mut buffer, SIZE).into_drawing_area(), spec).ok()?;
+    let img = image::RgbImage::from_raw(SIZE.0, SIZE.1, buffer)?;
+    preview::render(&image::DynamicImage::ImageRgb8(img))
+}
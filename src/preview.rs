@@ -0,0 +1,211 @@
+// Inline image rendering in the terminal (kitty, sixel, Unicode blocks or braille)
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, RgbImage};
+use std::io::IsTerminal;
+
+const MAX_COLUMNS: u32 = 100;
+const MAX_SIXEL_WIDTH: u32 = 800;
+const KITTY_CHUNK: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Graphics {
+    Kitty,
+    Sixel,
+    Blocks,
+    Braille,
+    None,
+}
+
+// Picks a protocol from the environment. `TERMINALAI_GRAPHICS` overrides detection and also
+// forces output when stdout is not a terminal.
+pub fn detect() -> Graphics {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    match var("TERMINALAI_GRAPHICS").to_lowercase().as_str() {
+        "kitty" => return Graphics::Kitty,
+        "sixel" => return Graphics::Sixel,
+        "blocks" | "block" => return Graphics::Blocks,
+        "braille" => return Graphics::Braille,
+        "none" | "off" => return Graphics::None,
+        _ => {}
+    }
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if !std::io::stdout().is_terminal() || term.is_empty() || term == "dumb" {
+        return Graphics::None;
+    }
+    if !var("KITTY_WINDOW_ID").is_empty() || term == "xterm-kitty" || matches!(program.as_str(), "WezTerm" | "ghostty") {
+        Graphics::Kitty
+    } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") || program == "iTerm.app" {
+        Graphics::Sixel
+    } else if std::env::var_os("NO_COLOR").is_some() {
+        Graphics::Braille
+    } else {
+        Graphics::Blocks
+    }
+}
+
+fn columns() -> u32 {
+    let cols = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(80);
+    cols.clamp(20, MAX_COLUMNS)
+}
+
+// Scales `img` to `width` pixels, keeping the aspect ratio and never enlarging.
+fn fit_width(img: &DynamicImage, width: u32) -> DynamicImage {
+    let (w, h) = img.dimensions();
+    if w <= width {
+        return img.clone();
+    }
+    let height = ((h as f64 * width as f64 / w as f64).round() as u32).max(1);
+    img.resize_exact(width, height, FilterType::Triangle)
+}
+
+/// Returns the escape sequences or characters that draw `img`, or `None` when the terminal
+/// has no usable graphics support.
+pub fn render(img: &DynamicImage) -> Option<String> {
+    match detect() {
+        Graphics::Kitty => kitty(img),
+        Graphics::Sixel => Some(sixel(&fit_width(img, MAX_SIXEL_WIDTH).to_rgb8())),
+        Graphics::Blocks => Some(blocks(&fit_width(img, columns()).to_rgb8())),
+        Graphics::Braille => Some(braille(&img.to_luma8(), columns() * 2)),
+        Graphics::None => None,
+    }
+}
+
+pub fn render_file(path: &std::path::Path) -> Option<String> {
+    image::open(path).ok().and_then(|img| render(&img))
+}
+
+fn kitty(img: &DynamicImage) -> Option<String> {
+    let mut png = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).ok()?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let payload = std::str::from_utf8(chunk).ok()?;
+        if i == 0 {
+            out.push_str(&format!("\x1b_Ga=T,f=100,c={},m={};{}\x1b\\", columns(), more, payload));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, payload));
+        }
+    }
+    Some(out)
+}
+
+// Maps a colour onto the 6x6x6 cube shared by sixel palettes and xterm's 256 colours.
+fn cube_index(r: u8, g: u8, b: u8) -> usize {
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+fn sixel(img: &RgbImage) -> String {
+    let (w, h) = img.dimensions();
+    let mut out = format!("\x1bPq\"1;1;{};{}", w, h);
+    for i in 0..216 {
+        let (r, g, b) = (i / 36, (i / 6) % 6, i % 6);
+        out.push_str(&format!("#{};2;{};{};{}", i, r * 20, g * 20, b * 20));
+    }
+    for band in (0..h).step_by(6) {
+        let rows = (h - band).min(6);
+        // Bit masks per column for every colour used in this band.
+        let mut masks: Vec<Option<Vec<u8>>> = vec![None; 216];
+        for x in 0..w {
+            for dy in 0..rows {
+                let p = img.get_pixel(x, band + dy);
+                let mask = masks[cube_index(p[0], p[1], p[2])].get_or_insert_with(|| vec![0; w as usize]);
+                mask[x as usize] |= 1 << dy;
+            }
+        }
+        for (color, mask) in masks.iter().enumerate() {
+            let Some(mask) = mask else { continue };
+            out.push_str(&format!("#{}", color));
+            let mut x = 0;
+            while x < mask.len() {
+                let run = mask[x..].iter().take_while(|&&m| m == mask[x]).count();
+                let c = (63 + mask[x]) as char;
+                if run > 3 {
+                    out.push_str(&format!("!{}{}", run, c));
+                } else {
+                    out.extend(std::iter::repeat_n(c, run));
+                }
+                x += run;
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+fn truecolor() -> bool {
+    matches!(std::env::var("COLORTERM").unwrap_or_default().as_str(), "truecolor" | "24bit")
+}
+
+// Two pixels per character cell: the upper half block takes the top pixel as foreground
+// and the bottom pixel as background.
+fn blocks(img: &RgbImage) -> String {
+    let (w, h) = img.dimensions();
+    let color = |fg: bool, p: &image::Rgb<u8>| {
+        let layer = if fg { 38 } else { 48 };
+        if truecolor() {
+            format!("\x1b[{};2;{};{};{}m", layer, p[0], p[1], p[2])
+        } else {
+            format!("\x1b[{};5;{}m", layer, 16 + cube_index(p[0], p[1], p[2]))
+        }
+    };
+    let mut out = String::new();
+    for y in (0..h).step_by(2) {
+        for x in 0..w {
+            let top = img.get_pixel(x, y);
+            out.push_str(&color(true, top));
+            if y + 1 < h {
+                out.push_str(&color(false, img.get_pixel(x, y + 1)));
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+// Monochrome rendering with 2x4 braille dots per cell. Each dot covers a block of source
+// pixels and is set when any of them stands out from the image's overall brightness, so thin
+// plot lines survive the downscaling and both dark-on-light and light-on-dark images work.
+fn braille(img: &image::GrayImage, dots_wide: u32) -> String {
+    let (w, h) = img.dimensions();
+    let scale = (w as f64 / dots_wide as f64).max(1.0);
+    let (dw, dh) = (((w as f64 / scale).ceil() as u32).max(1), ((h as f64 / scale).ceil() as u32).max(1));
+    let mean = img.pixels().map(|p| p[0] as u64).sum::<u64>() / (w as u64 * h as u64).max(1);
+    let light_background = mean > 127;
+    let inked = |x: u32, y: u32| {
+        if x >= dw || y >= dh {
+            return false;
+        }
+        let (x0, y0) = ((x as f64 * scale) as u32, (y as f64 * scale) as u32);
+        let (x1, y1) = ((((x + 1) as f64 * scale) as u32).min(w), (((y + 1) as f64 * scale) as u32).min(h));
+        (y0..y1.max(y0 + 1)).any(|py| {
+            (x0..x1.max(x0 + 1)).any(|px| {
+                let v = img.get_pixel(px.min(w - 1), py.min(h - 1))[0] as u64;
+                if light_background {
+                    v + 48 < mean
+                } else {
+                    v > mean + 48
+                }
+            })
+        })
+    };
+    const DOTS: [(u32, u32, u32); 8] =
+        [(0, 0, 0x01), (0, 1, 0x02), (0, 2, 0x04), (1, 0, 0x08), (1, 1, 0x10), (1, 2, 0x20), (0, 3, 0x40), (1, 3, 0x80)];
+    let mut out = String::new();
+    for y in (0..dh).step_by(4) {
+        for x in (0..dw).step_by(2) {
+            let bits = DOTS.iter().filter(|(dx, dy, _)| inked(x + dx, y + dy)).fold(0, |acc, (_, _, bit)| acc | bit);
+            out.push(char::from_u32(0x2800 + bits).unwrap_or(' '));
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,27 @@
+// Interactive questions: yes/no confirmation for commands that change files, and free-form answers
+use std::io::{self, BufRead, Write};
+
+// Asks `question` on stdout and reads the answer from the REPL's stdin. Anything but
+// `y`/`yes` (including end of input) counts as no.
+pub fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"),
+    }
+}
+
+// Asks `question` and returns the trimmed answer, or None at end of input.
+pub fn ask(question: &str) -> Option<String> {
+    print!("{} ", question);
+    io::stdout().flush().ok()?;
+    let mut answer = String::new();
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_string()),
+    }
+}
@@ -0,0 +1,126 @@
+// Planning for `replace` (new file contents and diffs) and writing the result through the undo journal
+use crate::search::{self, SearchOptions};
+use crate::undo::Transaction;
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+pub struct Edit {
+    pub path: PathBuf,
+    pub original: Vec<u8>,
+    pub updated: Vec<u8>,
+    pub count: usize,
+}
+
+impl Edit {
+    pub fn diff(&self) -> String {
+        let name = self.path.display().to_string();
+        let (old, new) = (String::from_utf8_lossy(&self.original), String::from_utf8_lossy(&self.updated));
+        similar::TextDiff::from_lines(old.as_ref(), new.as_ref()).unified_diff().header(&name, &name).to_string()
+    }
+}
+
+// Accepts sed-style `\1` group references alongside the regex crate's `$1` and `${name}`; `\\` is
+// a literal backslash, so `\\1` stays `\1`.
+fn expand_backreferences(replacement: &str) -> Cow<'_, str> {
+    let re = regex::Regex::new(r"\\(\\|\d+)").unwrap();
+    re.replace_all(replacement, |caps: &regex::Captures| match &caps[1] {
+        "\\" => "\\".to_string(),
+        group => format!("${{{}}}", group),
+    })
+}
+
+// Finds every file under `root` the pattern matches and what it would look like afterwards.
+pub fn plan(root: &Path, re: &Regex, replacement: &str, opts: &SearchOptions) -> Result<Vec<Edit>, Box<dyn Error>> {
+    let replacement = expand_backreferences(replacement);
+    let results = search::search(root, re, opts)?;
+    let mut edits = Vec::new();
+    for file in results.files {
+        let original = std::fs::read(&file.path)?;
+        let count = re.find_iter(&original).count();
+        let updated = re.replace_all(&original, replacement.as_bytes()).into_owned();
+        if updated != original {
+            edits.push(Edit { path: file.path, original, updated, count });
+        }
+    }
+    Ok(edits)
+}
+
+// Writes every edit, journaled as one undoable transaction. If one fails, the files already
+// written are put back and nothing is journaled.
+pub fn apply(edits: &[Edit], description: &str) -> Result<(), Box<dyn Error>> {
+    let mut transaction = Transaction::begin(description)?;
+    for edit in edits {
+        if let Err(e) = transaction.backup(&edit.path).and_then(|_| std::fs::write(&edit.path, &edit.updated)) {
+            transaction.rollback()?;
+            return Err(format!("{}: {}", edit.path.display(), e).into());
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{DataDir, TempDir};
+    use crate::undo;
+    use std::fs;
+
+    fn plan_in(root: &Path, pattern: &str, replacement: &str) -> Vec<Edit> {
+        let opts = SearchOptions::default();
+        plan(root, &opts.regex(pattern).unwrap(), replacement, &opts).unwrap()
+    }
+
+    #[test]
+    fn expands_sed_style_group_references() {
+        assert_eq!(expand_backreferences(r"\2-\1"), "${2}-${1}");
+        assert_eq!(expand_backreferences(r"\\1"), r"\1");
+        assert_eq!(expand_backreferences(r"\\\1"), r"\${1}");
+        assert_eq!(expand_backreferences("$1 ${name}"), "$1 ${name}");
+    }
+
+    #[test]
+    fn replaces_and_undoes() {
+        let _data = DataDir::fresh();
+        let dir = TempDir::new("replace");
+        let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        fs::write(&a, "fn old_name() {}\nold_name();\n").unwrap();
+        fs::write(&b, "nothing here\n").unwrap();
+
+        let edits = plan_in(dir.path(), r"old_(\w+)", r"new_\1");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].count, 2);
+        apply(&edits, "rename").unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "fn new_name() {}\nnew_name();\n");
+
+        let undone = undo::undo_last().unwrap().unwrap();
+        assert_eq!(undone, "Reverted: rename (1 file(s) restored)");
+        assert_eq!(fs::read_to_string(&a).unwrap(), "fn old_name() {}\nold_name();\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "nothing here\n");
+        assert_eq!(undo::undo_last().unwrap().unwrap(), "Nothing to undo.");
+    }
+
+    #[test]
+    fn rolls_back_a_partly_applied_replace() {
+        let data = DataDir::fresh();
+        let dir = TempDir::new("replace-rollback");
+        let (first, second) = (dir.path().join("first.txt"), dir.path().join("second.txt"));
+        fs::write(&first, "one\n").unwrap();
+        fs::write(&second, "one\n").unwrap();
+        // An earlier replace that must survive the failed one.
+        apply(&plan_in(&first, "one", "two"), "earlier").unwrap();
+
+        let mut edits = plan_in(dir.path(), "t?wo|one", "three");
+        edits.sort_by(|a, b| a.path.cmp(&b.path));
+        // Writing the second file fails after the first has been written.
+        edits[1].path = dir.path().join("missing").join("second.txt");
+        assert!(apply(&edits, "failed").is_err());
+        assert_eq!(fs::read_to_string(&first).unwrap(), "two\n");
+        assert_eq!(fs::read_dir(data.path().join("undo")).unwrap().count(), 1);
+
+        assert_eq!(undo::undo_last().unwrap().unwrap(), "Reverted: earlier (1 file(s) restored)");
+        assert_eq!(fs::read_to_string(&first).unwrap(), "one\n");
+    }
+}
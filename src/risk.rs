@@ -0,0 +1,501 @@
+// Static risk analysis of shell commands before `run`, `schedule` and `watch ... then run` execute them
+use crate::explain::{lex, Token, Word};
+use regex::Regex;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "LOW",
+            Severity::Medium => "MEDIUM",
+            Severity::High => "HIGH",
+            Severity::Critical => "CRITICAL",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+// Prefixes that run the rest of the line as a command, with the options that take a value.
+const WRAPPERS: &[(&str, &[&str])] = &[
+    ("sudo", &["-u", "-g", "-C", "-h", "-p", "-U"]),
+    ("doas", &["-u", "-C"]),
+    ("env", &["-u", "-C"]),
+    ("nice", &["-n"]),
+    ("xargs", &["-n", "-I", "-P", "-d", "-L", "-s", "-E", "-a"]),
+    ("timeout", &["-s", "-k"]),
+    ("stdbuf", &["-i", "-o", "-e"]),
+    ("nohup", &[]),
+    ("time", &[]),
+    ("exec", &[]),
+    ("command", &[]),
+];
+// Programs that execute whatever they are fed.
+const INTERPRETERS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "fish", "python", "python3", "perl", "ruby", "node", "eval", "source", "."];
+const DOWNLOADERS: &[&str] = &["curl", "wget", "fetch"];
+const SYSTEM_DIRS: &[&str] = &["/", "/bin", "/boot", "/dev", "/etc", "/home", "/lib", "/lib32", "/lib64", "/opt", "/proc", "/root", "/sbin", "/srv", "/sys", "/usr", "/var", "~"];
+const DOTFILES: &[&str] = &[".bashrc", ".bash_profile", ".bash_login", ".profile", ".zshrc", ".zprofile", ".zshenv", ".gitconfig", ".vimrc", ".ssh"];
+
+struct Redirect {
+    op: String,
+    target: String,
+    // Substitutions in the target, as in `< <(curl ...)`.
+    subs: Vec<String>,
+}
+
+#[derive(Default)]
+struct Simple {
+    words: Vec<Word>,
+    redirects: Vec<Redirect>,
+    // The pipeline operator or separator that follows this command.
+    next: Option<String>,
+}
+
+fn basename(program: &str) -> &str {
+    Path::new(program).file_name().and_then(|n| n.to_str()).unwrap_or(program)
+}
+
+// `~`, `$HOME` and `${HOME}` all mean the home directory; trailing `/`, `/*` and `/.` refer to
+// the directory itself.
+fn normalize(path: &str) -> String {
+    let mut path = path.to_string();
+    for home in ["${HOME}", "$HOME"] {
+        if let Some(rest) = path.strip_prefix(home) {
+            path = format!("~{}", rest);
+        }
+    }
+    loop {
+        let trimmed = path.strip_suffix("/*").or_else(|| path.strip_suffix("/.")).or_else(|| path.strip_suffix('/')).filter(|p| !p.is_empty());
+        match trimmed {
+            Some(p) => path = p.to_string(),
+            None if path == "/*" || path == "/." => return "/".to_string(),
+            None => return path,
+        }
+    }
+}
+
+fn is_critical_path(path: &str) -> bool {
+    let path = normalize(path);
+    SYSTEM_DIRS.contains(&path.as_str()) || Regex::new(r"^/home/[^/]+$").unwrap().is_match(&path)
+}
+
+fn is_system_file(path: &str) -> bool {
+    let path = normalize(path);
+    ["/etc/", "/boot/", "/usr/", "/bin/", "/sbin/", "/lib/", "/lib64/", "/sys/", "/proc/"].iter().any(|dir| path.starts_with(dir))
+}
+
+// Whole disks and partitions, as opposed to /dev/null and friends.
+fn is_block_device(path: &str) -> bool {
+    Regex::new(r"^/dev/(sd[a-z]|hd[a-z]|vd[a-z]|xvd[a-z]|nvme\d|mmcblk\d|loop\d|dm-\d|md\d|mapper/|disk/)").unwrap().is_match(path)
+}
+
+// Shell startup files and other dotfiles in a home directory.
+fn is_dotfile(path: &str) -> bool {
+    let path = normalize(path);
+    let relative = path
+        .strip_prefix("~/")
+        .or_else(|| path.strip_prefix("/root/"))
+        .or_else(|| Regex::new(r"^/home/[^/]+/").unwrap().find(&path).map(|m| &path[m.end()..]));
+    match relative {
+        Some(rest) => rest.starts_with('.') && rest != "." && !rest.starts_with(".."),
+        None => DOTFILES.iter().any(|name| path == *name || path.starts_with(&format!("{}/", name))),
+    }
+}
+
+// Whether `-x` (alone or bundled, as in `-rf`) or the long form is among the options.
+fn has_flag(args: &[&str], short: char, long: &str) -> bool {
+    args.iter().take_while(|a| **a != "--").any(|a| *a == long || (a.starts_with('-') && !a.starts_with("--") && a[1..].contains(short)))
+}
+
+// Non-option arguments.
+fn operands<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    let mut operands = Vec::new();
+    let mut options_done = false;
+    for arg in args {
+        if !options_done && *arg == "--" {
+            options_done = true;
+        } else if options_done || !arg.starts_with('-') || *arg == "-" {
+            operands.push(*arg);
+        }
+    }
+    operands
+}
+
+fn split(tokens: &[Token]) -> Vec<Simple> {
+    let mut commands = Vec::new();
+    let mut current = Simple::default();
+    let mut iter = tokens.iter().peekable();
+    while let Some(token) = iter.next() {
+        match token {
+            Token::Word(w) => current.words.push(w.clone()),
+            Token::Redirect { op, .. } => {
+                if let Some(Token::Word(target)) = iter.peek() {
+                    current.redirects.push(Redirect { op: op.clone(), target: target.text.clone(), subs: target.subs.clone() });
+                    iter.next();
+                }
+            }
+            Token::Op(op) => {
+                current.next = Some(op.clone());
+                commands.push(std::mem::take(&mut current));
+            }
+            Token::Open | Token::Close => commands.push(std::mem::take(&mut current)),
+        }
+    }
+    commands.push(current);
+    commands.retain(|c| !c.words.is_empty() || !c.redirects.is_empty());
+    commands
+}
+
+// Skips assignments and wrappers like `sudo -u root env X=1`, returning where the real
+// program starts and whether it runs as root.
+fn unwrap(words: &[Word]) -> (usize, bool) {
+    let assignment = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*=").unwrap();
+    let mut i = 0;
+    let mut privileged = false;
+    while i < words.len() {
+        if assignment.is_match(&words[i].text) {
+            i += 1;
+            continue;
+        }
+        let name = basename(&words[i].text);
+        let Some((wrapper, takes_value)) = WRAPPERS.iter().find(|(w, _)| *w == name) else {
+            break;
+        };
+        privileged |= matches!(*wrapper, "sudo" | "doas");
+        i += 1;
+        let mut positional_skip = usize::from(*wrapper == "timeout");
+        while let Some(word) = words.get(i) {
+            if word.text.starts_with('-') {
+                i += if takes_value.contains(&word.text.as_str()) { 2 } else { 1 };
+            } else if *wrapper == "env" && word.text.contains('=') {
+                i += 1;
+            } else if positional_skip > 0 {
+                positional_skip -= 1;
+                i += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    (i, privileged)
+}
+
+fn mentions_download(source: &str) -> bool {
+    Regex::new(r"\b(curl|wget|fetch)\b").unwrap().is_match(source)
+}
+
+fn check_redirect(op: &str, target: &str, findings: &mut Vec<Finding>) {
+    if !matches!(op, ">" | ">|" | ">>" | "&>" | "&>>") {
+        return;
+    }
+    let append = op.ends_with(">>");
+    if is_block_device(target) {
+        findings.push(Finding { severity: Severity::Critical, message: format!("writes directly to block device {}, destroying its data", target) });
+    } else if is_dotfile(target) {
+        let (severity, verb) = if append { (Severity::Medium, "appends to") } else { (Severity::High, "overwrites") };
+        findings.push(Finding { severity, message: format!("{} dotfile {}", verb, target) });
+    } else if is_system_file(target) {
+        findings.push(Finding { severity: Severity::High, message: format!("writes to system file {}", target) });
+    }
+}
+
+fn check_simple(words: &[Word], findings: &mut Vec<Finding>) {
+    let (start, privileged) = unwrap(words);
+    let Some(program) = words.get(start) else {
+        return;
+    };
+    let name = basename(&program.text);
+    let args: Vec<&str> = words[start + 1..].iter().map(|w| w.text.as_str()).collect();
+    let operands = operands(&args);
+    let mut push = |severity: Severity, message: String| findings.push(Finding { severity, message });
+    if privileged {
+        push(Severity::Low, format!("runs {} with root privileges", name));
+    }
+    match name {
+        "rm" => {
+            let recursive = has_flag(&args, 'r', "--recursive") || has_flag(&args, 'R', "--recursive");
+            let force = has_flag(&args, 'f', "--force");
+            if args.contains(&"--no-preserve-root") {
+                push(Severity::Critical, "rm --no-preserve-root disables the safeguard against deleting /".to_string());
+            }
+            for target in &operands {
+                let target = if *target == "{}" { "each file found" } else { target };
+                if recursive && is_critical_path(target) {
+                    push(Severity::Critical, format!("recursively deletes {}", target));
+                } else if recursive && matches!(normalize(target).as_str(), "*" | "." | ".." | ".*") {
+                    push(Severity::High, format!("recursively deletes everything matched by {}", target));
+                } else if is_system_file(target) || is_critical_path(target) {
+                    push(Severity::High, format!("deletes system path {}", target));
+                } else if recursive {
+                    push(Severity::Medium, format!("recursively deletes {}{}", target, if force { " without prompting" } else { "" }));
+                }
+            }
+        }
+        "chmod" | "chown" | "chgrp" => {
+            let what = if name == "chmod" { "permissions" } else { "ownership" };
+            let recursive = has_flag(&args, 'R', "--recursive");
+            let mode = operands.first().copied().unwrap_or_default();
+            let world_writable = name == "chmod" && Regex::new(r"^(0?[0-7]?7[0-7]?7|.*[oa]\+\w*w|\+w)$").unwrap().is_match(mode);
+            for target in operands.iter().skip(1) {
+                if recursive && is_critical_path(target) {
+                    push(Severity::Critical, format!("recursively changes {} of {}", what, target));
+                } else if recursive && world_writable {
+                    push(Severity::High, format!("recursively makes everything under {} world-writable", target));
+                } else if recursive {
+                    push(Severity::Medium, format!("recursively changes {} of {}", what, target));
+                } else if is_system_file(target) || is_critical_path(target) {
+                    push(Severity::High, format!("changes {} of system path {}", what, target));
+                } else if world_writable {
+                    push(Severity::Low, format!("makes {} world-writable", target));
+                }
+            }
+        }
+        "dd" => {
+            for target in args.iter().filter_map(|a| a.strip_prefix("of=")) {
+                if is_block_device(target) {
+                    push(Severity::Critical, format!("dd overwrites block device {}", target));
+                } else if is_dotfile(target) || is_system_file(target) {
+                    push(Severity::High, format!("dd overwrites {}", target));
+                }
+            }
+        }
+        "wipefs" | "mkswap" => push(Severity::Critical, format!("{} erases the signatures on {}", name, operands.join(" "))),
+        _ if name.starts_with("mkfs") => push(Severity::Critical, format!("{} formats {}, destroying its contents", name, operands.join(" "))),
+        "fdisk" | "sfdisk" | "gdisk" | "parted" => push(Severity::High, format!("{} edits a partition table", name)),
+        "shred" => {
+            for target in &operands {
+                let severity = if is_block_device(target) { Severity::Critical } else { Severity::Medium };
+                push(severity, format!("shred irreversibly overwrites {}", target));
+            }
+        }
+        "tee" => {
+            let op = if has_flag(&args, 'a', "--append") { ">>" } else { ">" };
+            let mut nested = Vec::new();
+            for target in &operands {
+                check_redirect(op, target, &mut nested);
+            }
+            findings.extend(nested);
+        }
+        "mv" | "cp" | "install" | "ln" => {
+            if let Some(dest) = operands.last().filter(|_| operands.len() > 1) {
+                if is_dotfile(dest) {
+                    push(Severity::High, format!("replaces dotfile {}", dest));
+                } else if *dest == "/dev/null" {
+                    push(Severity::High, format!("{} into /dev/null destroys {}", name, operands[..operands.len() - 1].join(" ")));
+                } else if is_block_device(dest) {
+                    push(Severity::Critical, format!("writes over block device {}", dest));
+                } else if is_system_file(dest) || is_critical_path(dest) {
+                    push(Severity::High, format!("writes into system path {}", dest));
+                }
+            }
+            if name == "mv" {
+                for source in operands.iter().rev().skip(1) {
+                    if is_critical_path(source) {
+                        push(Severity::Critical, format!("moves {} away", source));
+                    }
+                }
+            }
+        }
+        "shutdown" | "reboot" | "halt" | "poweroff" => push(Severity::High, format!("{} stops the machine", name)),
+        "init" | "telinit" if matches!(operands.first(), Some(&"0") | Some(&"6")) => push(Severity::High, format!("{} {} stops the machine", name, operands[0])),
+        "kill" if operands.contains(&"-1") || args.windows(2).any(|w| w[1] == "-1" && w[0].starts_with('-')) => {
+            push(Severity::High, "kill -1 signals every process you are allowed to".to_string())
+        }
+        "killall" | "pkill" => push(Severity::Medium, format!("{} signals every matching process", name)),
+        "crontab" if args.contains(&"-r") => push(Severity::Medium, "crontab -r removes all of your cron jobs".to_string()),
+        "git" => match operands.first() {
+            Some(&"push") if has_flag(&args, 'f', "--force") || args.iter().any(|a| a.starts_with("--force")) => {
+                push(Severity::Medium, "force-push rewrites history on the remote".to_string())
+            }
+            Some(&"reset") if args.contains(&"--hard") => push(Severity::Medium, "git reset --hard discards uncommitted changes".to_string()),
+            Some(&"clean") if has_flag(&args, 'f', "--force") => push(Severity::Medium, "git clean deletes untracked files".to_string()),
+            _ => {}
+        },
+        "find" => {
+            if args.contains(&"-delete") {
+                push(Severity::Medium, "find -delete removes every match".to_string());
+            }
+            // The command after -exec runs once per match.
+            if let Some(at) = words.iter().position(|w| matches!(w.text.as_str(), "-exec" | "-execdir" | "-ok" | "-okdir")) {
+                let end = words[at + 1..].iter().position(|w| w.text == ";" || w.text == "+").map_or(words.len(), |p| p + at + 1);
+                check_simple(&words[at + 1..end], findings);
+            }
+        }
+        _ if INTERPRETERS.contains(&name) => {
+            // `sh -c "..."` and `eval "..."` run their argument as a command line.
+            let is_shell = matches!(name, "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish");
+            let script = match args.iter().position(|a| *a == "-c") {
+                Some(at) if is_shell => args.get(at + 1).map(|s| s.to_string()),
+                _ if name == "eval" => Some(args.join(" ")).filter(|s| !s.is_empty()),
+                _ => None,
+            };
+            if let Some(script) = script {
+                findings.extend(analyze(&script));
+            }
+            if words[start..].iter().any(|w| w.subs.iter().any(|s| mentions_download(s))) {
+                findings.push(Finding { severity: Severity::High, message: format!("runs a downloaded script with {} without reviewing it", name) });
+            }
+        }
+        _ => {}
+    }
+}
+
+// Flags `name() { name | name & }` definitions.
+fn is_fork_bomb(command: &str) -> bool {
+    let definition = Regex::new(r"(?P<name>[\w:.]+)\s*\(\)\s*\{(?P<body>[^}]*)\}").unwrap();
+    let found = definition.captures_iter(command).any(|caps| {
+        let body: String = caps["body"].split_whitespace().collect();
+        body.contains(&format!("{0}|{0}", &caps["name"])) && body.contains('&')
+    });
+    found
+}
+
+fn check_tokens(tokens: &[Token], findings: &mut Vec<Finding>) {
+    let commands = split(tokens);
+    for (i, command) in commands.iter().enumerate() {
+        let (start, _) = unwrap(&command.words);
+        let interpreter = command.words.get(start).map(|w| basename(&w.text)).filter(|p| INTERPRETERS.contains(p));
+        for redirect in &command.redirects {
+            check_redirect(&redirect.op, &redirect.target, findings);
+            for sub in &redirect.subs {
+                findings.extend(analyze(sub));
+            }
+            // `sh < <(curl ...)`
+            if let (Some(program), "<") = (interpreter, redirect.op.as_str()) {
+                if redirect.subs.iter().any(|s| mentions_download(s)) {
+                    findings.push(Finding { severity: Severity::High, message: format!("runs a downloaded script with {} without reviewing it", program) });
+                }
+            }
+        }
+        check_simple(&command.words, findings);
+        for word in &command.words {
+            for sub in &word.subs {
+                findings.extend(analyze(sub));
+            }
+        }
+        // `curl ... | sh`
+        let is_download = command.words.get(start).is_some_and(|w| DOWNLOADERS.contains(&basename(&w.text)));
+        if is_download && matches!(command.next.as_deref(), Some("|") | Some("|&")) {
+            if let Some(next) = commands.get(i + 1) {
+                let (start, _) = unwrap(&next.words);
+                if let Some(program) = next.words.get(start).map(|w| basename(&w.text)).filter(|p| INTERPRETERS.contains(p)) {
+                    findings.push(Finding { severity: Severity::High, message: format!("pipes a downloaded script straight into {}; the remote code runs unreviewed", program) });
+                }
+            }
+        }
+    }
+}
+
+// Findings for `command`, most severe first.
+pub fn analyze(command: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if is_fork_bomb(command) {
+        findings.push(Finding { severity: Severity::Critical, message: "fork bomb: the function spawns copies of itself until the system runs out of processes".to_string() });
+    }
+    match lex(command) {
+        Ok(tokens) => check_tokens(&tokens, &mut findings),
+        Err(e) => findings.push(Finding { severity: Severity::Low, message: format!("cannot fully parse the command ({}); only pattern checks were applied", e) }),
+    }
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    let mut seen = std::collections::HashSet::new();
+    findings.retain(|f| seen.insert(f.message.clone()));
+    findings
+}
+
+// High and critical findings need an explicit yes before running.
+pub fn requires_confirmation(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.severity >= Severity::High)
+}
+
+pub fn report(findings: &[Finding]) -> String {
+    let mut lines = vec!["Risk check:".to_string()];
+    lines.extend(findings.iter().map(|f| format!("  [{}] {}", f.severity, f.message)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worst(command: &str) -> Option<Severity> {
+        analyze(command).first().map(|f| f.severity)
+    }
+
+    fn mentions(command: &str, text: &str) -> bool {
+        analyze(command).iter().any(|f| f.message.contains(text))
+    }
+
+    #[test]
+    fn flags_recursive_deletes_of_root_and_home() {
+        for command in ["rm -rf /", "rm -rf ~", "rm -fr /*", "rm -r -f ~/"] {
+            assert_eq!(worst(command), Some(Severity::Critical), "{}", command);
+            assert!(requires_confirmation(&analyze(command)), "{}", command);
+        }
+    }
+
+    #[test]
+    fn looks_through_sudo_and_env() {
+        assert_eq!(worst("sudo rm -rf /"), Some(Severity::Critical));
+        assert_eq!(worst("sudo -u root env FOO=1 rm -rf /"), Some(Severity::Critical));
+        assert_eq!(worst("env -u HOME rm -rf ~"), Some(Severity::Critical));
+    }
+
+    #[test]
+    fn flags_piping_downloads_into_a_shell() {
+        for command in ["curl -fsSL https://example.com/install.sh | sh", "wget -qO- https://example.com/x | sudo bash", "bash <(curl -s https://example.com/x)"] {
+            assert!(requires_confirmation(&analyze(command)), "{}", command);
+            assert!(mentions(command, "downloaded script"), "{}", command);
+        }
+        assert!(!requires_confirmation(&analyze("curl -o install.sh https://example.com/install.sh")));
+    }
+
+    #[test]
+    fn flags_the_fork_bomb() {
+        assert_eq!(worst(":(){ :|:& };:"), Some(Severity::Critical));
+        assert!(mentions(":(){ :|:& };:", "fork bomb"));
+    }
+
+    #[test]
+    fn flags_writes_to_block_devices_and_mkfs() {
+        for command in ["dd if=/dev/zero of=/dev/sda bs=1M", "mkfs.ext4 /dev/sdb1", "mkfs -t ext4 /dev/sdb1"] {
+            assert_eq!(worst(command), Some(Severity::Critical), "{}", command);
+        }
+        assert!(!requires_confirmation(&analyze("dd if=/dev/zero of=disk.img bs=1M count=10")));
+    }
+
+    #[test]
+    fn flags_recursive_chmod_of_system_dirs() {
+        assert!(requires_confirmation(&analyze("chmod -R 777 /")));
+        assert!(requires_confirmation(&analyze("sudo chown -R nobody /etc")));
+        assert!(!requires_confirmation(&analyze("chmod -R 755 build")));
+    }
+
+    #[test]
+    fn flags_overwriting_dotfiles() {
+        for command in ["echo 'alias ls=rm' > ~/.bashrc", "echo x | tee ~/.zshrc", "cat key > $HOME/.ssh/authorized_keys"] {
+            assert!(requires_confirmation(&analyze(command)), "{}", command);
+        }
+        assert!(!requires_confirmation(&analyze("echo 'export A=1' >> ~/.bashrc")));
+    }
+
+    #[test]
+    fn leaves_everyday_commands_alone() {
+        for command in ["rm build/tmp.txt", "ls -la", "rm -rf target/debug", "git status && cargo build"] {
+            assert!(!analyze(command).iter().any(|f| f.severity >= Severity::High), "{}", command);
+            assert!(!requires_confirmation(&analyze(command)), "{}", command);
+        }
+    }
+}
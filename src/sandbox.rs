@@ -1,4 +1,169 @@
-// Placeholder for sandboxing functionality
-pub fn run_in_sandbox(_code: &str) -> Result<String, String> {
-    Err("Sandboxing not yet implemented".to_string())
-} 
\ No newline at end of file
+// Resource-limited execution for `run code`: rlimits, a wall-clock timeout and capped output
+use crate::usage::{self, Usage};
+use serde::Serialize;
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MAX_OUTPUT: usize = 1024 * 1024;
+
+#[derive(Clone, Copy)]
+pub struct Limits {
+    pub timeout: Duration,
+    // RLIMIT_CPU, in seconds.
+    pub cpu_seconds: Option<u64>,
+    // RLIMIT_AS. Compilers reserve far more address space than they use, so builds leave it unset.
+    pub memory_bytes: Option<u64>,
+    // RLIMIT_FSIZE: the largest file the program may write.
+    pub file_bytes: Option<u64>,
+}
+
+impl Limits {
+    // For running snippets.
+    pub fn program() -> Limits {
+        Limits { timeout: Duration::from_secs(10), cpu_seconds: Some(5), memory_bytes: Some(512 * 1024 * 1024), file_bytes: Some(64 * 1024 * 1024) }
+    }
+
+    // For compiling them.
+    pub fn build() -> Limits {
+        Limits { timeout: Duration::from_secs(120), cpu_seconds: Some(120), memory_bytes: None, file_bytes: Some(512 * 1024 * 1024) }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Outcome {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    pub truncated: bool,
+    pub usage: Usage,
+}
+
+impl Outcome {
+    pub fn success(&self) -> bool {
+        self.code == Some(0) && !self.timed_out
+    }
+
+    // Why the program stopped, when that was not a clean exit.
+    pub fn failure(&self, limits: &Limits) -> Option<String> {
+        if self.timed_out {
+            return Some(format!("[timed out after {}s]", limits.timeout.as_secs()));
+        }
+        #[cfg(unix)]
+        match self.signal {
+            Some(libc::SIGXCPU) => return Some("[killed: CPU time limit exceeded]".to_string()),
+            Some(libc::SIGXFSZ) => return Some("[killed: file size limit exceeded]".to_string()),
+            Some(signal) => return Some(format!("[killed by signal {}]", signal)),
+            None => {}
+        }
+        match self.code {
+            Some(0) | None => None,
+            Some(code) => Some(format!("[exit {}]", code)),
+        }
+    }
+}
+
+fn read_capped(mut reader: impl Read) -> (Vec<u8>, bool) {
+    let mut captured = Vec::new();
+    let mut truncated = false;
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let room = MAX_OUTPUT.saturating_sub(captured.len());
+        captured.extend_from_slice(&buffer[..n.min(room)]);
+        truncated |= n > room;
+    }
+    (captured, truncated)
+}
+
+#[cfg(unix)]
+fn apply_limits(command: &mut Command, limits: &Limits) {
+    use std::os::unix::process::CommandExt;
+    let limits = *limits;
+    // Its own process group, so the timeout also stops anything the program started.
+    command.process_group(0);
+    // Only async-signal-safe calls between fork and exec: setrlimit is one.
+    unsafe {
+        command.pre_exec(move || {
+            let set = |resource, soft: u64, hard: u64| {
+                let limit = libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t };
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            };
+            set(libc::RLIMIT_CORE, 0, 0)?;
+            if let Some(cpu) = limits.cpu_seconds {
+                // SIGXCPU at the soft limit says why; the hard limit's SIGKILL would not.
+                set(libc::RLIMIT_CPU, cpu, cpu + 1)?;
+            }
+            if let Some(memory) = limits.memory_bytes {
+                set(libc::RLIMIT_AS, memory, memory)?;
+            }
+            if let Some(file) = limits.file_bytes {
+                set(libc::RLIMIT_FSIZE, file, file)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn apply_limits(_command: &mut Command, _limits: &Limits) {}
+
+// Runs `command` to completion under `limits`, feeding it `stdin` and capturing its output.
+pub fn run(mut command: Command, stdin: Option<&[u8]>, limits: &Limits) -> io::Result<Outcome> {
+    command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() }).stdout(Stdio::piped()).stderr(Stdio::piped());
+    apply_limits(&mut command, limits);
+    let started = Instant::now();
+    let mut child = command.spawn()?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        let input = input.to_vec();
+        // A separate thread so a program that never reads cannot block us.
+        thread::spawn(move || {
+            let _ = io::Write::write_all(&mut pipe, &input);
+        });
+    }
+    let stdout = child.stdout.take().ok_or_else(|| io::Error::other("no stdout pipe"))?;
+    let stderr = child.stderr.take().ok_or_else(|| io::Error::other("no stderr pipe"))?;
+    let out = thread::spawn(move || read_capped(stdout));
+    let err = thread::spawn(move || read_capped(stderr));
+    let mut timed_out = false;
+    let (status, usage) = loop {
+        if let Some(done) = usage::wait(&mut child, false)? {
+            break done;
+        }
+        if started.elapsed() >= limits.timeout {
+            timed_out = true;
+            #[cfg(unix)]
+            unsafe {
+                libc::kill(-(child.id() as i32), libc::SIGKILL);
+            }
+            let _ = child.kill();
+            break usage::wait(&mut child, true)?.ok_or_else(|| io::Error::other("the program did not exit"))?;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let wall_seconds = started.elapsed().as_secs_f64();
+    let (stdout, out_truncated) = out.join().unwrap_or_default();
+    let (stderr, err_truncated) = err.join().unwrap_or_default();
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal = None;
+    Ok(Outcome {
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        code: status.code(),
+        signal,
+        timed_out,
+        truncated: out_truncated || err_truncated,
+        usage: Usage { wall_seconds, ..usage },
+    })
+}
@@ -0,0 +1,216 @@
+// Recursive, gitignore-aware pattern search used by `search` (and reused by `replace`)
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use regex::bytes::{Regex, RegexBuilder};
+use regex::Regex as TextRegex;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Files with a NUL byte in their first block are treated as binary and skipped.
+const BINARY_PROBE: usize = 8192;
+const MAX_OUTPUT_LINES: usize = 1000;
+
+#[derive(Default)]
+pub struct SearchOptions {
+    pub ignore_case: bool,
+    pub whole_word: bool,
+    pub context: usize,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    // Also search hidden files and paths excluded by .gitignore and friends.
+    pub hidden: bool,
+    pub no_ignore: bool,
+}
+
+impl SearchOptions {
+    // Parses trailing flags such as `ignoring case`, `whole word`, `with 2 lines of context`,
+    // `including *.rs,*.toml`, `excluding target`, `including hidden` and `no ignore`.
+    pub fn parse(options: &str) -> Result<SearchOptions, String> {
+        let mut opts = SearchOptions::default();
+        let case = TextRegex::new(r"\b(?:ignoring|ignore)\s+case\b|\bcase[- ]insensitive\b").unwrap();
+        let word = TextRegex::new(r"\bwhole\s+words?\b").unwrap();
+        let context = TextRegex::new(r"\bwith\s+(\d+)\s+lines?\s+of\s+context\b|\bcontext\s+(\d+)\b").unwrap();
+        let hidden = TextRegex::new(r"\bincluding\s+hidden\b").unwrap();
+        let no_ignore = TextRegex::new(r"\bno\s+ignore\b").unwrap();
+        let include = TextRegex::new(r"\bincluding\s+(\S+)").unwrap();
+        let exclude = TextRegex::new(r"\bexcluding\s+(\S+)").unwrap();
+        opts.ignore_case = case.is_match(options);
+        opts.whole_word = word.is_match(options);
+        opts.hidden = hidden.is_match(options);
+        opts.no_ignore = no_ignore.is_match(options);
+        if let Some(caps) = context.captures(options) {
+            let n = caps.get(1).or(caps.get(2)).map_or("0", |m| m.as_str());
+            opts.context = n.parse().map_err(|_| format!("Invalid context size: {}", n))?;
+        }
+        // `including hidden` is a flag, not a glob, so strip it before collecting globs.
+        let rest = hidden.replace_all(options, "").to_string();
+        let globs = |re: &TextRegex| -> Vec<String> {
+            re.captures_iter(&rest).flat_map(|c| c[1].split(',').filter(|g| !g.is_empty()).map(str::to_string).collect::<Vec<_>>()).collect()
+        };
+        opts.include = globs(&include);
+        opts.exclude = globs(&exclude);
+        let leftover = [&case, &word, &context, &no_ignore, &include, &exclude]
+            .iter()
+            .fold(rest, |acc, re| re.replace_all(&acc, "").to_string());
+        let leftover = leftover.replace([',', ' '], "");
+        if !leftover.is_empty() {
+            return Err(format!("Unrecognized search option(s): {}", leftover));
+        }
+        Ok(opts)
+    }
+
+    pub fn regex(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let pattern = if self.whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern.to_string() };
+        RegexBuilder::new(&pattern).case_insensitive(self.ignore_case).build()
+    }
+
+    // A walker over `root` honouring .gitignore, hidden-file rules and the include/exclude globs.
+    pub fn walker(&self, root: &Path) -> Result<WalkBuilder, Box<dyn Error>> {
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &self.include {
+            overrides.add(glob)?;
+        }
+        for glob in &self.exclude {
+            overrides.add(&format!("!{}", glob))?;
+        }
+        let mut builder = WalkBuilder::new(root);
+        builder.standard_filters(!self.no_ignore).hidden(!self.hidden).overrides(overrides.build()?);
+        Ok(builder)
+    }
+}
+
+// Splits `<target> [options]`, where the target may be double-quoted to allow spaces.
+pub fn parse_target(rest: &str) -> Result<(PathBuf, SearchOptions), String> {
+    let re = TextRegex::new(r#"^(?:"(?P<quoted>[^"]+)"|(?P<target>\S+))(?P<opts>.*)$"#).unwrap();
+    let caps = re.captures(rest.trim()).ok_or("Missing file or directory")?;
+    let target = caps.name("quoted").or(caps.name("target")).map_or("", |m| m.as_str());
+    if !Path::new(target).exists() {
+        return Err(format!("Target '{}' not found", target));
+    }
+    Ok((PathBuf::from(target), SearchOptions::parse(&caps["opts"])?))
+}
+
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+    pub is_match: bool,
+}
+
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub matches: usize,
+    // Matching lines plus their context, in file order.
+    pub lines: Vec<Line>,
+}
+
+pub struct SearchResults {
+    pub files: Vec<FileMatches>,
+    pub searched: usize,
+    pub skipped_binary: usize,
+    pub errors: usize,
+}
+
+pub fn is_binary(content: &[u8]) -> bool {
+    content[..content.len().min(BINARY_PROBE)].contains(&0)
+}
+
+fn search_content(path: &Path, content: &[u8], re: &Regex, context: usize) -> Option<FileMatches> {
+    let content = content.strip_suffix(b"\n").unwrap_or(content);
+    let lines: Vec<&[u8]> = content.split(|&b| b == b'\n').collect();
+    let hits: Vec<usize> = lines.iter().enumerate().filter(|(_, line)| re.is_match(line)).map(|(i, _)| i).collect();
+    if hits.is_empty() {
+        return None;
+    }
+    let mut keep = vec![false; lines.len()];
+    for &i in &hits {
+        let end = (i + context).min(lines.len() - 1);
+        keep[i.saturating_sub(context)..=end].iter_mut().for_each(|k| *k = true);
+    }
+    let lines = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| keep[*i])
+        .map(|(i, line)| {
+            let text = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line)).into_owned();
+            Line { number: i + 1, text, is_match: hits.binary_search(&i).is_ok() }
+        })
+        .collect();
+    Some(FileMatches { path: path.to_path_buf(), matches: hits.len(), lines })
+}
+
+// Searches every non-binary file under `root` in parallel. Unreadable files are counted
+// rather than aborting the search.
+pub fn search(root: &Path, re: &Regex, opts: &SearchOptions) -> Result<SearchResults, Box<dyn Error>> {
+    let results = Mutex::new(SearchResults { files: Vec::new(), searched: 0, skipped_binary: 0, errors: 0 });
+    opts.walker(root)?.build_parallel().run(|| {
+        let results = &results;
+        Box::new(move |entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    results.lock().unwrap().errors += 1;
+                    return WalkState::Continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                return WalkState::Continue;
+            }
+            let content = match std::fs::read(entry.path()) {
+                Ok(content) => content,
+                Err(_) => {
+                    results.lock().unwrap().errors += 1;
+                    return WalkState::Continue;
+                }
+            };
+            if is_binary(&content) {
+                results.lock().unwrap().skipped_binary += 1;
+                return WalkState::Continue;
+            }
+            let found = search_content(entry.path(), &content, re, opts.context);
+            let mut results = results.lock().unwrap();
+            results.searched += 1;
+            results.files.extend(found);
+            WalkState::Continue
+        })
+    });
+    let mut results = results.into_inner().unwrap();
+    results.files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(results)
+}
+
+// grep-style output: `path:line: text` for matches, `path-line- text` for context and `--`
+// between separate groups, followed by a summary.
+pub fn format(results: &SearchResults) -> String {
+    let total: usize = results.files.iter().map(|f| f.matches).sum();
+    let mut out = Vec::new();
+    for file in &results.files {
+        let mut previous = None;
+        for line in &file.lines {
+            if previous.is_some_and(|p| p + 1 != line.number) {
+                out.push("--".to_string());
+            }
+            let sep = if line.is_match { ':' } else { '-' };
+            out.push(format!("{}{}{}{} {}", file.path.display(), sep, line.number, sep, line.text));
+            previous = Some(line.number);
+        }
+    }
+    let shown = out.len();
+    out.truncate(MAX_OUTPUT_LINES);
+    if shown > MAX_OUTPUT_LINES {
+        out.push(format!("... {} more line(s) not shown", shown - MAX_OUTPUT_LINES));
+    }
+    let mut summary = if total == 0 {
+        format!("No matches found ({} file(s) searched)", results.searched)
+    } else {
+        format!("{} match(es) in {} file(s) ({} file(s) searched)", total, results.files.len(), results.searched)
+    };
+    if results.skipped_binary > 0 {
+        summary.push_str(&format!(", {} binary file(s) skipped", results.skipped_binary));
+    }
+    if results.errors > 0 {
+        summary.push_str(&format!(", {} unreadable path(s)", results.errors));
+    }
+    out.push(summary);
+    out.join("\n")
+}
@@ -0,0 +1,522 @@
+// Shell command execution for `run`, `schedule` and `watch`: live output, exit status, timeouts
+// and an optional persistent session
+use crate::explain;
+use crate::humanize;
+use crate::usage::{self, Usage};
+use regex::Regex;
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_MAX_OUTPUT: usize = 1024 * 1024;
+// How long a timed-out command gets to exit after SIGTERM before it is killed.
+const KILL_GRACE: Duration = Duration::from_secs(2);
+
+// Captured stdout and stderr of the most recent command, for `show last output`.
+static LAST_OUTPUT: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+pub struct ExecOptions {
+    pub timeout: Option<Duration>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    // Bytes of each stream kept in the result; everything is still shown live.
+    pub max_output: usize,
+}
+
+impl Default for ExecOptions {
+    // `TERMINALAI_RUN_TIMEOUT` (seconds) sets a default timeout.
+    fn default() -> Self {
+        let timeout = std::env::var("TERMINALAI_RUN_TIMEOUT").ok().and_then(|t| t.parse().ok()).filter(|&t| t > 0).map(Duration::from_secs);
+        ExecOptions { timeout, cwd: None, env: Vec::new(), max_output: DEFAULT_MAX_OUTPUT }
+    }
+}
+
+impl ExecOptions {
+    // Parses `in <dir>`, `with NAME=value ...`, `timeout 30s` (or `5m`) and `max output 64KB`.
+    pub fn parse(options: &str) -> Result<ExecOptions, String> {
+        let mut opts = ExecOptions::default();
+        let dir = Regex::new(r#"\bin\s+(?:"(?P<quoted>[^"]+)"|(?P<dir>\S+))"#).unwrap();
+        let env = Regex::new(r"\bwith\s+(?P<vars>(?:[A-Za-z_]\w*=\S*(?:\s+|$))+)").unwrap();
+        let timeout = Regex::new(r"\btimeout\s+(\d+)\s*(s|secs?|seconds?|m|mins?|minutes?)?\b").unwrap();
+        let max_output = Regex::new(r"\bmax(?:imum)?\s+output\s+(\d+(?:\.\d+)?\s*[a-zA-Z]*)").unwrap();
+        if let Some(caps) = dir.captures(options) {
+            let path = PathBuf::from(caps.name("quoted").or(caps.name("dir")).map_or("", |m| m.as_str()));
+            if !path.is_dir() {
+                return Err(format!("'{}' is not a directory", path.display()));
+            }
+            opts.cwd = Some(path);
+        }
+        if let Some(caps) = env.captures(options) {
+            for var in caps["vars"].split_whitespace() {
+                let (name, value) = var.split_once('=').unwrap_or((var, ""));
+                opts.env.push((name.to_string(), value.to_string()));
+            }
+        }
+        if let Some(caps) = timeout.captures(options) {
+            let amount: u64 = caps[1].parse().map_err(|_| "Invalid timeout")?;
+            let minutes = caps.get(2).is_some_and(|u| u.as_str().starts_with('m'));
+            opts.timeout = Some(Duration::from_secs(if minutes { amount * 60 } else { amount }));
+        }
+        if let Some(caps) = max_output.captures(options) {
+            let size = humanize::parse_size(&caps[1]).ok_or(format!("Invalid size: {}", &caps[1]))?;
+            opts.max_output = usize::try_from(size).unwrap_or(usize::MAX);
+        }
+        let rest = [&dir, &env, &timeout, &max_output].iter().fold(options.to_string(), |acc, re| re.replace_all(&acc, "").to_string());
+        let rest = Regex::new(r"\band\b").unwrap().replace_all(&rest, "").replace([',', ' '], "");
+        if !rest.is_empty() {
+            return Err(format!("Unrecognized run option(s): {}", rest));
+        }
+        Ok(opts)
+    }
+}
+
+#[derive(Serialize)]
+pub struct ExecResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    // Also in `usage` as wall time.
+    #[serde(skip)]
+    pub duration: Duration,
+    pub timed_out: bool,
+    pub truncated: bool,
+    pub usage: Usage,
+}
+
+impl ExecResult {
+    // One line such as `[exit 0 in 0.42s]` or `[timed out, killed after 30.00s]`, followed by the
+    // resource usage with `--profile`.
+    pub fn summary(&self) -> String {
+        let elapsed = format!("{:.2}s", self.duration.as_secs_f64());
+        let mut summary = if self.timed_out {
+            format!("[timed out, killed after {}]", elapsed)
+        } else if let Some(signal) = self.signal {
+            format!("[terminated by signal {} after {}]", signal, elapsed)
+        } else {
+            format!("[exit {} in {}]", self.code.unwrap_or(-1), elapsed)
+        };
+        if self.truncated {
+            summary.push_str(" (captured output was truncated)");
+        }
+        if usage::profiling() {
+            summary.push('\n');
+            summary.push_str(&self.usage.report());
+        }
+        summary
+    }
+}
+
+fn colors_enabled() -> bool {
+    io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+// Single-quotes `value` for the shell unless it is made only of characters the shell leaves alone.
+pub fn quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || "-_./:=+,@%".contains(c)) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+// Shows a chunk of command output, stderr in red when `color` is set. Failing to echo (e.g. a
+// closed terminal) should not stop the capture.
+fn echo(chunk: &[u8], is_stderr: bool, color: bool) {
+    let _ = if is_stderr {
+        let mut err = io::stderr().lock();
+        if color {
+            err.write_all(b"\x1b[31m").and_then(|_| err.write_all(chunk)).and_then(|_| err.write_all(b"\x1b[0m"))
+        } else {
+            err.write_all(chunk)
+        }
+    } else {
+        let mut out = io::stdout().lock();
+        out.write_all(chunk).and_then(|_| out.flush())
+    };
+}
+
+// Appends up to `limit` bytes in total, returning whether anything was dropped.
+fn keep(captured: &mut Vec<u8>, chunk: &[u8], limit: usize) -> bool {
+    let room = limit.saturating_sub(captured.len());
+    captured.extend_from_slice(&chunk[..chunk.len().min(room)]);
+    chunk.len() > room
+}
+
+// Copies `reader` to the terminal as it arrives, keeping up to `limit` bytes.
+fn pump(mut reader: impl Read, is_stderr: bool, limit: usize, at_line_start: Arc<AtomicBool>) -> (Vec<u8>, bool) {
+    let color = is_stderr && colors_enabled();
+    let mut captured = Vec::new();
+    let mut truncated = false;
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        echo(&buffer[..n], is_stderr, color);
+        at_line_start.store(buffer[..n].ends_with(b"\n"), Ordering::Relaxed);
+        truncated |= keep(&mut captured, &buffer[..n], limit);
+    }
+    (captured, truncated)
+}
+
+#[cfg(unix)]
+fn signal_group(child: &Child, signal: i32) {
+    // The child leads its own process group, so this reaches everything it started.
+    unsafe {
+        libc::kill(-(child.id() as i32), signal);
+    }
+}
+
+// Stops a timed-out command: SIGTERM to its process group, then SIGKILL after a grace period.
+fn terminate(child: &mut Child) -> io::Result<(ExitStatus, Usage)> {
+    #[cfg(unix)]
+    {
+        signal_group(child, libc::SIGTERM);
+        let deadline = Instant::now() + KILL_GRACE;
+        while Instant::now() < deadline {
+            if let Some(done) = usage::wait(child, false)? {
+                return Ok(done);
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        signal_group(child, libc::SIGKILL);
+    }
+    let _ = child.kill();
+    usage::wait(child, true)?.ok_or_else(|| io::Error::other("the command did not exit"))
+}
+
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+struct Captured {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    truncated: bool,
+}
+
+// Builds the result and remembers it for `show last output` and `show last result`.
+fn finish(captured: Captured, code: Option<i32>, signal: Option<i32>, duration: Duration, usage: Usage, timed_out: bool) -> ExecResult {
+    let result = ExecResult {
+        stdout: String::from_utf8_lossy(&captured.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&captured.stderr).into_owned(),
+        code,
+        signal,
+        duration,
+        timed_out,
+        truncated: captured.truncated,
+        usage: Usage { wall_seconds: duration.as_secs_f64(), ..usage },
+    };
+    set_last_output(&result.stdout, &result.stderr);
+    usage::record(&result);
+    result
+}
+
+// Runs `cmd` in the shell session if one was started, otherwise in a fresh platform shell,
+// streaming stdout and stderr (in red on a colour terminal) while capturing them.
+pub fn run(cmd: &str, opts: &ExecOptions) -> io::Result<ExecResult> {
+    let mut session = SESSION.lock().map_err(|_| io::Error::other("shell session lock poisoned"))?;
+    if let Some(active) = session.as_mut() {
+        let (result, alive) = active.run(cmd, opts)?;
+        if !alive {
+            *session = None;
+            println!("The shell session ended; later commands run in a fresh shell.");
+        }
+        return Ok(result);
+    }
+    drop(session);
+    run_once(cmd, opts)
+}
+
+fn run_once(cmd: &str, opts: &ExecOptions) -> io::Result<ExecResult> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    };
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    if let Some(cwd) = &opts.cwd {
+        command.current_dir(cwd);
+    }
+    command.envs(opts.env.iter().map(|(k, v)| (k, v)));
+    // A separate process group lets a timeout kill the whole pipeline. Without a timeout the
+    // command stays in ours so Ctrl+C reaches it.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        if opts.timeout.is_some() {
+            command.process_group(0);
+        }
+        // Forces a real fork. With the default vfork-style spawn the child runs on our address
+        // space until exec, and the kernel then reports our RSS as the child's peak.
+        unsafe {
+            command.pre_exec(|| Ok(()));
+        }
+    }
+    let started = Instant::now();
+    let mut child = command.spawn()?;
+    let at_line_start = Arc::new(AtomicBool::new(true));
+    let stdout = child.stdout.take().ok_or_else(|| io::Error::other("no stdout pipe"))?;
+    let stderr = child.stderr.take().ok_or_else(|| io::Error::other("no stderr pipe"))?;
+    let (limit, flag) = (opts.max_output, at_line_start.clone());
+    let out = thread::spawn(move || pump(stdout, false, limit, flag));
+    let flag = at_line_start.clone();
+    let err = thread::spawn(move || pump(stderr, true, limit, flag));
+    let mut timed_out = false;
+    let (status, usage) = loop {
+        if let Some(done) = usage::wait(&mut child, false)? {
+            break done;
+        }
+        if opts.timeout.is_some_and(|t| started.elapsed() >= t) {
+            timed_out = true;
+            break terminate(&mut child)?;
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    let duration = started.elapsed();
+    let (stdout, out_truncated) = out.join().unwrap_or_default();
+    let (stderr, err_truncated) = err.join().unwrap_or_default();
+    if !at_line_start.load(Ordering::Relaxed) {
+        println!();
+    }
+    let captured = Captured { stdout, stderr, truncated: out_truncated || err_truncated };
+    Ok(finish(captured, status.code(), exit_signal(&status), duration, usage, timed_out))
+}
+
+// Also recorded by `run code` and `run file`, so any of them can feed the next one's stdin.
+pub fn set_last_output(stdout: &str, stderr: &str) {
+    if let Ok(mut last) = LAST_OUTPUT.lock() {
+        *last = Some((stdout.to_string(), stderr.to_string()));
+    }
+}
+
+pub fn last_output() -> Option<(String, String)> {
+    LAST_OUTPUT.lock().ok()?.clone()
+}
+
+// A long-lived shell for `start shell session`, so `cd`, `export` and functions carry over
+// between `run` commands. Each command is followed by a sentinel line on stdout carrying its
+// exit status and the shell's working directory, and one on stderr marking the end of its
+// error output.
+struct Session {
+    shell: &'static str,
+    child: Child,
+    stdin: ChildStdin,
+    // Lines from stdout (false) and stderr (true); None when the stream closes.
+    output: Receiver<(bool, Option<Vec<u8>>)>,
+    // The shell's working directory after the last command.
+    pwd: Option<PathBuf>,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+impl Session {
+    fn start() -> io::Result<Session> {
+        // dash exits on a syntax error even inside `eval`; bash survives one, so prefer it.
+        let (shell, args): (&'static str, &[&str]) = if explain::on_path("bash") { ("bash", &["--noprofile", "--norc"]) } else { ("sh", &[]) };
+        let mut command = Command::new(shell);
+        command.args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        // Its own process group, so a timeout can kill the session and everything it started.
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command.spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| io::Error::other("no stdin pipe"))?;
+        let stdout = child.stdout.take().ok_or_else(|| io::Error::other("no stdout pipe"))?;
+        let stderr = child.stderr.take().ok_or_else(|| io::Error::other("no stderr pipe"))?;
+        let (tx, rx) = mpsc::channel();
+        let readers: [(Box<dyn Read + Send>, bool); 2] = [(Box::new(stdout), false), (Box::new(stderr), true)];
+        for (reader, is_stderr) in readers {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(reader);
+                loop {
+                    let mut line = Vec::new();
+                    match reader.read_until(b'\n', &mut line) {
+                        Ok(0) | Err(_) => {
+                            let _ = tx.send((is_stderr, None));
+                            break;
+                        }
+                        Ok(_) => {
+                            if tx.send((is_stderr, Some(line))).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+        Ok(Session { shell, child, stdin, output: rx, pwd: None })
+    }
+
+    fn kill(&mut self) {
+        #[cfg(unix)]
+        signal_group(&self.child, libc::SIGKILL);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+
+    // The script sent for one command: follow the REPL's directory if it changed, run the
+    // command (in a subshell when it has its own directory or variables), then print sentinels.
+    fn script(&self, cmd: &str, opts: &ExecOptions, token: &str) -> String {
+        let mut script = String::new();
+        if let Ok(cwd) = std::env::current_dir() {
+            if self.pwd.as_ref() != Some(&cwd) {
+                script.push_str(&format!("cd -- {} 2>/dev/null\n", quote(&cwd.to_string_lossy())));
+            }
+        }
+        // Commands must not read the rest of the script from the session's stdin.
+        let eval = format!("eval {} </dev/null", quote(cmd));
+        if opts.cwd.is_some() || !opts.env.is_empty() {
+            let mut setup = String::new();
+            if let Some(dir) = &opts.cwd {
+                setup.push_str(&format!("cd -- {} && ", quote(&dir.to_string_lossy())));
+            }
+            for (name, value) in &opts.env {
+                setup.push_str(&format!("export {}={}; ", name, quote(value)));
+            }
+            script.push_str(&format!("( {}{} )\n", setup, eval));
+        } else {
+            script.push_str(&eval);
+            script.push('\n');
+        }
+        script.push_str(&format!("printf '%s %d %s\\n' {0} \"$?\" \"$PWD\"; printf '%s\\n' {0} >&2\n", token));
+        script
+    }
+
+    // Returns the result and whether the session is still usable.
+    fn run(&mut self, cmd: &str, opts: &ExecOptions) -> io::Result<(ExecResult, bool)> {
+        let started = Instant::now();
+        let check = Command::new(self.shell).args(["-n", "-c", cmd]).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::piped()).output()?;
+        if !check.status.success() {
+            echo(&check.stderr, true, colors_enabled());
+            let captured = Captured { stdout: Vec::new(), stderr: check.stderr, truncated: false };
+            return Ok((finish(captured, check.status.code(), None, started.elapsed(), Usage::default(), false), true));
+        }
+        let token = format!("__TERMINALAI_{:016x}__", rand::random::<u64>());
+        let script = self.script(cmd, opts, &token);
+        let before = usage::totals(self.child.id());
+        self.stdin.write_all(script.as_bytes())?;
+        self.stdin.flush()?;
+        let color = colors_enabled();
+        let mut captured = Captured { stdout: Vec::new(), stderr: Vec::new(), truncated: false };
+        let (mut out_done, mut err_done, mut alive) = (false, false, true);
+        let mut code = None;
+        let mut at_line_start = true;
+        // The sentinels the shell wrote, which are not the command's output.
+        let mut sentinels = 0;
+        while !(out_done && err_done) {
+            if opts.timeout.is_some_and(|t| started.elapsed() >= t) {
+                self.kill();
+                return Ok((finish(captured, None, None, started.elapsed(), Usage::default(), true), false));
+            }
+            let (is_stderr, line) = match self.output.recv_timeout(Duration::from_millis(100)) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => (false, None),
+            };
+            let Some(line) = line else {
+                // The shell exited, e.g. the command ran `exit`.
+                alive = false;
+                out_done |= !is_stderr;
+                err_done |= is_stderr;
+                continue;
+            };
+            // Output without a trailing newline ends up on the sentinel's line.
+            let at = line.windows(token.len()).position(|w| w == token.as_bytes());
+            let content = &line[..at.unwrap_or(line.len())];
+            if !content.is_empty() {
+                echo(content, is_stderr, color && is_stderr);
+                at_line_start = content.ends_with(b"\n");
+                let stream = if is_stderr { &mut captured.stderr } else { &mut captured.stdout };
+                captured.truncated |= keep(stream, content, opts.max_output);
+            }
+            let Some(at) = at else {
+                continue;
+            };
+            sentinels += line.len() - at;
+            if is_stderr {
+                err_done = true;
+            } else {
+                out_done = true;
+                let status = String::from_utf8_lossy(&line[at + token.len()..]).trim_end_matches('\n').to_string();
+                let (status, pwd) = status.trim_start().split_once(' ').unwrap_or((status.trim(), ""));
+                code = status.parse().ok();
+                if !pwd.is_empty() {
+                    let pwd = PathBuf::from(pwd);
+                    // Keep the REPL's directory in step with the shell's.
+                    if std::env::set_current_dir(&pwd).is_ok() {
+                        self.pwd = Some(pwd);
+                    }
+                }
+            }
+        }
+        if !at_line_start {
+            println!();
+        }
+        // The shell's counters also cover every earlier command, so take the difference. An exited
+        // shell can still be read until it is reaped.
+        let mut used = usage::totals(self.child.id()).since(&before);
+        used.read_bytes = used.read_bytes.map(|b| b.saturating_sub(script.len() as u64));
+        used.written_bytes = used.written_bytes.map(|b| b.saturating_sub(sentinels as u64));
+        let mut signal = None;
+        if !alive {
+            let status = self.child.wait()?;
+            code = status.code();
+            signal = exit_signal(&status);
+        }
+        Ok((finish(captured, code, signal, started.elapsed(), used, false), alive))
+    }
+}
+
+// Starts the session, returning the shell it runs.
+pub fn start_session() -> io::Result<Option<&'static str>> {
+    let mut session = SESSION.lock().map_err(|_| io::Error::other("shell session lock poisoned"))?;
+    if session.is_some() {
+        return Ok(None);
+    }
+    let started = Session::start()?;
+    let shell = started.shell;
+    *session = Some(started);
+    Ok(Some(shell))
+}
+
+// Returns whether a session was running.
+pub fn end_session() -> bool {
+    let Ok(mut session) = SESSION.lock() else {
+        return false;
+    };
+    match session.take() {
+        Some(mut active) => {
+            active.kill();
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,52 @@
+// Fixtures shared by the unit tests
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+// A fresh directory under the system temp dir, removed again when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("terminalai-{}-{}-{:08x}", name, std::process::id(), rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+static DATA_DIR: Mutex<()> = Mutex::new(());
+
+// Points `paths::data_dir` (the undo journal and the like) at a fresh directory until dropped.
+// The variable is process-wide, so tests holding one run one at a time.
+pub struct DataDir {
+    dir: TempDir,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl DataDir {
+    pub fn fresh() -> DataDir {
+        let lock = DATA_DIR.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = TempDir::new("data");
+        std::env::set_var("TERMINALAI_DATA_DIR", dir.path());
+        DataDir { dir, _lock: lock }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl Drop for DataDir {
+    fn drop(&mut self) {
+        std::env::remove_var("TERMINALAI_DATA_DIR");
+    }
+}
@@ -0,0 +1,214 @@
+// Natural-language to shell translation for `run`: a template library, an optional local LLM
+// and a per-instruction cache
+use crate::{explain, shell};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const DEFAULT_MODEL: &str = "llama3";
+const AS_TYPED: &str = "as typed";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Translation {
+    pub command: String,
+    // Where the command came from: "template", "ollama llama3", "edited", ...
+    pub source: String,
+    #[serde(skip)]
+    pub cached: bool,
+}
+
+pub trait Translator {
+    fn name(&self) -> String;
+    fn translate(&self, instruction: &str) -> Result<Option<String>, Box<dyn Error>>;
+}
+
+// `(pattern, command)` pairs. `{name}` is filled from the named group, shell-quoted, and
+// `{name=default}` supplies a value when the group did not match. Groups used inside quotes in
+// the command only match characters that need no quoting.
+const TEMPLATES: &[(&str, &str)] = &[
+    (r"^(?:list|show) (?:all )?(?:the )?hidden files(?: in (?P<dir>\S+))?$", "ls -A {dir=.}"),
+    (r"^(?:list|show) (?:all )?(?:the )?files(?: in (?P<dir>\S+))?(?: with details)?$", "ls -la {dir=.}"),
+    (r"^(?:find|list|show) (?:all )?(?:the )?(?:files )?larger than (?P<size>\d+[kmg])b?(?: in (?P<dir>\S+))?$", "find {dir=.} -type f -size +{size}"),
+    (r"^(?:find|list|show) (?:all )?(?:the )?\.?(?P<ext>\w+) files(?: in (?P<dir>\S+))?$", "find {dir=.} -type f -name '*.{ext}'"),
+    (r"^(?:find|locate) (?:the )?(?:file|files) named (?P<name>\S+)(?: in (?P<dir>\S+))?$", "find {dir=.} -name {name}"),
+    (r"^(?:find|list|show) (?:files )?modified (?:in the last|within) (?P<n>\d+) days?(?: in (?P<dir>\S+))?$", "find {dir=.} -type f -mtime -{n}"),
+    (r"^(?:search|grep|look) for (?P<pattern>.+?) in (?P<path>\S+)$", "grep -rn {pattern} {path}"),
+    (r"^count (?:the )?lines (?:in|of) (?P<file>\S+)$", "wc -l {file}"),
+    (r"^count (?:the )?files in (?P<dir>\S+)$", "find {dir} -type f | wc -l"),
+    (r"^show (?:the )?(?:first|top) (?P<n>\d+) lines of (?P<file>\S+)$", "head -n {n} {file}"),
+    (r"^show (?:the )?last (?P<n>\d+) lines of (?P<file>\S+)$", "tail -n {n} {file}"),
+    (r"^(?:follow|watch) (?:the )?(?:log )?(?:file )?(?P<file>\S+\.log)$", "tail -f {file}"),
+    (r"^(?:show )?(?:the )?(?:size|disk usage) of (?P<path>\S+)$", "du -sh {path}"),
+    (r"^show (?:the )?(?:disk usage|free (?:disk )?space|disk space)$", "df -h"),
+    (r"^show (?:the )?memory(?: usage)?$", "free -h"),
+    (r"^(?:show|list) (?:all )?(?:the )?(?:running )?processes$", "ps aux"),
+    (r"^(?:kill|stop) (?:the )?process(?:es)? (?:named )?(?P<name>\S+)$", "pkill {name}"),
+    (r"^(?:what|which) (?:process )?is (?:using|listening on) port (?P<port>\d+)$", "lsof -i :{port}"),
+    (r"^(?:show|what is|what's) (?:the )?(?:current|working) directory$", "pwd"),
+    (r"^(?:create|make) (?:a )?(?:new )?(?:directory|folder) (?:called |named )?(?P<dir>\S+)$", "mkdir -p {dir}"),
+    (r"^(?:create|make) (?:an )?(?:empty )?file (?:called |named )?(?P<file>\S+)$", "touch {file}"),
+    (r"^(?:delete|remove) (?:the )?(?:directory|folder) (?P<dir>\S+)$", "rm -r {dir}"),
+    (r"^(?:delete|remove) (?:the )?(?:file )?(?P<file>\S+)$", "rm {file}"),
+    (r"^(?:copy) (?P<from>\S+) to (?P<to>\S+)$", "cp -r {from} {to}"),
+    (r"^(?:move|rename) (?P<from>\S+) to (?P<to>\S+)$", "mv {from} {to}"),
+    (r"^make (?P<file>\S+) executable$", "chmod +x {file}"),
+    (r"^(?:compress|archive|zip up) (?P<dir>[^\s/]+)/?$", "tar -czf {dir}.tar.gz {dir}"),
+    (r"^(?:extract|unpack|untar) (?P<file>\S+\.(?:tar\.gz|tgz))$", "tar -xzf {file}"),
+    (r"^(?:extract|unzip) (?P<file>\S+\.zip)$", "unzip {file}"),
+    (r"^replace (?P<from>[\w.-]+) with (?P<to>[\w.-]+) in (?P<file>\S+)$", "sed -i 's/{from}/{to}/g' {file}"),
+    (r"^download (?P<url>https?://\S+)$", "curl -LO {url}"),
+    (r"^(?:show|what is|what's) my (?:ip|ip address)$", "hostname -I"),
+    (r"^(?:show|list) (?:the )?environment(?: variables)?$", "env"),
+    (r"^(?:show )?(?:the )?git status$", "git status"),
+    (r"^show (?:the )?(?:last|recent) (?:(?P<n>\d+) )?commits$", "git log --oneline -n {n=10}"),
+    (r"^(?:show|list) (?:the )?(?:git )?branches$", "git branch -a"),
+    (r"^(?:show )?(?:the )?uptime$", "uptime"),
+];
+
+pub struct Templates;
+
+impl Translator for Templates {
+    fn name(&self) -> String {
+        "template".to_string()
+    }
+
+    fn translate(&self, instruction: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let placeholder = Regex::new(r"\{(\w+)(?:=([^}]*))?\}")?;
+        let instruction = instruction.trim().trim_end_matches(['.', '!', '?']);
+        for (pattern, template) in TEMPLATES {
+            let re = Regex::new(&format!("(?i){}", pattern))?;
+            let Some(caps) = re.captures(instruction) else {
+                continue;
+            };
+            let command = placeholder.replace_all(template, |p: &regex::Captures| match caps.name(&p[1]) {
+                Some(value) => shell::quote(value.as_str().trim_matches(['"', '\''])),
+                None => p.get(2).map_or(String::new(), |d| d.as_str().to_string()),
+            });
+            return Ok(Some(command.into_owned()));
+        }
+        Ok(None)
+    }
+}
+
+// An Ollama-compatible server, configured with `TERMINALAI_LLM_URL` (e.g. http://localhost:11434)
+// and `TERMINALAI_LLM_MODEL`.
+pub struct Ollama {
+    pub url: String,
+    pub model: String,
+}
+
+impl Translator for Ollama {
+    fn name(&self) -> String {
+        format!("ollama {}", self.model)
+    }
+
+    fn translate(&self, instruction: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let prompt = format!(
+            "Translate the instruction into a single shell command for {} using sh syntax. \
+             Reply with the command only, no explanation and no code fences.\nInstruction: {}",
+            std::env::consts::OS,
+            instruction
+        );
+        let body = serde_json::json!({ "model": self.model, "prompt": prompt, "stream": false });
+        let client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(120)).build()?;
+        let response = client.post(format!("{}/api/generate", self.url.trim_end_matches('/'))).json(&body).send()?;
+        if !response.status().is_success() {
+            return Err(format!("model server returned {}", response.status()).into());
+        }
+        let json: serde_json::Value = response.json()?;
+        let text = json["response"].as_str().unwrap_or_default();
+        // Models like to wrap the answer in fences or prefix a prompt sign.
+        let command = text
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with("```"))
+            .map(|l| l.trim_start_matches("$ ").trim_matches('`').to_string());
+        Ok(command.filter(|c| !c.is_empty()))
+    }
+}
+
+pub fn model() -> Option<Ollama> {
+    let url = std::env::var("TERMINALAI_LLM_URL").ok().filter(|u| !u.is_empty())?;
+    let model = std::env::var("TERMINALAI_LLM_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string());
+    Some(Ollama { url, model })
+}
+
+fn cache_path() -> PathBuf {
+    crate::paths::data_dir().join("translations.json")
+}
+
+// Instructions differing only in case, spacing or final punctuation share a cache entry.
+fn cache_key(instruction: &str) -> String {
+    instruction.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase().trim_end_matches(['.', '!', '?']).to_string()
+}
+
+fn load_cache() -> BTreeMap<String, Translation> {
+    std::fs::read_to_string(cache_path()).ok().and_then(|c| serde_json::from_str(&c).ok()).unwrap_or_default()
+}
+
+fn save_cache(cache: &BTreeMap<String, Translation>) -> Result<(), Box<dyn Error>> {
+    let path = cache_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(cache)?)?;
+    Ok(())
+}
+
+// Stores the command to reuse for `instruction`, e.g. after the user edited it. Commands
+// that were typed verbatim are not worth keeping.
+pub fn remember(instruction: &str, translation: &Translation) -> Result<(), Box<dyn Error>> {
+    if translation.source == AS_TYPED {
+        return Ok(());
+    }
+    let mut cache = load_cache();
+    cache.insert(cache_key(instruction), translation.clone());
+    save_cache(&cache)
+}
+
+// Drops a rejected translation so the next attempt starts fresh.
+pub fn forget(instruction: &str) -> Result<(), Box<dyn Error>> {
+    let mut cache = load_cache();
+    if cache.remove(&cache_key(instruction)).is_some() {
+        save_cache(&cache)?;
+    }
+    Ok(())
+}
+
+// Input starting with a program on PATH, a shell builtin, an existing path or a variable
+// assignment is already a command: `echo hello`, `cargo build`, `./deploy.sh`, `FOO=1 make`.
+fn looks_like_command(instruction: &str) -> bool {
+    let Some(first) = instruction.split_whitespace().next() else {
+        return false;
+    };
+    let program = first.trim_end_matches([';', '&', '|']);
+    let assignment = Regex::new(r"^[A-Za-z_]\w*=").unwrap();
+    explain::on_path(program) || explain::is_builtin(program) || Path::new(program).exists() || assignment.is_match(program)
+}
+
+// Tries the cache, passes instructions that already are shell commands through as typed, then
+// tries the template library and finally asks the local model.
+pub fn translate(instruction: &str) -> Result<Option<Translation>, Box<dyn Error>> {
+    if let Some(cached) = load_cache().remove(&cache_key(instruction)) {
+        return Ok(Some(Translation { cached: true, ..cached }));
+    }
+    if looks_like_command(instruction) {
+        return Ok(Some(Translation { command: instruction.trim().to_string(), source: AS_TYPED.to_string(), cached: false }));
+    }
+    let generated = |translator: &dyn Translator| -> Result<Option<Translation>, Box<dyn Error>> {
+        Ok(translator.translate(instruction)?.map(|command| Translation { command, source: translator.name(), cached: false }))
+    };
+    if let Some(translation) = generated(&Templates)? {
+        return Ok(Some(translation));
+    }
+    if let Some(model) = model() {
+        if let Some(translation) = generated(&model)? {
+            remember(instruction, &translation)?;
+            return Ok(Some(translation));
+        }
+    }
+    Ok(None)
+}
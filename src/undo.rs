@@ -0,0 +1,182 @@
+// Journal of file changes so that `undo` can revert the most recent one
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "manifest.json";
+
+// What `undo` has to do to put one file back.
+#[derive(Serialize, Deserialize)]
+pub enum Action {
+    // Put `backup` back in place of whatever is at `path` now.
+    Restore { path: PathBuf, backup: PathBuf },
+    // Move the file at `to` back to `from`.
+    Move { from: PathBuf, to: PathBuf },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Transaction {
+    pub description: String,
+    actions: Vec<Action>,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+fn journal_dir() -> PathBuf {
+    crate::paths::data_dir().join("undo")
+}
+
+// The absolute path of the entry `path` names. Only the parent is resolved, so a symlink stays
+// the link rather than becoming its target.
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} does not name a file", path.display())))?;
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    Ok(fs::canonicalize(parent)?.join(name))
+}
+
+impl Transaction {
+    pub fn begin(description: &str) -> io::Result<Transaction> {
+        let id = chrono::Local::now().format("%Y%m%d-%H%M%S-%9f").to_string();
+        let dir = journal_dir().join(id);
+        fs::create_dir_all(&dir)?;
+        Ok(Transaction { description: description.to_string(), actions: Vec::new(), dir })
+    }
+
+    // Copies `path` into the journal before it gets modified. Writes go through a symlink, so it
+    // is the target that gets backed up and restored.
+    pub fn backup(&mut self, path: &Path) -> io::Result<()> {
+        let backup = self.dir.join(self.actions.len().to_string());
+        fs::copy(path, &backup)?;
+        self.actions.push(Action::Restore { path: fs::canonicalize(path)?, backup });
+        Ok(())
+    }
+
+    // Moves `path` into the journal, e.g. to delete it or to replace it with a hardlink.
+    pub fn remove(&mut self, path: &Path) -> io::Result<()> {
+        let backup = self.dir.join(self.actions.len().to_string());
+        let path = absolute(path)?;
+        fs::symlink_metadata(&path)?;
+        move_file(&path, &backup)?;
+        self.actions.push(Action::Restore { path, backup });
+        Ok(())
+    }
+
+    pub fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        let from = absolute(from)?;
+        fs::symlink_metadata(&from)?;
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(&from, to)?;
+        self.actions.push(Action::Move { from, to: absolute(to)? });
+        Ok(())
+    }
+
+    // Writes the manifest; only committed transactions can be undone.
+    pub fn commit(self) -> io::Result<()> {
+        if self.actions.is_empty() {
+            return fs::remove_dir_all(&self.dir);
+        }
+        let manifest = serde_json::to_string_pretty(&self)?;
+        fs::write(self.dir.join(MANIFEST), manifest)
+    }
+
+    // Reverts what this transaction has done so far, e.g. after a failure halfway through, and
+    // leaves the journal (and earlier transactions) untouched.
+    pub fn rollback(self) -> io::Result<()> {
+        revert(&self.actions)?;
+        fs::remove_dir_all(&self.dir)
+    }
+}
+
+fn revert(actions: &[Action]) -> io::Result<()> {
+    for action in actions.iter().rev() {
+        match action {
+            Action::Restore { path, backup } => {
+                match fs::remove_file(path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+                move_file(backup, path)?;
+            }
+            Action::Move { from, to } => move_file(to, from)?,
+        }
+    }
+    Ok(())
+}
+
+// Renames, falling back to copy and delete across filesystems. A symlink is recreated, not copied
+// from its target.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    if fs::symlink_metadata(from)?.file_type().is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)?;
+        return fs::remove_file(from);
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+// Reverts the most recently committed transaction and removes it from the journal.
+pub fn undo_last() -> Result<Option<String>, Box<dyn Error>> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(journal_dir()) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.join(MANIFEST).is_file()).collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    entries.sort();
+    let Some(dir) = entries.pop() else {
+        return Ok(Some("Nothing to undo.".to_string()));
+    };
+    let transaction: Transaction = serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST))?)?;
+    revert(&transaction.actions)?;
+    fs::remove_dir_all(&dir)?;
+    Ok(Some(format!("Reverted: {} ({} file(s) restored)", transaction.description, transaction.actions.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{DataDir, TempDir};
+
+    #[cfg(unix)]
+    #[test]
+    fn deletes_and_restores_a_symlink_itself() {
+        let _data = DataDir::fresh();
+        let dir = TempDir::new("undo-symlink");
+        let (target, link) = (dir.path().join("target.txt"), dir.path().join("link.txt"));
+        fs::write(&target, "keep me").unwrap();
+        std::os::unix::fs::symlink("target.txt", &link).unwrap();
+
+        let mut transaction = Transaction::begin("delete file link.txt").unwrap();
+        transaction.remove(&link).unwrap();
+        transaction.commit().unwrap();
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep me");
+
+        undo_last().unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("target.txt"));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep me");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn moves_a_symlink_and_back() {
+        let _data = DataDir::fresh();
+        let dir = TempDir::new("undo-move-symlink");
+        let (target, link, moved) = (dir.path().join("target.txt"), dir.path().join("link.txt"), dir.path().join("sub").join("link.txt"));
+        fs::write(&target, "keep me").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let mut transaction = Transaction::begin("move").unwrap();
+        transaction.rename(&link, &moved).unwrap();
+        transaction.rollback().unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), target);
+        assert!(target.is_file() && fs::symlink_metadata(&moved).is_err());
+    }
+}
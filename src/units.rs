@@ -0,0 +1,386 @@
+// Dimensional analysis and unit conversion for `calculate`
+use crate::expr::{self, Expr, Op};
+use crate::numeric::format_number;
+
+// Exponents of length, mass, time, temperature and data.
+type Dims = [i32; 5];
+
+const DIMENSIONLESS: Dims = [0; 5];
+const LENGTH: Dims = [1, 0, 0, 0, 0];
+const MASS: Dims = [0, 1, 0, 0, 0];
+const TIME: Dims = [0, 0, 1, 0, 0];
+const TEMPERATURE: Dims = [0, 0, 0, 1, 0];
+const DATA: Dims = [0, 0, 0, 0, 1];
+const SPEED: Dims = [1, 0, -1, 0, 0];
+
+const EXPONENT_TOO_LARGE: &str = "unit exponent too large";
+
+const BASE_SYMBOLS: [&str; 5] = ["m", "kg", "s", "K", "B"];
+
+struct UnitDef {
+    names: &'static [&'static str],
+    // Size of one unit in base units (m, kg, s, K, B).
+    factor: f64,
+    dims: Dims,
+    // Kelvin offset for absolute temperature scales (°C, °F).
+    offset: f64,
+    prefixes: Prefixes,
+}
+
+#[derive(PartialEq)]
+enum Prefixes {
+    None,
+    Si,
+    SiAndIec,
+}
+
+const fn unit(names: &'static [&'static str], factor: f64, dims: Dims, prefixes: Prefixes) -> UnitDef {
+    UnitDef { names, factor, dims, offset: 0.0, prefixes }
+}
+
+const UNITS: &[UnitDef] = &[
+    // Length
+    unit(&["m", "meter", "meters", "metre", "metres"], 1.0, LENGTH, Prefixes::Si),
+    unit(&["in", "inch", "inches"], 0.0254, LENGTH, Prefixes::None),
+    unit(&["ft", "foot", "feet"], 0.3048, LENGTH, Prefixes::None),
+    unit(&["yd", "yard", "yards"], 0.9144, LENGTH, Prefixes::None),
+    unit(&["mi", "mile", "miles"], 1609.344, LENGTH, Prefixes::None),
+    unit(&["nmi"], 1852.0, LENGTH, Prefixes::None),
+    // Mass
+    unit(&["g", "gram", "grams"], 1e-3, MASS, Prefixes::Si),
+    unit(&["t", "tonne", "tonnes"], 1000.0, MASS, Prefixes::None),
+    unit(&["lb", "lbs", "pound", "pounds"], 0.45359237, MASS, Prefixes::None),
+    unit(&["oz", "ounce", "ounces"], 0.028349523125, MASS, Prefixes::None),
+    unit(&["st", "stone"], 6.35029318, MASS, Prefixes::None),
+    // Time
+    unit(&["s", "sec", "secs", "second", "seconds"], 1.0, TIME, Prefixes::Si),
+    unit(&["min", "mins", "minute", "minutes"], 60.0, TIME, Prefixes::None),
+    unit(&["h", "hr", "hrs", "hour", "hours"], 3600.0, TIME, Prefixes::None),
+    unit(&["d", "day", "days"], 86400.0, TIME, Prefixes::None),
+    unit(&["wk", "week", "weeks"], 604800.0, TIME, Prefixes::None),
+    unit(&["yr", "year", "years"], 31557600.0, TIME, Prefixes::None),
+    // Temperature
+    unit(&["K", "kelvin"], 1.0, TEMPERATURE, Prefixes::Si),
+    unit(&["R", "degR", "rankine"], 5.0 / 9.0, TEMPERATURE, Prefixes::None),
+    UnitDef {
+        names: &["degC", "°C", "celsius"],
+        factor: 1.0,
+        dims: TEMPERATURE,
+        offset: 273.15,
+        prefixes: Prefixes::None,
+    },
+    UnitDef {
+        names: &["degF", "°F", "fahrenheit"],
+        factor: 5.0 / 9.0,
+        dims: TEMPERATURE,
+        offset: 459.67 * 5.0 / 9.0,
+        prefixes: Prefixes::None,
+    },
+    // Data
+    unit(&["B", "byte", "bytes"], 1.0, DATA, Prefixes::SiAndIec),
+    unit(&["b", "bit", "bits"], 0.125, DATA, Prefixes::SiAndIec),
+    // Speed
+    unit(&["mph"], 0.44704, SPEED, Prefixes::None),
+    unit(&["kph", "kmh"], 1000.0 / 3600.0, SPEED, Prefixes::None),
+    unit(&["kn", "knot", "knots"], 1852.0 / 3600.0, SPEED, Prefixes::None),
+    unit(&["c"], 299_792_458.0, SPEED, Prefixes::None),
+];
+
+const SI_PREFIXES: &[(&str, &str, f64)] = &[
+    ("Y", "yotta", 1e24),
+    ("Z", "zetta", 1e21),
+    ("E", "exa", 1e18),
+    ("P", "peta", 1e15),
+    ("T", "tera", 1e12),
+    ("G", "giga", 1e9),
+    ("M", "mega", 1e6),
+    ("k", "kilo", 1e3),
+    ("h", "hecto", 1e2),
+    ("da", "deca", 1e1),
+    ("d", "deci", 1e-1),
+    ("c", "centi", 1e-2),
+    ("m", "milli", 1e-3),
+    ("u", "micro", 1e-6),
+    ("µ", "micro", 1e-6),
+    ("n", "nano", 1e-9),
+    ("p", "pico", 1e-12),
+    ("f", "femto", 1e-15),
+    ("a", "atto", 1e-18),
+];
+
+const IEC_PREFIXES: &[(&str, &str, f64)] = &[
+    ("Ki", "kibi", 1024.0),
+    ("Mi", "mebi", 1048576.0),
+    ("Gi", "gibi", 1073741824.0),
+    ("Ti", "tebi", 1099511627776.0),
+    ("Pi", "pebi", 1125899906842624.0),
+    ("Ei", "exbi", 1152921504606846976.0),
+];
+
+struct Resolved {
+    factor: f64,
+    dims: Dims,
+    offset: f64,
+}
+
+fn lookup(name: &str) -> Option<Resolved> {
+    if let Some(def) = UNITS.iter().find(|u| u.names.contains(&name)) {
+        return Some(Resolved { factor: def.factor, dims: def.dims, offset: def.offset });
+    }
+    for def in UNITS.iter().filter(|u| u.prefixes != Prefixes::None) {
+        let iec: &[(&str, &str, f64)] = if def.prefixes == Prefixes::SiAndIec { IEC_PREFIXES } else { &[] };
+        for &(short, long, scale) in iec.iter().chain(SI_PREFIXES) {
+            let stripped = [name.strip_prefix(short), name.strip_prefix(long)];
+            if stripped.iter().flatten().any(|base| def.names.contains(base)) {
+                return Some(Resolved { factor: def.factor * scale, dims: def.dims, offset: 0.0 });
+            }
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quantity {
+    value: f64,
+    dims: Dims,
+}
+
+fn eval(expr: &Expr) -> Result<Quantity, String> {
+    match expr {
+        Expr::Num(n) => {
+            let value = n.parse().map_err(|_| format!("invalid number '{}'", n))?;
+            Ok(Quantity { value, dims: DIMENSIONLESS })
+        }
+        Expr::Ident(name) => match name.as_str() {
+            "pi" => Ok(Quantity { value: std::f64::consts::PI, dims: DIMENSIONLESS }),
+            "e" => Ok(Quantity { value: std::f64::consts::E, dims: DIMENSIONLESS }),
+            _ => match lookup(name) {
+                Some(u) if u.offset != 0.0 => Err(format!(
+                    "'{}' is an absolute temperature scale and can only be converted directly, e.g. `100 degC to degF`",
+                    name
+                )),
+                Some(u) => Ok(Quantity { value: u.factor, dims: u.dims }),
+                None => Err(format!("unknown unit '{}'", name)),
+            },
+        },
+        Expr::Neg(inner) => {
+            let q = eval(inner)?;
+            Ok(Quantity { value: -q.value, dims: q.dims })
+        }
+        Expr::Factorial(_) | Expr::Call(..) | Expr::List(_) => {
+            Err("factorials, functions and lists cannot be combined with units".to_string())
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let a = eval(lhs)?;
+            let b = eval(rhs)?;
+            match op {
+                Op::Add | Op::Sub => {
+                    if a.dims != b.dims {
+                        return Err(format!(
+                            "incompatible dimensions: cannot {} {} and {}",
+                            if *op == Op::Add { "add" } else { "subtract" },
+                            describe(a.dims),
+                            describe(b.dims)
+                        ));
+                    }
+                    let value = if *op == Op::Add { a.value + b.value } else { a.value - b.value };
+                    Ok(Quantity { value, dims: a.dims })
+                }
+                Op::Mul => Ok(Quantity { value: a.value * b.value, dims: combine(a.dims, b.dims, 1)? }),
+                Op::Div => Ok(Quantity { value: a.value / b.value, dims: combine(a.dims, b.dims, -1)? }),
+                Op::Pow => {
+                    if b.dims != DIMENSIONLESS {
+                        return Err("exponents must be dimensionless".to_string());
+                    }
+                    if a.dims == DIMENSIONLESS {
+                        return Ok(Quantity { value: a.value.powf(b.value), dims: DIMENSIONLESS });
+                    }
+                    if b.value.fract() != 0.0 {
+                        return Err(format!("cannot raise {} to a fractional power", describe(a.dims)));
+                    }
+                    if b.value.abs() > i32::MAX as f64 {
+                        return Err(EXPONENT_TOO_LARGE.to_string());
+                    }
+                    let mut dims = a.dims;
+                    for d in dims.iter_mut() {
+                        *d = d.checked_mul(b.value as i32).ok_or(EXPONENT_TOO_LARGE)?;
+                    }
+                    Ok(Quantity { value: a.value.powf(b.value), dims })
+                }
+            }
+        }
+    }
+}
+
+fn combine(a: Dims, b: Dims, sign: i32) -> Result<Dims, String> {
+    let mut out = a;
+    for (o, d) in out.iter_mut().zip(b) {
+        *o = sign.checked_mul(d).and_then(|d| o.checked_add(d)).ok_or(EXPONENT_TOO_LARGE)?;
+    }
+    Ok(out)
+}
+
+fn base_units(dims: Dims) -> String {
+    let fmt_part = |i: usize, exp: u32| {
+        if exp == 1 {
+            BASE_SYMBOLS[i].to_string()
+        } else {
+            format!("{}^{}", BASE_SYMBOLS[i], exp)
+        }
+    };
+    let num: Vec<String> = (0..5).filter(|&i| dims[i] > 0).map(|i| fmt_part(i, dims[i].unsigned_abs())).collect();
+    let den: Vec<String> = (0..5).filter(|&i| dims[i] < 0).map(|i| fmt_part(i, dims[i].unsigned_abs())).collect();
+    match (num.is_empty(), den.is_empty()) {
+        (_, true) => num.join("*"),
+        (true, false) => format!("1/{}", den.join("*")),
+        (false, false) => format!("{}/{}", num.join("*"), den.join("*")),
+    }
+}
+
+fn describe(dims: Dims) -> String {
+    let name = match dims {
+        DIMENSIONLESS => return "a plain number".to_string(),
+        LENGTH => "length",
+        MASS => "mass",
+        TIME => "time",
+        TEMPERATURE => "temperature",
+        DATA => "data size",
+        SPEED => "speed",
+        [0, 0, -1, 0, 1] => "data rate",
+        [2, 0, 0, 0, 0] => "area",
+        [3, 0, 0, 0, 0] => "volume",
+        [1, 0, -2, 0, 0] => "acceleration",
+        [0, 0, -1, 0, 0] => "frequency",
+        _ => return base_units(dims),
+    };
+    format!("{} ({})", name, base_units(dims))
+}
+
+// Splits off a trailing `to <unit>` / `in <unit>` conversion target.
+fn split_target(input: &str) -> (&str, Option<&str>) {
+    for keyword in [" to ", " in "] {
+        if let Some(idx) = input.rfind(keyword) {
+            let (source, target) = (input[..idx].trim(), input[idx + keyword.len()..].trim());
+            if !source.is_empty() && !target.is_empty() && expr::parse(target).is_ok() {
+                return (source, Some(target));
+            }
+        }
+    }
+    (input, None)
+}
+
+// Recognises `<number> <temperature unit>`, which needs an affine rather than a linear conversion.
+fn absolute_temperature(expr: &Expr) -> Option<(f64, &str, Resolved)> {
+    let (value, name) = match expr {
+        Expr::Binary(Op::Mul, lhs, rhs) => match (lhs.as_ref(), rhs.as_ref()) {
+            (Expr::Num(n), Expr::Ident(name)) => (n.parse().ok()?, name),
+            (Expr::Neg(inner), Expr::Ident(name)) => match inner.as_ref() {
+                Expr::Num(n) => (-n.parse::<f64>().ok()?, name),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        Expr::Neg(inner) => {
+            let (value, name, unit) = absolute_temperature(inner)?;
+            return Some((-value, name, unit));
+        }
+        _ => return None,
+    };
+    let unit = lookup(name)?;
+    (unit.dims == TEMPERATURE).then_some((value, name.as_str(), unit))
+}
+
+/// Evaluates `expr` with unit support. Returns `Ok(None)` when the expression mentions no units,
+/// so callers can fall back to the plain scalar evaluator.
+pub fn evaluate(input: &str) -> Result<Option<String>, String> {
+    let (source, target) = split_target(input.trim());
+    let Ok(source_expr) = expr::parse(source) else {
+        return Ok(None);
+    };
+    let target_expr = target.and_then(|t| expr::parse(t).ok());
+    let source_units: Vec<&str> = source_expr.idents().into_iter().filter(|name| lookup(name).is_some()).collect();
+    let target_has_units = target_expr.as_ref().is_some_and(|t| t.idents().iter().any(|name| lookup(name).is_some()));
+    if source_units.is_empty() && !target_has_units {
+        return Ok(None);
+    }
+
+    if let Some((value, from_name, from)) = absolute_temperature(&source_expr) {
+        let Some(Expr::Ident(to_name)) = &target_expr else {
+            return Ok(Some(format!("{} {}", format_number(value), from_name)));
+        };
+        let to = lookup(to_name).ok_or_else(|| format!("unknown unit '{}'", to_name))?;
+        if to.dims != TEMPERATURE {
+            return Err(format!("cannot convert temperature to {}", describe(to.dims)));
+        }
+        let kelvin = value * from.factor + from.offset;
+        return Ok(Some(format!("{} {}", format_number((kelvin - to.offset) / to.factor), to_name)));
+    }
+
+    let result = eval(&source_expr)?;
+    if let (Some(t), Some(target_expr)) = (target, &target_expr) {
+        let unit = eval(target_expr)?;
+        if unit.dims != result.dims {
+            return Err(format!("cannot convert {} to {}", describe(result.dims), describe(unit.dims)));
+        }
+        return Ok(Some(format!("{} {}", format_number(result.value / unit.value), t)));
+    }
+
+    // Without an explicit target, show the result in the first unit of the input that fits.
+    for name in source_units {
+        if let Some(u) = lookup(name) {
+            if u.dims == result.dims {
+                return Ok(Some(format!("{} {}", format_number(result.value / u.factor), name)));
+            }
+        }
+    }
+    let units = base_units(result.dims);
+    if units.is_empty() {
+        Ok(Some(format_number(result.value)))
+    } else {
+        Ok(Some(format!("{} {}", format_number(result.value), units)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(input: &str) -> String {
+        evaluate(input).unwrap().unwrap()
+    }
+
+    #[test]
+    fn converts_temperatures_with_offsets() {
+        assert_eq!(calc("100 degC to degF"), "212 degF");
+        assert_eq!(calc("-40 °F in °C"), "-40 °C");
+        assert_eq!(calc("0 celsius to K"), "273.15 K");
+        // A difference of temperatures is linear: no offset.
+        assert_eq!(calc("5 K to R"), "9 R");
+    }
+
+    #[test]
+    fn rejects_mismatched_dimensions() {
+        assert_eq!(evaluate("3 m to s").unwrap_err(), "cannot convert length (m) to time (s)");
+        assert_eq!(evaluate("1 m + 1 s").unwrap_err(), "incompatible dimensions: cannot add length (m) and time (s)");
+        assert_eq!(evaluate("20 degC to kg").unwrap_err(), "cannot convert temperature to mass (kg)");
+    }
+
+    #[test]
+    fn splits_off_the_last_to_or_in() {
+        assert_eq!(calc("5 GiB / 30 s in MB/s"), "178.9569707 MB/s");
+        // `in` is also the inch.
+        assert_eq!(calc("12 in in cm"), "30.48 cm");
+        assert_eq!(calc("1 in to mm"), "25.4 mm");
+        assert_eq!(split_target("2 to"), ("2 to", None));
+    }
+
+    #[test]
+    fn leaves_plain_numbers_to_the_scalar_evaluator() {
+        assert_eq!(evaluate("2 + 3").unwrap(), None);
+    }
+
+    #[test]
+    fn reports_exponent_overflow() {
+        assert_eq!(evaluate("m^2147483647 * m").unwrap_err(), EXPONENT_TOO_LARGE);
+    }
+}
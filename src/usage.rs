@@ -0,0 +1,167 @@
+// Resource usage of finished commands (printed with `--profile`) and the JSON record of the last run
+use crate::humanize;
+use serde::Serialize;
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static PROFILE: AtomicBool = AtomicBool::new(false);
+static LAST_RESULT: Mutex<Option<String>> = Mutex::new(None);
+
+// Fields are None where the platform cannot measure them, and peak memory in a shell session.
+#[derive(Serialize, Clone, Default)]
+pub struct Usage {
+    pub wall_seconds: f64,
+    pub user_seconds: Option<f64>,
+    pub system_seconds: Option<f64>,
+    pub max_rss_bytes: Option<u64>,
+    // Everything the process read and wrote through system calls, pipes and terminals included.
+    pub read_bytes: Option<u64>,
+    pub written_bytes: Option<u64>,
+}
+
+impl Usage {
+    pub fn report(&self) -> String {
+        let mut parts = vec![format!("wall {:.2}s", self.wall_seconds)];
+        parts.extend(self.user_seconds.map(|s| format!("user {:.2}s", s)));
+        parts.extend(self.system_seconds.map(|s| format!("sys {:.2}s", s)));
+        parts.extend(self.max_rss_bytes.map(|b| format!("peak RSS {}", humanize::size(b))));
+        parts.extend(self.read_bytes.map(|b| format!("read {}", humanize::size(b))));
+        parts.extend(self.written_bytes.map(|b| format!("written {}", humanize::size(b))));
+        format!("[usage: {}]", parts.join(", "))
+    }
+
+    // What was used between two readings of `totals` for the same process.
+    pub fn since(&self, earlier: &Usage) -> Usage {
+        let seconds = |now: Option<f64>, then: Option<f64>| Some((now? - then?).max(0.0));
+        let bytes = |now: Option<u64>, then: Option<u64>| Some(now?.saturating_sub(then?));
+        Usage {
+            wall_seconds: self.wall_seconds - earlier.wall_seconds,
+            user_seconds: seconds(self.user_seconds, earlier.user_seconds),
+            system_seconds: seconds(self.system_seconds, earlier.system_seconds),
+            max_rss_bytes: None,
+            read_bytes: bytes(self.read_bytes, earlier.read_bytes),
+            written_bytes: bytes(self.written_bytes, earlier.written_bytes),
+        }
+    }
+}
+
+pub fn enable_profiling() {
+    PROFILE.store(true, Ordering::Relaxed);
+}
+
+pub fn profiling() -> bool {
+    PROFILE.load(Ordering::Relaxed)
+}
+
+// Keeps `result` for `show last result`.
+pub fn record<T: Serialize>(result: &T) {
+    if let (Ok(json), Ok(mut last)) = (serde_json::to_string_pretty(result), LAST_RESULT.lock()) {
+        *last = Some(json);
+    }
+}
+
+pub fn last_result() -> Option<String> {
+    LAST_RESULT.lock().ok()?.clone()
+}
+
+// Whether the child has exited, without reaping it, so its /proc entry is still readable.
+#[cfg(target_os = "linux")]
+fn exited(pid: libc::pid_t, block: bool) -> io::Result<bool> {
+    let flags = libc::WEXITED | libc::WNOWAIT | if block { 0 } else { libc::WNOHANG };
+    loop {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        if unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, flags) } == 0 {
+            return Ok(unsafe { info.si_pid() } != 0);
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+// Bytes read and written by a process and the children it has waited for.
+#[cfg(target_os = "linux")]
+fn io_counters(pid: libc::pid_t) -> (Option<u64>, Option<u64>) {
+    let Ok(io) = std::fs::read_to_string(format!("/proc/{}/io", pid)) else {
+        return (None, None);
+    };
+    let field = |name: &str| io.lines().find_map(|l| l.strip_prefix(name)).and_then(|v| v.trim().parse().ok());
+    (field("rchar:"), field("wchar:"))
+}
+
+// CPU time and I/O so far of a process that is still running (the shell session) and of the
+// children it has waited for. Peak memory cannot be split between commands, so it is left out.
+#[cfg(target_os = "linux")]
+pub fn totals(pid: u32) -> Usage {
+    let pid = pid as libc::pid_t;
+    let (read_bytes, written_bytes) = io_counters(pid);
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+    // Fields after the command name, which may contain spaces: state is the first, utime the 12th.
+    let fields: Vec<&str> = stat.rsplit_once(')').map_or(Vec::new(), |(_, rest)| rest.split_whitespace().collect());
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as f64;
+    // Its own time plus that of reaped children (cutime and cstime, two fields on).
+    let time = |at: usize| -> Option<f64> {
+        let own: f64 = fields.get(at)?.parse().ok()?;
+        let children: f64 = fields.get(at + 2)?.parse().ok()?;
+        Some((own + children) / ticks)
+    };
+    Usage { wall_seconds: 0.0, user_seconds: time(11), system_seconds: time(12), max_rss_bytes: None, read_bytes, written_bytes }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn totals(_pid: u32) -> Usage {
+    Usage::default()
+}
+
+// Like `Child::try_wait` (or `Child::wait` when `block` is set), also returning the resource
+// usage of the child and its descendants. `wall_seconds` is left for the caller.
+#[cfg(unix)]
+pub fn wait(child: &mut Child, block: bool) -> io::Result<Option<(ExitStatus, Usage)>> {
+    use std::os::unix::process::ExitStatusExt;
+    let pid = child.id() as libc::pid_t;
+    #[cfg(target_os = "linux")]
+    let (read_bytes, written_bytes) = {
+        if !exited(pid, block)? {
+            return Ok(None);
+        }
+        io_counters(pid)
+    };
+    #[cfg(not(target_os = "linux"))]
+    let (read_bytes, written_bytes) = (None, None);
+    let mut status = 0;
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    let flags = if block { 0 } else { libc::WNOHANG };
+    let reaped = loop {
+        let reaped = unsafe { libc::wait4(pid, &mut status, flags, &mut rusage) };
+        if reaped >= 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            break reaped;
+        }
+    };
+    match reaped {
+        0 => return Ok(None),
+        r if r < 0 => return Err(io::Error::last_os_error()),
+        _ => {}
+    }
+    let seconds = |t: libc::timeval| t.tv_sec as f64 + t.tv_usec as f64 / 1e6;
+    // Linux reports kilobytes, macOS bytes.
+    let rss = rusage.ru_maxrss.max(0) as u64;
+    let max_rss_bytes = if cfg!(target_os = "macos") { rss } else { rss * 1024 };
+    let usage = Usage {
+        wall_seconds: 0.0,
+        user_seconds: Some(seconds(rusage.ru_utime)),
+        system_seconds: Some(seconds(rusage.ru_stime)),
+        max_rss_bytes: Some(max_rss_bytes),
+        read_bytes,
+        written_bytes,
+    };
+    Ok(Some((ExitStatus::from_raw(status), usage)))
+}
+
+#[cfg(not(unix))]
+pub fn wait(child: &mut Child, block: bool) -> io::Result<Option<(ExitStatus, Usage)>> {
+    let status = if block { Some(child.wait()?) } else { child.try_wait()? };
+    Ok(status.map(|s| (s, Usage::default())))
+}
@@ -0,0 +1,365 @@
+// Weather lookups for `show weather`: providers, parsed forecasts and a TTL cache
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_BASE_URL: &str = "https://wttr.in";
+const DEFAULT_TTL_SECS: u64 = 600;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Units {
+    Metric,
+    Imperial,
+}
+
+// All values are stored in metric units and converted for display.
+#[derive(Serialize, Deserialize)]
+pub struct Current {
+    pub description: String,
+    pub temp_c: f64,
+    pub feels_like_c: f64,
+    pub humidity: f64,
+    pub wind_kmph: f64,
+    pub wind_dir: String,
+    pub pressure_mb: f64,
+    pub precip_mm: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DayForecast {
+    pub date: String,
+    pub description: String,
+    pub min_c: f64,
+    pub max_c: f64,
+    pub chance_of_rain: f64,
+    pub precip_mm: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Report {
+    pub location: String,
+    pub current: Current,
+    pub forecast: Vec<DayForecast>,
+}
+
+pub trait WeatherProvider {
+    fn name(&self) -> &str;
+    fn fetch(&self, location: &str) -> Result<Report, Box<dyn Error>>;
+
+    // Identifies the provider and where its data comes from in cache keys.
+    fn source(&self) -> String {
+        self.name().to_string()
+    }
+}
+
+// wttr.in's `?format=j1` JSON. A `file://` base URL reads `<dir>/<location>.json` instead,
+// so tests and air-gapped machines can use canned responses.
+pub struct Wttr {
+    pub base_url: String,
+}
+
+impl WeatherProvider for Wttr {
+    fn name(&self) -> &str {
+        "wttr"
+    }
+
+    fn source(&self) -> String {
+        format!("wttr {}", self.base_url)
+    }
+
+    fn fetch(&self, location: &str) -> Result<Report, Box<dyn Error>> {
+        let body = match self.base_url.strip_prefix("file://") {
+            Some(dir) => {
+                let file = PathBuf::from(dir).join(format!("{}.json", location.to_lowercase().replace(' ', "_")));
+                std::fs::read_to_string(&file).map_err(|e| format!("{}: {}", file.display(), e))?
+            }
+            None => {
+                let url = format!("{}/{}?format=j1", self.base_url.trim_end_matches('/'), location.replace(' ', "+"));
+                let response = reqwest::blocking::Client::builder().timeout(Duration::from_secs(15)).build()?.get(&url).send()?;
+                if !response.status().is_success() {
+                    return Err(format!("weather service returned {}", response.status()).into());
+                }
+                response.text()?
+            }
+        };
+        parse_wttr(location, &serde_json::from_str(&body)?)
+    }
+}
+
+// wttr.in sends numbers as strings.
+fn num(value: &Value) -> f64 {
+    match value {
+        Value::Number(n) => n.as_f64().unwrap_or(0.0),
+        Value::String(s) => s.trim().parse().unwrap_or(0.0),
+        _ => 0.0,
+    }
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().map(str::to_string).unwrap_or_default()
+}
+
+// `[{"value": "..."}]` lists used for descriptions and names.
+fn first_value(value: &Value) -> String {
+    text(&value[0]["value"])
+}
+
+fn parse_wttr(query: &str, json: &Value) -> Result<Report, Box<dyn Error>> {
+    let current = json["current_condition"].get(0).ok_or("unexpected weather response: no current conditions")?;
+    let area = &json["nearest_area"][0];
+    let location = match (first_value(&area["areaName"]), first_value(&area["country"])) {
+        (name, country) if !name.is_empty() && !country.is_empty() => format!("{}, {}", name, country),
+        (name, _) if !name.is_empty() => name,
+        _ => query.to_string(),
+    };
+    let forecast = json["weather"]
+        .as_array()
+        .map(|days| {
+            days.iter()
+                .map(|day| {
+                    let hourly = day["hourly"].as_array().cloned().unwrap_or_default();
+                    // Midday conditions describe the day best.
+                    let midday = hourly.get(hourly.len() / 2).map(|h| first_value(&h["weatherDesc"])).unwrap_or_default();
+                    DayForecast {
+                        date: text(&day["date"]),
+                        description: midday,
+                        min_c: num(&day["mintempC"]),
+                        max_c: num(&day["maxtempC"]),
+                        chance_of_rain: hourly.iter().map(|h| num(&h["chanceofrain"])).fold(0.0, f64::max),
+                        precip_mm: hourly.iter().map(|h| num(&h["precipMM"])).sum(),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Report {
+        location,
+        current: Current {
+            description: first_value(&current["weatherDesc"]),
+            temp_c: num(&current["temp_C"]),
+            feels_like_c: num(&current["FeelsLikeC"]),
+            humidity: num(&current["humidity"]),
+            wind_kmph: num(&current["windspeedKmph"]),
+            wind_dir: text(&current["winddir16Point"]),
+            pressure_mb: num(&current["pressure"]),
+            precip_mm: num(&current["precipMM"]),
+        },
+        forecast,
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fetched_at: u64,
+    report: Report,
+}
+
+// Serves repeated lookups from `dir` until the entry is older than `ttl`.
+pub struct Cached<P: WeatherProvider> {
+    pub inner: P,
+    pub ttl: Duration,
+    pub dir: PathBuf,
+}
+
+impl<P: WeatherProvider> Cached<P> {
+    // Keyed by the provider's source too, so pointing it at another server does not serve
+    // what the previous one returned.
+    fn path(&self, location: &str) -> PathBuf {
+        let key: String = location.to_lowercase().chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+        let source = blake3::hash(self.inner.source().as_bytes()).to_hex();
+        self.dir.join(format!("{}-{}-{}.json", self.inner.name(), &source[..12], key))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl<P: WeatherProvider> WeatherProvider for Cached<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn source(&self) -> String {
+        self.inner.source()
+    }
+
+    fn fetch(&self, location: &str) -> Result<Report, Box<dyn Error>> {
+        let path = self.path(location);
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            if let Ok(entry) = serde_json::from_str::<CacheEntry>(&content) {
+                if now().saturating_sub(entry.fetched_at) < self.ttl.as_secs() {
+                    return Ok(entry.report);
+                }
+            }
+        }
+        let report = self.inner.fetch(location)?;
+        let entry = CacheEntry { fetched_at: now(), report };
+        // A cache that cannot be written is not worth failing the lookup over.
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(&path, serde_json::to_string(&entry)?);
+        Ok(entry.report)
+    }
+}
+
+// Configured from `TERMINALAI_WEATHER_URL` (default wttr.in) and `TERMINALAI_WEATHER_TTL`
+// in seconds (default 600; 0 disables the cache).
+pub fn provider() -> Box<dyn WeatherProvider> {
+    let base_url = std::env::var("TERMINALAI_WEATHER_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+    let ttl = std::env::var("TERMINALAI_WEATHER_TTL").ok().and_then(|t| t.parse().ok()).unwrap_or(DEFAULT_TTL_SECS);
+    let wttr = Wttr { base_url };
+    if ttl == 0 {
+        Box::new(wttr)
+    } else {
+        Box::new(Cached { inner: wttr, ttl: Duration::from_secs(ttl), dir: crate::paths::data_dir().join("weather_cache") })
+    }
+}
+
+fn temp(celsius: f64, units: Units) -> String {
+    match units {
+        Units::Metric => format!("{:.0}°C", celsius),
+        Units::Imperial => format!("{:.0}°F", celsius * 9.0 / 5.0 + 32.0),
+    }
+}
+
+fn speed(kmph: f64, units: Units) -> String {
+    match units {
+        Units::Metric => format!("{:.0} km/h", kmph),
+        Units::Imperial => format!("{:.0} mph", kmph / 1.609_344),
+    }
+}
+
+fn precipitation(mm: f64, units: Units) -> String {
+    match units {
+        Units::Metric => format!("{:.1} mm", mm),
+        Units::Imperial => format!("{:.2} in", mm / 25.4),
+    }
+}
+
+pub fn format(report: &Report, units: Units) -> String {
+    let c = &report.current;
+    let mut lines = vec![
+        format!("Weather for {}", report.location),
+        format!("  Now: {}, {} (feels like {})", c.description, temp(c.temp_c, units), temp(c.feels_like_c, units)),
+        format!("  Humidity {:.0}%, wind {} {}, pressure {:.0} hPa, precipitation {}", c.humidity, speed(c.wind_kmph, units), c.wind_dir, c.pressure_mb, precipitation(c.precip_mm, units)),
+    ];
+    if !report.forecast.is_empty() {
+        lines.push("  Forecast:".to_string());
+        for day in &report.forecast {
+            lines.push(format!(
+                "    {}  {:>5} / {:<5}  {:>3.0}% rain, {}  {}",
+                day.date,
+                temp(day.min_c, units),
+                temp(day.max_c, units),
+                day.chance_of_rain,
+                precipitation(day.precip_mm, units),
+                day.description
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::cell::Cell;
+
+    // Trimmed from a real `wttr.in/Oslo?format=j1` response.
+    const J1: &str = r#"{
+        "current_condition": [{
+            "FeelsLikeC": "-3", "humidity": "80", "precipMM": "25.4", "pressure": "1012", "temp_C": "20",
+            "weatherDesc": [{"value": "Light snow"}], "winddir16Point": "NNE", "windspeedKmph": "16.09344"
+        }],
+        "nearest_area": [{"areaName": [{"value": "Oslo"}], "country": [{"value": "Norway"}]}],
+        "weather": [{
+            "date": "2024-01-15", "maxtempC": "1", "mintempC": "-6",
+            "hourly": [
+                {"chanceofrain": "10", "precipMM": "0.5", "weatherDesc": [{"value": "Cloudy"}]},
+                {"chanceofrain": "70", "precipMM": "1.5", "weatherDesc": [{"value": "Snow"}]},
+                {"chanceofrain": "40", "precipMM": "0.0", "weatherDesc": [{"value": "Overcast"}]}
+            ]
+        }]
+    }"#;
+
+    // Returns the canned report and counts how often it was asked.
+    struct Counting {
+        calls: Cell<usize>,
+    }
+
+    impl WeatherProvider for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn fetch(&self, location: &str) -> Result<Report, Box<dyn Error>> {
+            self.calls.set(self.calls.get() + 1);
+            parse_wttr(location, &serde_json::from_str(J1)?)
+        }
+    }
+
+    #[test]
+    fn parses_j1() {
+        let dir = TempDir::new("weather-j1");
+        std::fs::write(dir.path().join("oslo.json"), J1).unwrap();
+        let report = Wttr { base_url: format!("file://{}", dir.path().display()) }.fetch("Oslo").unwrap();
+        assert_eq!(report.location, "Oslo, Norway");
+        assert_eq!(report.current.description, "Light snow");
+        assert_eq!(report.current.temp_c, 20.0);
+        assert_eq!(report.current.wind_dir, "NNE");
+        let day = &report.forecast[0];
+        assert_eq!((day.date.as_str(), day.min_c, day.max_c), ("2024-01-15", -6.0, 1.0));
+        assert_eq!(day.description, "Snow");
+        assert_eq!(day.chance_of_rain, 70.0);
+        assert_eq!(day.precip_mm, 2.0);
+    }
+
+    #[test]
+    fn rejects_a_response_without_current_conditions() {
+        assert!(parse_wttr("Oslo", &serde_json::json!({"weather": []})).is_err());
+    }
+
+    #[test]
+    fn serves_from_cache_until_the_ttl_expires() {
+        let dir = TempDir::new("weather-ttl");
+        let cached = Cached { inner: Counting { calls: Cell::new(0) }, ttl: Duration::from_secs(600), dir: dir.path().to_path_buf() };
+        cached.fetch("Oslo").unwrap();
+        cached.fetch("oslo").unwrap();
+        assert_eq!(cached.inner.calls.get(), 1);
+
+        // Age the entry past the TTL.
+        let path = cached.path("Oslo");
+        let mut entry: CacheEntry = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        entry.fetched_at -= 601;
+        std::fs::write(&path, serde_json::to_string(&entry).unwrap()).unwrap();
+        cached.fetch("Oslo").unwrap();
+        assert_eq!(cached.inner.calls.get(), 2);
+    }
+
+    #[test]
+    fn cache_is_keyed_by_base_url() {
+        let dir = TempDir::new("weather-key");
+        let real = Cached { inner: Wttr { base_url: DEFAULT_BASE_URL.to_string() }, ttl: Duration::from_secs(600), dir: dir.path().to_path_buf() };
+        let stub = Cached { inner: Wttr { base_url: "http://127.0.0.1:8080".to_string() }, ttl: Duration::from_secs(600), dir: dir.path().to_path_buf() };
+        assert_ne!(real.path("Oslo"), stub.path("Oslo"));
+    }
+
+    #[test]
+    fn formats_metric_and_imperial() {
+        let report = parse_wttr("Oslo", &serde_json::from_str(J1).unwrap()).unwrap();
+        let metric = format(&report, Units::Metric);
+        assert!(metric.contains("Light snow, 20°C (feels like -3°C)"), "{}", metric);
+        assert!(metric.contains("wind 16 km/h NNE"), "{}", metric);
+        assert!(metric.contains("precipitation 25.4 mm"), "{}", metric);
+        let imperial = format(&report, Units::Imperial);
+        assert!(imperial.contains("Light snow, 68°F (feels like 27°F)"), "{}", imperial);
+        assert!(imperial.contains("wind 10 mph NNE"), "{}", imperial);
+        assert!(imperial.contains("precipitation 1.00 in"), "{}", imperial);
+        assert!(imperial.contains("21°F / 34°F"), "{}", imperial);
+    }
+}